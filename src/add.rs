@@ -0,0 +1,224 @@
+//! add subcommand: create a payment from a short text
+//!
+//! `add "cafe 480 starbucks yesterday #work"` is parsed into:
+//! - amount: the first number (`480`, `¥1,200` or `1200円`)
+//! - date: `today`, `yesterday`, weekday names, `YYYY-MM-DD` or `M/D` [default: today]
+//! - genre: the first word matching a genre name, case-insensitively or by prefix
+//! - comment: hashtags
+//! - place: the rest of words
+
+use crate::names::{name_key, Masters};
+use crate::output::{OutputFormat, Table};
+use crate::prompt::Prompt;
+use crate::session::Session;
+
+use zaim_cli::models::{Genre, NewPayment};
+
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde_json::json;
+
+#[derive(clap::Args, Debug)]
+pub struct AddArgs {
+    /// Account paying it (name or id) [default: defaults.account of configuration]
+    #[arg(long, value_name = "ACCOUNT")]
+    pub account: Option<String>,
+
+    /// Create without confirmation
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Text such as "cafe 480 starbucks yesterday #work"
+    #[arg(required = true, num_args = 1..)]
+    pub text: Vec<String>,
+}
+
+/// Payment parsed from a text
+#[derive(Debug, PartialEq)]
+struct QuickAdd {
+    date: NaiveDate,
+    amount: i64,
+    genre_id: u64,
+    place: Option<String>,
+    comment: Option<String>,
+}
+
+pub fn parse_amount(word: &str) -> Option<i64> {
+    let s = word.trim_start_matches(['¥', '￥']).trim_end_matches('円').replace(',', "");
+    s.parse().ok().filter(|a| *a > 0)
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let w = word.to_lowercase();
+    let days = [
+        ("monday", Weekday::Mon), ("tuesday", Weekday::Tue), ("wednesday", Weekday::Wed), ("thursday", Weekday::Thu),
+        ("friday", Weekday::Fri), ("saturday", Weekday::Sat), ("sunday", Weekday::Sun),
+    ];
+    // Abbreviations of 3 or more letters
+    days.iter().find(|(d, _)| w.len() >= 3 && d.starts_with(&w)).map(|(_, wd)| *wd)
+}
+
+/// Date of a keyword. Weekdays and M/D are the latest one on or before today
+pub fn parse_date(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let days_ago = |n| today.checked_sub_days(Days::new(n));
+    match word.to_lowercase().as_str() {
+        "today" | "今日" => return Some(today),
+        "yesterday" | "昨日" => return days_ago(1),
+        "おととい" | "一昨日" => return days_ago(2),
+        _ => (),
+    }
+    if let Some(wd) = parse_weekday(word) {
+        let n = (7 + today.weekday().num_days_from_monday() - wd.num_days_from_monday()) % 7;
+        return days_ago(n as u64);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Some(d);
+    }
+    let (m, d) = word.split_once('/')?;
+    let (m, d) = (m.parse().ok()?, d.parse().ok()?);
+    // February 29 may be 8 years ago at most
+    (0..=8).map(|n| today.year() - n)
+        .find_map(|y| NaiveDate::from_ymd_opt(y, m, d).filter(|date| *date <= today))
+}
+
+/// Genre of a word: exact name, or the only name starting with it, compared by `name_key`
+fn find_genre(word: &str, masters: &Masters) -> Result<Option<u64>> {
+    let word = name_key(word);
+    let genres: Vec<&Genre> = masters.genres.values().filter(|g| g.active.is_none_or(|a| a > 0)).collect();
+    let mut found: Vec<&Genre> = genres.iter().copied().filter(|g| name_key(&g.name) == word).collect();
+    if found.is_empty() && word.chars().count() >= 2 {
+        found = genres.iter().copied().filter(|g| name_key(&g.name).starts_with(&word)).collect();
+    }
+    found.sort_by_key(|g| g.id);
+    match found.as_slice() {
+        [] => Ok(None),
+        [g] => Ok(Some(g.id)),
+        _ => Err(anyhow!("ambiguous genre: {} ({})", word, found.iter()
+            .map(|g| format!("{}/{}", masters.category_name(Some(g.category_id)).unwrap_or("-"), g.name))
+            .collect::<Vec<_>>().join(", "))),
+    }
+}
+
+fn parse(text: &str, today: NaiveDate, masters: &Masters) -> Result<QuickAdd> {
+    let mut amount = None;
+    let mut date = None;
+    let mut genre_id = None;
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+
+    for word in text.split_whitespace() {
+        if word.len() > 1 && word.starts_with('#') {
+            tags.push(word);
+        } else if let Some(a) = amount.is_none().then(|| parse_amount(word)).flatten() {
+            amount = Some(a);
+        } else if let Some(d) = date.is_none().then(|| parse_date(word, today)).flatten() {
+            date = Some(d);
+        } else if let Some(g) = if genre_id.is_none() { find_genre(word, masters)? } else { None } {
+            genre_id = Some(g);
+        } else {
+            words.push(word);
+        }
+    }
+
+    let join = |v: Vec<&str>| (!v.is_empty()).then(|| v.join(" "));
+    Ok(QuickAdd {
+        date: date.unwrap_or(today),
+        amount: amount.ok_or_else(|| anyhow!("no amount in: {}", text))?,
+        genre_id: genre_id.ok_or_else(|| anyhow!("no genre in: {}", text))?,
+        place: join(words),
+        comment: join(tags),
+    })
+}
+
+pub fn run(args: &AddArgs, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let api = session.api()?;
+    let masters = Masters::fetch(&api)?;
+
+    let text = args.text.join(" ");
+    let parsed = parse(&text, session.defaults.today()?, &masters)?;
+    let account = args.account.as_deref().or(session.defaults.account.as_deref());
+    let payment = NewPayment {
+        date: parsed.date,
+        amount: parsed.amount,
+        category_id: masters.genres[&parsed.genre_id].category_id,
+        genre_id: parsed.genre_id,
+        from_account_id: account.map(|a| masters.account_id(a)).transpose()?,
+        name: None,
+        place: parsed.place,
+        comment: parsed.comment,
+    };
+
+    let mut table = Table::new(&["date", "amount", "category", "genre", "account", "place", "comment"]);
+    table.push(vec![
+        json!(payment.date), json!(payment.amount),
+        json!(masters.category_name(Some(payment.category_id))), json!(masters.genre_name(Some(payment.genre_id))),
+        json!(masters.account_name(payment.from_account_id)), json!(payment.place), json!(payment.comment),
+    ]);
+    table.print(format)?;
+
+    if !args.yes && !Prompt::new(std::io::stdin().lock(), std::io::stderr()).confirm("Create this payment?")? {
+        eprintln!("canceled");
+        return Ok(ExitCode::FAILURE);
+    }
+    let created = api.create_payment(&payment)?;
+    eprintln!("payment {} created", created.id);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_date, QuickAdd};
+    use crate::names::test_masters;
+
+    use chrono::NaiveDate;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_date() {
+        // Sunday
+        let today = date("2026-10-18");
+        assert_eq!(parse_date("Yesterday", today), Some(date("2026-10-17")));
+        assert_eq!(parse_date("一昨日", today), Some(date("2026-10-16")));
+        assert_eq!(parse_date("fri", today), Some(date("2026-10-16")));
+        assert_eq!(parse_date("sunday", today), Some(today));
+        assert_eq!(parse_date("10/1", today), Some(date("2026-10-01")));
+        assert_eq!(parse_date("12/25", today), Some(date("2025-12-25")));
+        assert_eq!(parse_date("10/18", today), Some(today));
+        assert_eq!(parse_date("2/29", today), Some(date("2024-02-29")));
+        assert_eq!(parse_date("2/30", today), None);
+        assert_eq!(parse_date("2026-09-30", today), Some(date("2026-09-30")));
+        assert_eq!(parse_date("starbucks", today), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let today = date("2026-10-18");
+        let masters = test_masters();
+        assert_eq!(parse("cafe 480 starbucks yesterday #work", today, &masters).unwrap(), QuickAdd {
+            date: date("2026-10-17"),
+            amount: 480,
+            genre_id: 10103,
+            place: Some(String::from("starbucks")),
+            comment: Some(String::from("#work")),
+        });
+        let parsed = parse("¥1,200 カフェ ドトール 渋谷", today, &masters).unwrap();
+        assert_eq!((parsed.date, parsed.amount, parsed.genre_id), (today, 1200, 10102));
+        assert_eq!(parsed.place.as_deref(), Some("ドトール 渋谷"));
+        assert_eq!(parse("gro 300", today, &masters).unwrap().genre_id, 10104);
+
+        assert_eq!(parse("cafe starbucks", today, &masters).unwrap_err().to_string(), "no amount in: cafe starbucks");
+        assert_eq!(
+            parse("din 3000", today, &masters).unwrap_err().to_string(),
+            "ambiguous genre: din (交際費/Dining out, 交際費/Dinner)"
+        );
+    }
+}
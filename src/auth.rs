@@ -0,0 +1,366 @@
+//! auth subcommands: login, status, logout and whoami
+
+use crate::config::Config;
+use crate::output::{OutputFormat, Table, print_json};
+use crate::session::Session;
+use crate::zaim_api::{User, ZaimApiError};
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use clap::Subcommand;
+use percent_encoding::percent_decode_str;
+use serde_json::json;
+
+const CALLBACK_PATH: &str = "/callback";
+/// Time to wait for the redirect after showing the url
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(600);
+/// Time to read a request from each connection
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(10);
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Subcommand, Debug)]
+pub enum AuthCommand {
+    /// Log in and save access tokens
+    Login {
+        /// Receive verifier code by a local http server instead of typing it
+        #[arg(long)]
+        loopback: bool,
+
+        /// Port of the local http server (0: any free port)
+        #[arg(long, default_value_t = 0, requires = "loopback")]
+        port: u16,
+    },
+
+    /// Check access tokens by /v2/home/user/verify
+    Status {
+        /// Check all profiles in configuration file
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Remove stored access tokens
+    Logout,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct AuthStatus {
+    profile: Option<String>,
+    logged_in: bool,
+    user: Option<User>,
+    error: Option<String>,
+}
+
+impl AuthStatus {
+    fn check(session: &Session) -> Self {
+        let mut status = Self {
+            profile: session.profile_name.clone(),
+            logged_in: false,
+            user: None,
+            error: None,
+        };
+
+        if !session.is_logged_in() {
+            status.error = Some(String::from("no access tokens"));
+            return status;
+        }
+
+        match session.api().and_then(|api| Ok(api.verify_user()?)) {
+            Ok(user) => {
+                status.logged_in = true;
+                status.user = Some(user);
+            },
+            Err(e) => status.error = Some(e.to_string()),
+        }
+
+        status
+    }
+
+    fn table_row(&self) -> Vec<serde_json::Value> {
+        let user = self.user.as_ref();
+        vec![
+            json!(self.profile.as_deref().unwrap_or("-")),
+            json!(if self.logged_in { "logged in" } else { "not logged in" }),
+            json!(user.map(|u| u.id)),
+            json!(user.map(|u| u.name.as_str())),
+            json!(self.error.as_deref().map(|e| e.replace('\n', " "))),
+        ]
+    }
+}
+
+fn status_table(statuses: &[AuthStatus]) -> Table {
+    let mut table = Table::new(&["profile", "status", "user_id", "name", "error"]);
+    for s in statuses {
+        table.push(s.table_row());
+    }
+    table
+}
+
+pub fn run(
+    command: &AuthCommand,
+    session: Result<Session>,
+    config: &Config,
+    format: OutputFormat
+) -> Result<ExitCode> {
+    match command {
+        AuthCommand::Login { loopback, port } => login(&session?, *loopback, *port, format),
+        AuthCommand::Status { all: true } => status_all(config, format),
+        AuthCommand::Status { all: false } => status(&session?, format),
+        AuthCommand::Logout => logout(&session?, format),
+    }
+}
+
+fn login(session: &Session, loopback: bool, port: u16, format: OutputFormat) -> Result<ExitCode> {
+    let mut api = session.api()?;
+
+    if loopback {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        api.oauth1.callback = api_callback_url(port);
+
+        api.authenticate_with(|auth_link, request_token| {
+            eprintln!("Please access following url by your web browser.\n  {}", auth_link);
+            eprintln!("Waiting for redirect to {} ...", api_callback_url(port));
+            wait_for_callback(&listener, &request_token.request_token, CALLBACK_TIMEOUT)
+                .map_err(|e| ZaimApiError::new(format!("Failed to receive verifier code: {}", e)))
+        })?;
+    } else {
+        api.access_tokens = None;
+        api.authenticate()?;
+    }
+
+    session.store_access_tokens(&api)?;
+
+    let user = api.verify_user()?;
+    let status = AuthStatus {
+        profile: session.profile_name.clone(),
+        logged_in: true,
+        user: Some(user.clone()),
+        error: None,
+    };
+    match format {
+        OutputFormat::Json => print_json(&status)?,
+        _ => println!("Logged in as {} ({})", user.name, user.id),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn api_callback_url(port: u16) -> String {
+    format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH)
+}
+
+fn status(session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let status = AuthStatus::check(session);
+    let logged_in = status.logged_in;
+
+    match format {
+        OutputFormat::Json => print_json(&status)?,
+        _ => status_table(&[status]).print(format)?,
+    }
+
+    Ok(if logged_in { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+fn status_all(config: &Config, format: OutputFormat) -> Result<ExitCode> {
+    let statuses: Vec<AuthStatus> = config.profiles.iter()
+        .map(|(name, profile)| match Session::for_profile(config, name, profile) {
+            Ok(s) => AuthStatus::check(&s),
+            Err(e) => AuthStatus {
+                profile: Some(name.clone()),
+                logged_in: false,
+                user: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Json => print_json(&statuses)?,
+        _ => status_table(&statuses).print(format)?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn logout(session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let removed = session.erase_access_tokens()?;
+
+    match format {
+        OutputFormat::Json => print_json(&json!({
+            "profile": session.profile_name,
+            "removed": removed,
+        }))?,
+        _ if removed => println!("Access tokens are removed"),
+        _ => println!("No stored access tokens"),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn whoami(session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let user = session.api()?.verify_user()?;
+
+    match format {
+        OutputFormat::Json => print_json(&user)?,
+        _ => {
+            let mut table = Table::new(&["field", "value"]);
+            if let serde_json::Value::Object(m) = serde_json::to_value(&user)? {
+                for (k, v) in m.into_iter().filter(|(_, v)| !v.is_null()) {
+                    table.push(vec![json!(k), v]);
+                }
+            }
+            table.print(format)?;
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Wait for redirect from Zaim and return verifier code. Connections which are broken,
+/// too slow or not the callback are skipped until the timeout
+fn wait_for_callback(listener: &TcpListener, request_token: &str, timeout: Duration) -> Result<String> {
+    let deadline = Instant::now() + timeout;
+    listener.set_nonblocking(true)?;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            bail!("timed out after {} seconds", timeout.as_secs());
+        }
+
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                if e.kind() != ErrorKind::WouldBlock {
+                    tracing::debug!(error = %e, "failed to accept connection");
+                }
+                std::thread::sleep(remaining.min(ACCEPT_INTERVAL));
+                continue;
+            },
+        };
+        match handle_callback(stream, request_token, remaining.min(CALLBACK_READ_TIMEOUT)) {
+            Ok(Some(verifier)) => return Ok(verifier),
+            Ok(None) => (),
+            Err(e) => tracing::debug!(error = %e, "ignoring connection"),
+        }
+    }
+}
+
+/// Respond to a request, and return verifier code if it is the callback
+fn handle_callback(mut stream: TcpStream, request_token: &str, timeout: Duration) -> Result<Option<String>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let target = read_request_target(&stream)?;
+
+    match parse_callback(&target) {
+        Some((token, verifier)) if token == request_token => {
+            // Verifier is received even if the browser has gone
+            if let Err(e) = respond(&mut stream, "200 OK", "Logged in to Zaim. You can close this window.") {
+                tracing::debug!(error = %e, "failed to respond to callback");
+            }
+            return Ok(Some(verifier));
+        },
+        Some(_) => respond(&mut stream, "400 Bad Request", "Unexpected oauth_token")?,
+        None => respond(&mut stream, "404 Not Found", "Not Found")?,
+    }
+    Ok(None)
+}
+
+fn read_request_target(stream: &TcpStream) -> Result<String> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        line.clear();
+    }
+
+    Ok(request_line.split_whitespace().nth(1).unwrap_or("").to_string())
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )?;
+    Ok(())
+}
+
+/// Parse "/callback?oauth_token=...&oauth_verifier=..." into (token, verifier)
+fn parse_callback(target: &str) -> Option<(String, String)> {
+    let (path, query) = target.split_once('?')?;
+    if path != CALLBACK_PATH {
+        return None;
+    }
+
+    let params: HashMap<String, String> = query.split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), percent_decode_str(v).decode_utf8_lossy().to_string()))
+        .collect();
+
+    Some((params.get("oauth_token")?.clone(), params.get("oauth_verifier")?.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_callback, wait_for_callback};
+
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_callback() {
+        assert_eq!(
+            parse_callback("/callback?oauth_token=abc&oauth_verifier=x%2By"),
+            Some((String::from("abc"), String::from("x+y")))
+        );
+        assert_eq!(parse_callback("/favicon.ico"), None);
+        assert_eq!(parse_callback("/callback?oauth_token=abc"), None);
+    }
+
+    #[test]
+    fn test_wait_for_callback() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            // Closed without a request, and a request other than the callback
+            drop(TcpStream::connect(addr).unwrap());
+            let mut other = TcpStream::connect(addr).unwrap();
+            other.write_all(b"GET /favicon.ico HTTP/1.1\r\n\r\n").unwrap();
+            let mut response = String::new();
+            other.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 404"));
+
+            let mut callback = TcpStream::connect(addr).unwrap();
+            callback.write_all(b"GET /callback?oauth_token=abc&oauth_verifier=v HTTP/1.1\r\nHost: x\r\n\r\n").unwrap();
+            let mut response = String::new();
+            callback.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        assert_eq!(wait_for_callback(&listener, "abc", Duration::from_secs(10)).unwrap(), "v");
+        assert!(client.join().unwrap().starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn test_wait_for_callback_timeout() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        // Connection sending nothing does not block beyond the timeout
+        let _silent = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let error = wait_for_callback(&listener, "abc", Duration::from_secs(1)).unwrap_err();
+        assert_eq!(error.to_string(), "timed out after 1 seconds");
+    }
+}
@@ -0,0 +1,255 @@
+//! budget subcommand: compare spending with local budgets
+//!
+//! Budgets are monthly limits of payment categories or genres.
+//! With rollover, the remainder (or overspend) of the previous month is carried.
+//! Only one month is carried, so that what the previous month carried from its
+//! former month is not carried again.
+//!
+//! ```toml
+//! [[budgets]]
+//! category = "食費"
+//! limit = 40000
+//!
+//! [[budgets]]
+//! genre = "カフェ"
+//! limit = 5000
+//! rollover = true
+//! ```
+
+use crate::config;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery};
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde_json::json;
+
+const BUDGETS_FILE: &str = "budgets.toml";
+
+/// Exit status when a budget is exceeded, distinct from 1 of errors and 2 of usage errors
+const EXIT_EXCEEDED: u8 = 3;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum BudgetCommand {
+    /// Show spending against budgets. Exits with status 3 when a budget is exceeded
+    Status(StatusArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatusArgs {
+    /// Budgets file [default: $XDG_CONFIG_HOME/zaim-cli/budgets.toml]
+    #[arg(long, value_name = "FILE")]
+    pub budgets: Option<PathBuf>,
+
+    /// Month to check (YYYY-MM) [default: this month]
+    #[arg(long, value_name = "MONTH", value_parser = parse_month)]
+    pub month: Option<NaiveDate>,
+}
+
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d").map_err(|_| format!("invalid month: {}", s))
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetsFile {
+    #[serde(default)]
+    pub budgets: Vec<BudgetSpec>,
+}
+
+/// Monthly limit of a category or genre. Names or ids
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetSpec {
+    pub category: Option<String>,
+    pub genre: Option<String>,
+    pub limit: i64,
+    #[serde(default)]
+    pub rollover: bool,
+}
+
+impl BudgetsFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        config::load_toml(path)
+    }
+}
+
+#[derive(Debug)]
+struct Budget {
+    category_id: u64,
+    genre_id: Option<u64>,
+    limit: i64,
+    rollover: bool,
+}
+
+impl Budget {
+    fn resolve(spec: &BudgetSpec, masters: &Masters) -> Result<Self> {
+        let category_id = spec.category.as_deref().map(|c| masters.category_id(Mode::Payment, c)).transpose()?;
+        let (category_id, genre_id) = match (category_id, spec.genre.as_deref()) {
+            (category_id, Some(g)) => {
+                let genre_id = masters.genre_id(category_id, g)?;
+                (masters.genres[&genre_id].category_id, Some(genre_id))
+            },
+            (Some(c), None) => (c, None),
+            (None, None) => return Err(anyhow!("category or genre is required")),
+        };
+        Ok(Self { category_id, genre_id, limit: spec.limit, rollover: spec.rollover })
+    }
+
+    fn spent(&self, money: &[Money], month: NaiveDate) -> i64 {
+        money.iter()
+            .filter(|m| m.mode == Mode::Payment && m.date.with_day(1) == Some(month))
+            .filter(|m| m.category_id == Some(self.category_id) && self.genre_id.is_none_or(|g| m.genre_id == Some(g)))
+            .map(|m| m.amount)
+            .sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Ok,
+    /// Projected month-end spend is over the budget
+    Warning,
+    Exceeded,
+}
+
+impl State {
+    fn as_str(&self) -> &'static str {
+        match self {
+            State::Ok => "ok",
+            State::Warning => "warning",
+            State::Exceeded => "exceeded",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Status {
+    carried: i64,
+    available: i64,
+    spent: i64,
+    /// Percentage of available
+    used: Option<f64>,
+    projected: i64,
+    state: State,
+}
+
+/// Status of the budget in `month` as of `today`
+fn status(budget: &Budget, money: &[Money], month: NaiveDate, today: NaiveDate) -> Status {
+    let carried = match budget.rollover {
+        true => budget.limit - budget.spent(money, month - Months::new(1)),
+        false => 0,
+    };
+    let available = budget.limit + carried;
+    let spent = budget.spent(money, month);
+
+    let next_month = month + Months::new(1);
+    let projected = if today >= month && today < next_month {
+        let days = (next_month - month).num_days();
+        (spent as f64 / today.day() as f64 * days as f64).round() as i64
+    } else {
+        spent
+    };
+    let state = if spent > available {
+        State::Exceeded
+    } else if projected > available {
+        State::Warning
+    } else {
+        State::Ok
+    };
+
+    Status {
+        carried,
+        available,
+        spent,
+        used: (available > 0).then(|| (spent as f64 / available as f64 * 1000.0).round() / 10.0),
+        projected,
+        state,
+    }
+}
+
+pub fn run(command: &BudgetCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let BudgetCommand::Status(args) = command;
+    let path = match &args.budgets {
+        Some(p) => p.clone(),
+        None => config::config_dir()?.join(BUDGETS_FILE),
+    };
+    let file = BudgetsFile::load(&path)?;
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+
+    let api = session.api()?;
+    let masters = Masters::fetch(&api)?;
+    let budgets = file.budgets.iter().enumerate()
+        .map(|(i, b)| Budget::resolve(b, &masters).map_err(|e| anyhow!("budget {}: {}", i + 1, e)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let today = session.defaults.today()?;
+    let month = args.month.unwrap_or(today.with_day(1).unwrap());
+    let rollover = budgets.iter().any(|b| b.rollover);
+    let money = api.money(&MoneyQuery {
+        mode: Some(Mode::Payment),
+        start_date: Some(if rollover { month - Months::new(1) } else { month }),
+        end_date: Some(month + Months::new(1) - Days::new(1)),
+        ..Default::default()
+    })?;
+
+    let mut table = Table::new(&[
+        "category", "genre", "limit", "carried", "available", "spent", "used", "projected", "status",
+    ]);
+    let mut exceeded = false;
+    for b in budgets.iter() {
+        let s = status(b, &money, month, today);
+        exceeded |= s.state == State::Exceeded;
+        table.push(vec![
+            json!(masters.category_name(Some(b.category_id))), json!(masters.genre_name(b.genre_id)),
+            json!(b.limit), json!(s.carried), json!(s.available), json!(s.spent), json!(s.used),
+            json!(s.projected), json!(s.state.as_str()),
+        ]);
+    }
+    table.print(format)?;
+
+    Ok(if exceeded { ExitCode::from(EXIT_EXCEEDED) } else { ExitCode::SUCCESS })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_month, status, Budget, State};
+    use zaim_cli::models::Money;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_status() {
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-10", "amount": 8000, "category_id": 101, "genre_id": 10101},
+            {"id": 2, "mode": "payment", "date": "2026-02-03", "amount": 3000, "category_id": 101, "genre_id": 10102},
+            {"id": 3, "mode": "payment", "date": "2026-02-05", "amount": 2000, "category_id": 101, "genre_id": 10101},
+            {"id": 4, "mode": "payment", "date": "2026-02-05", "amount": 9000, "category_id": 102},
+        ])).unwrap();
+        let month = parse_month("2026-02").unwrap();
+        let today = "2026-02-14".parse().unwrap();
+
+        let food = Budget { category_id: 101, genre_id: None, limit: 10000, rollover: false };
+        let s = status(&food, &money, month, today);
+        assert_eq!((s.spent, s.used, s.projected, s.state), (5000, Some(50.0), 10000, State::Ok));
+
+        let rollover = Budget { rollover: true, ..food };
+        let s = status(&rollover, &money, month, today);
+        assert_eq!((s.carried, s.available, s.state), (2000, 12000, State::Ok));
+
+        let cafe = Budget { category_id: 101, genre_id: Some(10102), limit: 5000, rollover: false };
+        assert_eq!(status(&cafe, &money, month, today).state, State::Warning);
+        assert_eq!(status(&cafe, &money, month, "2026-03-01".parse().unwrap()).state, State::Ok);
+
+        let other = Budget { category_id: 102, genre_id: None, limit: 5000, rollover: false };
+        assert_eq!(status(&other, &money, month, today).state, State::Exceeded);
+    }
+}
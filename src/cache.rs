@@ -0,0 +1,72 @@
+//! cache subcommand: show or clear cached master data of the profile
+
+use crate::config;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::master_cache::MasterCache;
+
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use serde_json::json;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum CacheCommand {
+    /// List cached categories, genres, accounts and currencies
+    Show,
+    /// Remove cached master data
+    Clear {
+        /// Remove caches of all profiles
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+fn cache_of(session: &Session) -> Result<&MasterCache> {
+    session.cache.as_ref().ok_or_else(|| anyhow!("cache is disabled by cache.ttl_secs = 0"))
+}
+
+fn show(cache: &MasterCache) -> Table {
+    let mut table = Table::new(&["kind", "items", "updated", "state", "path"]);
+    for e in cache.entries() {
+        table.push(vec![
+            json!(e.kind),
+            json!(e.items),
+            json!(DateTime::<Local>::from(e.modified).format("%Y-%m-%d %H:%M:%S").to_string()),
+            json!(if e.fresh { "fresh" } else { "expired" }),
+            json!(e.path.display().to_string()),
+        ]);
+    }
+    table
+}
+
+pub fn run(command: &CacheCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        CacheCommand::Show => {
+            let cache = cache_of(session)?;
+            let table = show(cache);
+            if table.rows.is_empty() {
+                eprintln!("no master data cached in {}", cache.dir.display());
+            }
+            table.print(format)?;
+        },
+        CacheCommand::Clear { all: true } => {
+            let dir = config::cache_dir()?;
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)
+                    .map_err(|e| anyhow!("failed to remove {}\n{}", dir.display(), e))?;
+            }
+            eprintln!("removed {}", dir.display());
+        },
+        CacheCommand::Clear { all: false } => {
+            let cache = cache_of(session)?;
+            let removed = cache.clear()
+                .map_err(|e| anyhow!("failed to clear {}\n{}", cache.dir.display(), e))?;
+            eprintln!("{} cache files removed from {}", removed, cache.dir.display());
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
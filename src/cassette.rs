@@ -0,0 +1,261 @@
+//! Record and replay of http interactions for reproducible tests
+//!
+//! A cassette is a json file of request/response pairs.
+//! Authorization header is not recorded, so that nonce, timestamp, signature
+//! and tokens never appear in cassettes. Token values in responses of token
+//! endpoints are also replaced by "***".
+//! Requests are matched by method, url (including query string) and body.
+
+use crate::transport::{HttpResponse, Transport};
+use crate::zaim_api::{PreparedRequest, ZaimApiError};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Result};
+
+/// Keys in responses of token endpoints which must not be recorded
+const SECRET_RESPONSE_KEYS: [&str; 2] = ["oauth_token", "oauth_token_secret"];
+
+/// Response headers which must not be recorded
+const SECRET_HEADERS: [&str; 1] = ["set-cookie"];
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+/// Transport which records interactions sent by another transport,
+/// or replays them without network.
+pub struct Cassette {
+    /// File to save recorded interactions. None for in-memory cassettes.
+    path: Option<PathBuf>,
+    /// Transport to send requests while recording. None for replay.
+    inner: Option<Arc<dyn Transport>>,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Send requests by `inner` and record them into `path`. Existing file is overwritten.
+    pub fn record(path: &Path, inner: Arc<dyn Transport>) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+            inner: Some(inner),
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load interactions from `path` to replay them
+    pub fn replay(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read cassette {}\n{}", path.display(), e))?;
+        let file: CassetteFile = serde_json::from_str(&data)
+            .map_err(|e| anyhow!("failed to parse cassette {}\n{}", path.display(), e))?;
+
+        let mut cassette = Self::from_interactions(file.interactions);
+        cassette.path = Some(path.to_path_buf());
+        Ok(cassette)
+    }
+
+    /// Replay the given interactions, e.g. written in tests
+    pub fn from_interactions(interactions: Vec<Interaction>) -> Self {
+        Self {
+            path: None,
+            inner: None,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        }
+    }
+
+    /// Find the first unused interaction matching the request.
+    /// Each interaction is replayed only once, so that repeated requests
+    /// must be recorded as many times as they are sent.
+    fn find(&self, request: &PreparedRequest) -> Result<RecordedResponse> {
+        let key = RecordedRequest::from(request);
+        let mut interactions = self.interactions.lock().unwrap();
+
+        match interactions.iter_mut().find(|(i, used)| !used && i.request == key) {
+            Some((i, used)) => {
+                *used = true;
+                Ok(i.response.clone())
+            },
+            None => bail!("no recorded interaction for {} {}", key.method, key.url),
+        }
+    }
+
+    /// Append an interaction and save the cassette
+    fn push(&self, request: &PreparedRequest, response: &HttpResponse) -> Result<()> {
+        let headers = response.headers.iter()
+            .map(|(k, v)| (k.to_lowercase(), v.clone()))
+            .filter(|(k, _)| !SECRET_HEADERS.contains(&k.as_str()))
+            .collect();
+        let interaction = Interaction {
+            request: RecordedRequest::from(request),
+            response: RecordedResponse {
+                status: response.status,
+                headers,
+                body: redact_token_response(&response.body),
+            },
+        };
+
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push((interaction, true));
+
+        if let Some(path) = &self.path {
+            let file = CassetteFile {
+                interactions: interactions.iter().map(|(i, _)| i.clone()).collect(),
+            };
+            std::fs::write(path, serde_json::to_string_pretty(&file)? + "\n")
+                .map_err(|e| anyhow!("failed to save cassette {}\n{}", path.display(), e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Transport for Cassette {
+    fn send(&self, request: &PreparedRequest) -> Result<HttpResponse, ZaimApiError> {
+        let inner = match &self.inner {
+            Some(t) => t,
+            None => {
+                return match self.find(request) {
+                    Ok(r) => Ok(HttpResponse {
+                        status: r.status,
+                        headers: r.headers.into_iter().collect(),
+                        body: r.body,
+                    }),
+                    Err(e) => Err(ZaimApiError::new(format!("cassette Error: {}", e))),
+                };
+            },
+        };
+
+        let response = inner.send(request)?;
+        if let Err(e) = self.push(request, &response) {
+            return Err(ZaimApiError::new(format!("cassette Error: {}", e)));
+        }
+        Ok(response)
+    }
+}
+
+impl From<&PreparedRequest> for RecordedRequest {
+    fn from(request: &PreparedRequest) -> Self {
+        Self {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            body: request.body.clone(),
+        }
+    }
+}
+
+/// Replace token values in a form encoded response (e.g. "oauth_token=...&oauth_token_secret=...")
+fn redact_token_response(body: &str) -> String {
+    if !body.split('&').any(|kv| matches!(kv.split_once('='), Some((k, _)) if SECRET_RESPONSE_KEYS.contains(&k))) {
+        return body.to_string();
+    }
+
+    body.split('&').map(|kv| match kv.split_once('=') {
+        Some((k, _)) if SECRET_RESPONSE_KEYS.contains(&k) => format!("{}=***", k),
+        _ => kv.to_string(),
+    }).collect::<Vec<_>>().join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cassette, Interaction, RecordedRequest, RecordedResponse, redact_token_response};
+    use crate::transport::{FakeTransport, HttpResponse, Transport};
+    use crate::zaim_api::{AccessTokens, ConsumerInfo, PreparedRequest, ZaimApi};
+
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    fn verify_interaction() -> Interaction {
+        Interaction {
+            request: RecordedRequest {
+                method: String::from("GET"),
+                url: String::from("https://api.zaim.net/v2/home/user/verify"),
+                body: None,
+            },
+            response: RecordedResponse {
+                status: 200,
+                headers: BTreeMap::new(),
+                body: String::from(r#"{"me": {"id": 1, "login": "taro", "name": "Taro"}}"#),
+            },
+        }
+    }
+
+    #[test]
+    fn test_cassette_replay() {
+        let cassette = Cassette::from_interactions(vec![verify_interaction()]);
+        let request = PreparedRequest::new(
+            "https://api.zaim.net/v2/home/user/verify", "GET", "OAuth oauth_nonce=\"1\"", None
+        ).unwrap();
+
+        assert_eq!(cassette.send(&request).unwrap().status, 200);
+        // Each interaction is replayed once
+        assert!(cassette.send(&request).is_err());
+    }
+
+    #[test]
+    fn test_cassette_record() {
+        let fake = Arc::new(FakeTransport::new());
+        fake.push_response(HttpResponse {
+            status: 200,
+            headers: vec![(String::from("set-cookie"), String::from("session=abc"))],
+            body: String::from("oauth_token=abc&oauth_token_secret=def"),
+        });
+        let cassette = Cassette { path: None, inner: Some(fake), interactions: Mutex::new(Vec::new()) };
+        let request = PreparedRequest::new(
+            "https://api.zaim.net/v2/auth/access", "POST", "OAuth oauth_nonce=\"1\"", None
+        ).unwrap();
+
+        // Recorded response is redacted, but the response itself is not
+        assert_eq!(cassette.send(&request).unwrap().body, "oauth_token=abc&oauth_token_secret=def");
+        let interactions = cassette.interactions.lock().unwrap();
+        assert_eq!(interactions[0].0.response.body, "oauth_token=***&oauth_token_secret=***");
+        assert!(interactions[0].0.response.headers.is_empty());
+    }
+
+    #[test]
+    fn test_redact_token_response() {
+        assert_eq!(
+            redact_token_response("oauth_token=abc&oauth_token_secret=def&oauth_callback_confirmed=true"),
+            "oauth_token=***&oauth_token_secret=***&oauth_callback_confirmed=true"
+        );
+        assert_eq!(redact_token_response(r#"{"a": "b=c"}"#), r#"{"a": "b=c"}"#);
+    }
+
+    #[test]
+    fn test_replay_zaim_api() {
+        let mut api = ZaimApi::with_consumer_info(
+            ConsumerInfo { consumer_key: String::from("key"), consumer_secret: String::from("secret") },
+            Some(AccessTokens { access_token: String::from("token"), access_token_secret: String::from("secret") })
+        );
+        api.transport = Arc::new(Cassette::from_interactions(vec![verify_interaction()]));
+
+        let user = api.verify_user().unwrap();
+        assert_eq!(user.id, 1);
+        assert_eq!(user.name, "Taro");
+    }
+}
@@ -0,0 +1,826 @@
+//! Configuration files and named profiles
+//!
+//! Settings are taken in following order (former wins).
+//! 1. Command line options
+//! 2. Environment variables
+//! 3. Project file (`.zaim-cli.toml` in current or ancestor directory).
+//!    It may set only `default_profile`, `defaults`, `aliases`, `retry` and `profiles.<name>.defaults`
+//! 4. User file (`$XDG_CONFIG_HOME/zaim-cli/config.toml`)
+//!
+//! ```toml
+//! default_profile = "personal"
+//!
+//! [credential]
+//! helper = "pass-zaim"
+//!
+//! [defaults]
+//! output = "json"
+//! timezone = "Asia/Tokyo"
+//! currency = "JPY"
+//! account = "1"
+//!
+//! [retry]
+//! max_attempts = 3
+//! backoff_ms = 500
+//!
+//! [cache]
+//! ttl_secs = 86400
+//!
+//! [network]
+//! proxy = "http://proxy.example.com:8080"
+//! no_proxy = "localhost,.example.com"
+//! ca_certificates = ["/path/to/corporate_ca.pem"]
+//! connect_timeout_secs = 10
+//! timeout_secs = 60
+//! user_agent = "zaim-cli"
+//!
+//! [aliases]
+//! st = "auth status --all"
+//!
+//! [profiles.personal]
+//! consumer_info = "/path/to/consumer_info.json"
+//!
+//! [profiles.household]
+//! consumer_info = "/path/to/household_consumer_info.json"
+//! access_token = "/path/to/household_access_tokens.json"
+//! api_base = "https://api.zaim.net/v2"
+//!
+//! [profiles.household.defaults]
+//! account = "2"
+//! ```
+
+use crate::output::{OutputFormat, Table, print_json};
+use crate::transport::NetworkOptions;
+use crate::zaim_api::RetryPolicy;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+
+pub const ENV_CONFIG: &str = "ZAIM_CONFIG";
+pub const ENV_PROFILE: &str = "ZAIM_PROFILE";
+pub const ENV_OUTPUT: &str = "ZAIM_OUTPUT";
+pub const ENV_TIMEZONE: &str = "ZAIM_TIMEZONE";
+pub const ENV_CURRENCY: &str = "ZAIM_CURRENCY";
+pub const ENV_ACCOUNT: &str = "ZAIM_ACCOUNT";
+pub const DEFAULT_PROFILE: &str = "default";
+pub const PROJECT_CONFIG_FILE: &str = ".zaim-cli.toml";
+const APP_DIR: &str = "zaim-cli";
+const CONFIG_FILE: &str = "config.toml";
+const ACCESS_TOKEN_FILE: &str = "access_tokens.json";
+
+/// Top level keys allowed in project files. Others such as credentials, `api_base` and `network`
+/// could run commands or send tokens elsewhere, so that they are taken only from the user file
+const PROJECT_KEYS: [&str; 4] = ["default_profile", "defaults", "aliases", "retry"];
+
+/// Subcommands which aliases of project files may run, for the same reason.
+/// An alias starts with one of them
+const PROJECT_ALIAS_COMMANDS: [&str; 15] = [
+    "auth status", "whoami", "profile list", "sync", "query", "export", "import", "apply-rules", "add", "money",
+    "report", "budget", "recurring", "tui", "cache show",
+];
+
+/// Options which aliases of project files may use. Options for credentials, network, raw requests
+/// (e.g. `--uri`) and files to write (e.g. `--db`, `--state`) are not allowed
+const PROJECT_ALIAS_OPTIONS: [&str; 41] = [
+    "-o", "--output", "-v", "--verbose", "--dry-run", "--refresh", "--timeout", "--connect-timeout",
+    "--all", "--full", "--lookback-days", "--start-date", "--end-date", "--format", "--mapping", "--mode",
+    "--category", "--genre", "--profile", "--commit", "--rules", "--account", "-y", "--yes", "-i", "--interactive",
+    "--date", "--amount", "--from-account", "--to-account", "--place", "--comment", "--max-days", "--min-score",
+    "--decisions", "--year", "--from", "--to", "--budgets", "--month", "--file",
+];
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Profile used when neither --profile nor ZAIM_PROFILE is given
+    pub default_profile: Option<String>,
+
+    /// Credentials used when the profile does not specify them
+    #[serde(default)]
+    pub credential: CredentialConfig,
+
+    #[serde(default)]
+    pub defaults: Defaults,
+
+    #[serde(default)]
+    pub retry: RetryPolicy,
+
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    #[serde(default)]
+    pub network: NetworkOptions,
+
+    /// Command aliases (e.g. `st = "auth status --all"`)
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialConfig {
+    /// File path of consumer information (json)
+    pub consumer_info: Option<PathBuf>,
+
+    /// File path of access tokens (json)
+    pub access_token: Option<PathBuf>,
+
+    /// External credential helper
+    pub helper: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// File path of consumer information (json)
+    pub consumer_info: Option<PathBuf>,
+
+    /// File path of access tokens (json).
+    /// If omitted, tokens are kept in the profile directory.
+    pub access_token: Option<PathBuf>,
+
+    /// External credential helper
+    pub credential_helper: Option<String>,
+
+    /// Base of Zaim Rest API (e.g. "https://api.zaim.net/v2")
+    pub api_base: Option<String>,
+
+    #[serde(default)]
+    pub defaults: Defaults,
+}
+
+/// Cache of master data
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct CacheConfig {
+    /// Seconds to use cached categories, genres, accounts and currencies (0: no cache)
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 24 * 60 * 60 }
+    }
+}
+
+/// Default values for commands
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    /// Output format of subcommands
+    pub output: Option<OutputFormat>,
+
+    /// Timezone to decide dates such as "today" (e.g. "Asia/Tokyo")
+    pub timezone: Option<String>,
+
+    /// Currency code of exported records without one (e.g. "JPY")
+    pub currency: Option<String>,
+
+    /// Account (name or id) paying payments, receiving incomes and sending transfers
+    /// created without accounts
+    pub account: Option<String>,
+}
+
+impl Defaults {
+    /// Fill unset values by `base`
+    fn or(self, base: &Defaults) -> Self {
+        Self {
+            output: self.output.or(base.output),
+            timezone: self.timezone.or_else(|| base.timezone.clone()),
+            currency: self.currency.or_else(|| base.currency.clone()),
+            account: self.account.or_else(|| base.account.clone()),
+        }
+    }
+
+    /// Parsed `timezone`, None for the local timezone
+    pub fn tz(&self) -> Result<Option<Tz>> {
+        self.timezone.as_deref()
+            .map(|t| t.parse::<Tz>().map_err(|e| anyhow!("invalid timezone {}: {}", t, e)))
+            .transpose()
+    }
+
+    /// Current time in `timezone`
+    pub fn now(&self) -> Result<DateTime<FixedOffset>> {
+        Ok(match self.tz()? {
+            Some(tz) => Utc::now().with_timezone(&tz).fixed_offset(),
+            None => Local::now().fixed_offset(),
+        })
+    }
+
+    /// Today in `timezone`, for dates such as "today" and the current month
+    pub fn today(&self) -> Result<NaiveDate> {
+        Ok(self.now()?.date_naive())
+    }
+
+    fn from_env() -> Result<Self> {
+        let var = |k: &str| std::env::var(k).ok().filter(|v| !v.is_empty());
+        let output = match var(ENV_OUTPUT) {
+            Some(v) => Some(<OutputFormat as clap::ValueEnum>::from_str(&v, true)
+                .map_err(|e| anyhow!("invalid {}: {}", ENV_OUTPUT, e))?),
+            None => None,
+        };
+
+        Ok(Self {
+            output,
+            timezone: var(ENV_TIMEZONE),
+            currency: var(ENV_CURRENCY),
+            account: var(ENV_ACCOUNT),
+        })
+    }
+}
+
+/// Directory for configuration, e.g. `$XDG_CONFIG_HOME/zaim-cli`
+pub fn config_dir() -> Result<PathBuf> {
+    dirs::config_dir()
+        .map(|d| d.join(APP_DIR))
+        .ok_or_else(|| anyhow!("failed to find configuration directory"))
+}
+
+/// Directory for state of commands, e.g. `$XDG_DATA_HOME/zaim-cli`
+pub fn data_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|d| d.join(APP_DIR))
+        .ok_or_else(|| anyhow!("failed to find data directory"))
+}
+
+/// Read a toml file given to commands, such as rules and budgets
+pub fn load_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read {}\n{}", path.display(), e))?;
+    toml::from_str(&data).map_err(|e| anyhow!("failed to parse {}\n{}", path.display(), e))
+}
+
+/// Read state of a command in json, or the default one if the file does not exist
+pub fn load_json<T: serde::de::DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data).map_err(|e| anyhow!("failed to parse {}\n{}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(anyhow!("failed to read {}\n{}", path.display(), e)),
+    }
+}
+
+/// Write state of a command in json, creating its directory
+pub fn save_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)?)
+        .map_err(|e| anyhow!("failed to write {}\n{}", path.display(), e))
+}
+
+/// Directory for caches, e.g. `$XDG_CACHE_HOME/zaim-cli`
+pub fn cache_dir() -> Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|d| d.join(APP_DIR))
+        .ok_or_else(|| anyhow!("failed to find cache directory"))
+}
+
+pub fn default_config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join(CONFIG_FILE))
+}
+
+/// Find project file in current directory or its ancestors
+pub fn find_project_config() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|d| d.join(PROJECT_CONFIG_FILE))
+        .find(|p| p.is_file())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    User,
+    Project,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::User => write!(f, "user"),
+            Scope::Project => write!(f, "project"),
+        }
+    }
+}
+
+pub struct ConfigFile {
+    pub scope: Scope,
+    pub path: PathBuf,
+    pub table: toml::Table,
+}
+
+/// Configuration files in ascending priority
+pub struct ConfigFiles {
+    pub files: Vec<ConfigFile>,
+}
+
+impl ConfigFiles {
+    /// Load user file and project file.
+    /// If user_path is None, the default path is used and a missing file is treated as empty.
+    pub fn load(user_path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match user_path {
+            Some(p) => (p.to_path_buf(), true),
+            None => (default_config_path()?, false),
+        };
+
+        let mut files = Vec::new();
+        if path.exists() {
+            files.push(ConfigFile { scope: Scope::User, table: read_table(&path, Scope::User)?, path });
+        } else if required {
+            bail!("{} not found", path.display());
+        }
+
+        if let Some(path) = find_project_config() {
+            files.push(ConfigFile { scope: Scope::Project, table: read_table(&path, Scope::Project)?, path });
+        }
+
+        Ok(Self { files })
+    }
+
+    pub fn merged(&self) -> toml::Table {
+        let mut merged = toml::Table::new();
+        for f in self.files.iter() {
+            merge_table(&mut merged, &f.table);
+        }
+        merged
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        Ok(toml::Value::Table(self.merged()).try_into()?)
+    }
+
+    /// File which decides the value of key
+    pub fn origin(&self, key: &str) -> Option<&ConfigFile> {
+        self.files.iter().rev()
+            .find(|f| lookup(&f.table, key).is_some())
+    }
+}
+
+/// Error if a project file sets keys other than `PROJECT_KEYS` and `profiles.<name>.defaults`
+fn check_project_table(table: &toml::Table) -> Result<()> {
+    for (key, value) in table.iter() {
+        if key == "profiles" {
+            for (name, profile) in value.as_table().into_iter().flatten() {
+                if let Some(k) = profile.as_table().into_iter().flatten().map(|(k, _)| k).find(|k| *k != "defaults") {
+                    bail!("profiles.{}.{} is not allowed in project file. Set it in the user file", name, k);
+                }
+            }
+        } else if !PROJECT_KEYS.contains(&key.as_str()) {
+            bail!("{} is not allowed in project file. Set it in the user file", key);
+        }
+    }
+
+    for (name, alias) in table.get("aliases").and_then(|a| a.as_table()).into_iter().flatten() {
+        check_project_alias(alias.as_str().unwrap_or_default())
+            .map_err(|e| anyhow!("alias {} in project file {}", name, e))?;
+    }
+
+    Ok(())
+}
+
+/// Error if an alias runs a subcommand or uses an option not allowed in project files
+fn check_project_alias(alias: &str) -> Result<()> {
+    let words: Vec<&str> = alias.split_whitespace().collect();
+    let allowed = PROJECT_ALIAS_COMMANDS.iter()
+        .any(|c| c.split(' ').enumerate().all(|(i, w)| words.get(i) == Some(&w)));
+    if !allowed {
+        bail!("cannot run {}", alias);
+    }
+
+    for w in words.iter().filter(|w| w.starts_with('-')) {
+        let option = w.split_once('=').map_or(*w, |(o, _)| o);
+        // Short options may be combined (e.g. -vv)
+        let shorts = option.strip_prefix('-').filter(|o| !o.starts_with('-') && !o.is_empty());
+        let ok = match shorts {
+            Some(s) => s.chars().all(|c| PROJECT_ALIAS_OPTIONS.contains(&format!("-{}", c).as_str())),
+            None => PROJECT_ALIAS_OPTIONS.contains(&option),
+        };
+        if !ok {
+            bail!("cannot use {}", w);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_table(path: &Path, scope: Scope) -> Result<toml::Table> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read {}\n{}", path.display(), e))?;
+    let table: toml::Table = data.parse()
+        .map_err(|e| anyhow!("failed to parse {}\n{}", path.display(), e))?;
+
+    // Validate each file on its own for better error messages
+    toml::Value::Table(table.clone()).try_into::<Config>()
+        .map_err(|e| anyhow!("invalid configuration in {}\n{}", path.display(), e))?;
+    if scope == Scope::Project {
+        check_project_table(&table).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    }
+
+    Ok(table)
+}
+
+/// Merge tables recursively. Values other than tables are replaced.
+pub fn merge_table(base: &mut toml::Table, other: &toml::Table) {
+    for (k, v) in other.iter() {
+        match (base.get_mut(k), v) {
+            (Some(toml::Value::Table(b)), toml::Value::Table(o)) => merge_table(b, o),
+            _ => {
+                base.insert(k.clone(), v.clone());
+            },
+        }
+    }
+}
+
+/// Look up dotted key (e.g. "defaults.output")
+pub fn lookup<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut segments = key.split('.');
+    let mut value = table.get(segments.next()?)?;
+    for seg in segments {
+        value = value.as_table()?.get(seg)?;
+    }
+    Some(value)
+}
+
+/// Flatten into dotted keys and values
+pub fn flatten(table: &toml::Table) -> Vec<(String, toml::Value)> {
+    let mut items = Vec::new();
+    for (k, v) in table.iter() {
+        match v {
+            toml::Value::Table(t) => {
+                for (sub_k, sub_v) in flatten(t) {
+                    items.push((format!("{}.{}", k, sub_k), sub_v));
+                }
+            },
+            _ => items.push((k.clone(), v.clone())),
+        }
+    }
+    items
+}
+
+/// Set dotted key in a toml document, keeping comments and formatting.
+/// The value is parsed as toml, or treated as a string if it is not valid toml
+/// or the key takes a string (e.g. `defaults.account 1`).
+pub fn set_value(doc: &mut toml_edit::DocumentMut, key: &str, value: &str) -> Result<()> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        bail!("invalid key: {}", key);
+    }
+
+    let mut candidates = vec![toml_edit::Value::from(value)];
+    if let Some(v) = value.parse::<toml_edit::Value>().ok().filter(|v| !v.is_str()) {
+        candidates.insert(0, v);
+    }
+
+    let mut error = None;
+    for v in candidates {
+        let mut edited = doc.clone();
+        match set_item(&mut edited, &segments, v) {
+            Ok(()) => {
+                *doc = edited;
+                return Ok(());
+            },
+            Err(e) => { error.get_or_insert(e); },
+        }
+    }
+    Err(error.unwrap())
+}
+
+fn set_item(doc: &mut toml_edit::DocumentMut, segments: &[&str], value: toml_edit::Value) -> Result<()> {
+    let (last, parents) = segments.split_last().unwrap();
+    let mut table = doc.as_table_mut();
+    for seg in parents {
+        let item = table.entry(seg).or_insert_with(|| {
+            let mut t = toml_edit::Table::new();
+            t.set_implicit(true);
+            toml_edit::Item::Table(t)
+        });
+        table = item.as_table_mut()
+            .ok_or_else(|| anyhow!("{} is not a table", seg))?;
+    }
+    table.insert(last, toml_edit::value(value));
+
+    // Validate the result
+    Config::parse(&doc.to_string())
+        .map_err(|e| anyhow!("invalid configuration\n{}", e))?;
+
+    Ok(())
+}
+
+impl Config {
+    pub fn parse(data: &str) -> Result<Self> {
+        Ok(toml::from_str(data)?)
+    }
+
+    /// Load user file (or `path`) and project file
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        ConfigFiles::load(path)?.config()
+    }
+
+    /// Select profile name.
+    /// Explicit name (--profile or ZAIM_PROFILE) is preferred, then `default_profile`,
+    /// then the profile named "default" if it exists.
+    pub fn select_profile(&self, name: Option<&str>) -> Result<Option<(String, Profile)>> {
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(n) => n,
+            None if self.profiles.contains_key(DEFAULT_PROFILE) => DEFAULT_PROFILE,
+            None => return Ok(None),
+        };
+
+        match self.profiles.get(name) {
+            Some(p) => Ok(Some((name.to_string(), self.with_globals(p.clone())))),
+            None => bail!("profile '{}' is not found in configuration", name),
+        }
+    }
+
+    /// Profile used when no profile is selected
+    pub fn global_profile(&self) -> Profile {
+        self.with_globals(Profile::default())
+    }
+
+    /// Fill unset items of profile by `[credential]` and `[defaults]`.
+    /// Environment variables are applied in `Config::defaults`.
+    fn with_globals(&self, mut profile: Profile) -> Profile {
+        profile.consumer_info = profile.consumer_info.or_else(|| self.credential.consumer_info.clone());
+        profile.access_token = profile.access_token.or_else(|| self.credential.access_token.clone());
+        profile.credential_helper = profile.credential_helper.or_else(|| self.credential.helper.clone());
+        profile.defaults = profile.defaults.or(&self.defaults);
+        profile
+    }
+
+    /// Effective defaults of profile: environment variables > profile > `[defaults]`
+    pub fn defaults(&self, profile: &Profile) -> Result<Defaults> {
+        Ok(Defaults::from_env()?.or(&profile.defaults.clone().or(&self.defaults)))
+    }
+}
+
+impl Profile {
+    /// File path of access tokens for the profile
+    pub fn access_token_path(&self, name: &str) -> Result<PathBuf> {
+        match &self.access_token {
+            Some(p) => Ok(p.clone()),
+            None => Ok(config_dir()?.join("profiles").join(name).join(ACCESS_TOKEN_FILE)),
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective value of a key (e.g. "defaults.output")
+    Get {
+        key: String,
+    },
+
+    /// Set a value into user file, or project file with --project
+    Set {
+        key: String,
+        value: String,
+
+        /// Write into project file (.zaim-cli.toml)
+        #[arg(long)]
+        project: bool,
+    },
+
+    /// List effective values and the file deciding each of them
+    List,
+
+    /// Open user file, or project file with --project, by $VISUAL or $EDITOR
+    Edit {
+        /// Edit project file (.zaim-cli.toml)
+        #[arg(long)]
+        project: bool,
+    },
+}
+
+fn target_path(user_path: Option<&Path>, project: bool) -> Result<PathBuf> {
+    if project {
+        match find_project_config() {
+            Some(p) => Ok(p),
+            None => Ok(std::env::current_dir()?.join(PROJECT_CONFIG_FILE)),
+        }
+    } else {
+        match user_path {
+            Some(p) => Ok(p.to_path_buf()),
+            None => default_config_path(),
+        }
+    }
+}
+
+fn value_to_string(v: &toml::Value) -> String {
+    match v {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn run(command: &ConfigCommand, user_path: Option<&Path>, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        ConfigCommand::Get { key } => {
+            let files = ConfigFiles::load(user_path)?;
+            let merged = files.merged();
+            let value = match lookup(&merged, key) {
+                Some(v) => v,
+                None => bail!("{} is not set", key),
+            };
+
+            match format {
+                OutputFormat::Json => print_json(&json!({
+                    "key": key,
+                    "value": value,
+                    "origin": files.origin(key).map(|f| f.scope.to_string()),
+                    "file": files.origin(key).map(|f| f.path.display().to_string()),
+                }))?,
+                _ => println!("{}", value_to_string(value)),
+            }
+        },
+        ConfigCommand::Set { key, value, project } => {
+            let path = target_path(user_path, *project)?;
+            let data = if path.exists() { std::fs::read_to_string(&path)? } else { String::new() };
+            let mut doc: toml_edit::DocumentMut = data.parse()
+                .map_err(|e| anyhow!("failed to parse {}\n{}", path.display(), e))?;
+
+            set_value(&mut doc, key, value)?;
+            if *project {
+                check_project_table(&doc.to_string().parse()?)?;
+            }
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, doc.to_string())?;
+        },
+        ConfigCommand::List => {
+            let files = ConfigFiles::load(user_path)?;
+            let mut table = Table::new(&["key", "value", "origin"]);
+            for (k, v) in flatten(&files.merged()) {
+                let origin = files.origin(&k).map(|f| f.scope.to_string());
+                table.push(vec![json!(k), json!(v), json!(origin)]);
+            }
+            table.print(format)?;
+        },
+        ConfigCommand::Edit { project } => {
+            let path = target_path(user_path, *project)?;
+            if !path.exists() {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, "")?;
+            }
+
+            let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| String::from("vi"));
+            let status = std::process::Command::new(&editor).arg(&path).status()
+                .map_err(|e| anyhow!("failed to run {}: {}", editor, e))?;
+            if !status.success() {
+                bail!("{} exited with {}", editor, status);
+            }
+
+            read_table(&path, if *project { Scope::Project } else { Scope::User })?;
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, check_project_table, flatten, lookup, merge_table, set_value};
+
+    const CONFIG: &str = r#"
+default_profile = "household"
+
+[credential]
+helper = "pass-zaim"
+
+[defaults]
+currency = "JPY"
+account = "1"
+
+[profiles.personal]
+
+[profiles.household]
+consumer_info = "household.json"
+api_base = "http://localhost:8080/v2"
+
+[profiles.household.defaults]
+account = "2"
+"#;
+
+    #[test]
+    fn test_config_select_profile() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        let (name, profile) = config.select_profile(None).unwrap().unwrap();
+        assert_eq!(name, "household");
+        assert_eq!(profile.api_base.as_deref(), Some("http://localhost:8080/v2"));
+        assert_eq!(profile.defaults.account.as_deref(), Some("2"));
+        assert_eq!(profile.defaults.currency.as_deref(), Some("JPY"));
+
+        let (name, profile) = config.select_profile(Some("personal")).unwrap().unwrap();
+        assert_eq!(name, "personal");
+        assert_eq!(profile.credential_helper.as_deref(), Some("pass-zaim"));
+        assert_eq!(profile.defaults.account.as_deref(), Some("1"));
+
+        assert!(config.select_profile(Some("unknown")).is_err());
+    }
+
+    #[test]
+    fn test_defaults_timezone() {
+        let defaults = Config::parse("[defaults]\ntimezone = \"Asia/Tokyo\"\n").unwrap().defaults;
+        assert_eq!(defaults.now().unwrap().offset().local_minus_utc(), 9 * 3600);
+
+        let defaults = Config::parse("[defaults]\ntimezone = \"Mars/Base\"\n").unwrap().defaults;
+        assert!(defaults.today().unwrap_err().to_string().starts_with("invalid timezone Mars/Base"));
+    }
+
+    #[test]
+    fn test_config_without_profiles() {
+        let config = Config::parse("").unwrap();
+        assert!(config.select_profile(None).unwrap().is_none());
+        assert_eq!(config.retry.max_attempts, 3);
+        assert_eq!(config.cache.ttl_secs, 86400);
+    }
+
+    #[test]
+    fn test_config_network() {
+        let config = Config::parse("[network]\nproxy = \"http://proxy:8080\"\ntimeout_secs = 0\n").unwrap();
+        assert_eq!(config.network.proxy.as_deref(), Some("http://proxy:8080"));
+        assert_eq!(config.network.timeout_secs, 0);
+        assert_eq!(config.network.connect_timeout_secs, 10);
+    }
+
+    #[test]
+    fn test_config_unknown_field() {
+        assert!(Config::parse("[profiles.a]\nconsumer = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn test_merge_table() {
+        let mut user: toml::Table = "[defaults]\noutput = \"table\"\ncurrency = \"JPY\"\n".parse().unwrap();
+        let project: toml::Table = "[defaults]\noutput = \"json\"\n".parse().unwrap();
+        merge_table(&mut user, &project);
+
+        assert_eq!(lookup(&user, "defaults.output").unwrap().as_str(), Some("json"));
+        assert_eq!(lookup(&user, "defaults.currency").unwrap().as_str(), Some("JPY"));
+        assert_eq!(flatten(&user).len(), 2);
+    }
+
+    #[test]
+    fn test_check_project_table() {
+        let check = |data: &str| check_project_table(&data.parse().unwrap()).map_err(|e| e.to_string());
+        assert!(check("[defaults]\noutput = \"json\"\n[aliases]\nst = \"auth status\"\n[profiles.p.defaults]\naccount = \"1\"").is_ok());
+        assert_eq!(
+            check("[credential]\nhelper = \"touch /tmp/x\""),
+            Err(String::from("credential is not allowed in project file. Set it in the user file"))
+        );
+        assert_eq!(
+            check("[profiles.p]\ncredential_helper = \"touch /tmp/x\""),
+            Err(String::from("profiles.p.credential_helper is not allowed in project file. Set it in the user file"))
+        );
+        assert!(check("[profiles.p]\napi_base = \"https://example.com\"").is_err());
+        assert!(check("[network]\nproxy = \"http://example.com\"").is_err());
+        assert_eq!(
+            check("[aliases]\nst = \"auth status --proxy=http://example.com\""),
+            Err(String::from("alias st in project file cannot use --proxy=http://example.com"))
+        );
+        assert_eq!(
+            check("[aliases]\nm = \"money add --uri https://example.com/steal\""),
+            Err(String::from("alias m in project file cannot use --uri"))
+        );
+        assert!(check("[aliases]\ns = \"sync --db /home/me/.bashrc\"").is_err());
+        assert!(check("[aliases]\ni = \"import csv --state=/tmp/x bank.csv\"").is_err());
+        assert!(check("[aliases]\nc = \"config set defaults.account 1\"").is_err());
+        assert!(check("[aliases]\nc = \"cache clear --all\"").is_err());
+        assert!(check("[aliases]\nr = \"report category -vv --from 2026-01-01\"").is_ok());
+        assert!(check("[aliases]\ni = \"import csv --profile bank.toml --commit\"").is_ok());
+    }
+
+    #[test]
+    fn test_set_value() {
+        let mut doc: toml_edit::DocumentMut = "# comment\n[defaults]\noutput = \"table\"\n".parse().unwrap();
+        set_value(&mut doc, "defaults.output", "json").unwrap();
+        set_value(&mut doc, "retry.max_attempts", "5").unwrap();
+        set_value(&mut doc, "aliases.st", "auth status --all").unwrap();
+
+        let config = Config::parse(&doc.to_string()).unwrap();
+        assert!(doc.to_string().starts_with("# comment\n"));
+        assert_eq!(config.retry.max_attempts, 5);
+        assert_eq!(config.aliases.get("st").map(String::as_str), Some("auth status --all"));
+
+        // Account ids are kept as strings, and numbers of numeric keys are not
+        set_value(&mut doc, "defaults.account", "1").unwrap();
+        assert_eq!(Config::parse(&doc.to_string()).unwrap().defaults.account.as_deref(), Some("1"));
+        assert!(doc.to_string().contains("account = \"1\""));
+        assert!(set_value(&mut doc, "retry.max_attempts", "many").is_err());
+        assert!(doc.to_string().contains("max_attempts = 5"));
+
+        assert!(set_value(&mut doc, "defaults.unknown", "1").is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! External credential helper protocol
+//!
+//! A credential helper is an executable called as `<helper> <action>`,
+//! where action is one of `get`, `store` and `erase` (same as git-credential).
+//! Attributes are exchanged through stdin/stdout as `key=value` lines,
+//! terminated by a blank line or EOF.
+//!
+//! Input always starts with `protocol`, `host` and `profile`, which identify the api base
+//! (`protocol=https` and `host=api.zaim.net` by default) and the profile of configuration.
+//! - get: helper prints the known attributes out of
+//!   `consumer_key`, `consumer_secret`, `access_token` and `access_token_secret`.
+//! - store: helper receives the attributes to be saved.
+//!   `consumer_key` is also sent with access tokens to identify the application.
+//! - erase: helper receives the attributes to be removed.
+
+use crate::zaim_api::{AccessTokens, ConsumerInfo};
+
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::Url;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Credentials {
+    pub consumer_key: Option<String>,
+    pub consumer_secret: Option<String>,
+    pub access_token: Option<String>,
+    pub access_token_secret: Option<String>,
+}
+
+impl Credentials {
+    pub fn from_access_tokens(access_tokens: &AccessTokens) -> Self {
+        Self {
+            access_token: Some(access_tokens.access_token.clone()),
+            access_token_secret: Some(access_tokens.access_token_secret.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Return consumer information if both key and secret are provided.
+    pub fn consumer_info(&self) -> Option<ConsumerInfo> {
+        match (&self.consumer_key, &self.consumer_secret) {
+            (Some(k), Some(s)) => Some(ConsumerInfo {
+                consumer_key: k.clone(),
+                consumer_secret: s.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Return access tokens if both token and secret are provided.
+    pub fn access_tokens(&self) -> Option<AccessTokens> {
+        match (&self.access_token, &self.access_token_secret) {
+            (Some(t), Some(s)) => Some(AccessTokens {
+                access_token: t.clone(),
+                access_token_secret: s.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 4] {
+        [
+            ("consumer_key", &self.consumer_key),
+            ("consumer_secret", &self.consumer_secret),
+            ("access_token", &self.access_token),
+            ("access_token_secret", &self.access_token_secret),
+        ]
+    }
+
+    /// Serialize into helper input, following the identifying attributes.
+    pub fn to_helper_input(&self, target: &Target) -> Result<String> {
+        let mut input = String::new();
+        let fields = target.fields().into_iter().chain(self.fields());
+        for (k, v) in fields {
+            if let Some(v) = v {
+                if v.contains('\n') || v.contains('\0') {
+                    bail!("value of '{}' contains invalid character", k);
+                }
+                input.push_str(format!("{}={}\n", k, v).as_str());
+            }
+        }
+        input.push('\n');
+
+        Ok(input)
+    }
+
+    /// Parse helper output. Unknown attributes are ignored.
+    pub fn parse_helper_output(output: &str) -> Result<Self> {
+        let mut credentials = Self::default();
+        for line in output.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                break;
+            }
+
+            let (k, v) = line.split_once('=')
+                .ok_or_else(|| anyhow!("invalid line from credential helper: {}", line))?;
+            let v = Some(v.to_string());
+            match k {
+                "consumer_key" => credentials.consumer_key = v,
+                "consumer_secret" => credentials.consumer_secret = v,
+                "access_token" => credentials.access_token = v,
+                "access_token_secret" => credentials.access_token_secret = v,
+                _ => (),
+            }
+        }
+
+        Ok(credentials)
+    }
+}
+
+/// Identifying attributes sent before credentials, so that a helper can keep
+/// credentials of each api base and profile apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub protocol: Option<String>,
+    pub host: Option<String>,
+    pub profile: Option<String>,
+}
+
+impl Target {
+    pub fn new(api_base: &str, profile: &str) -> Result<Self> {
+        let url = Url::parse(api_base).map_err(|e| anyhow!("invalid api base {}: {}", api_base, e))?;
+        let host = url.host_str().map(|h| match url.port() {
+            Some(port) => format!("{}:{}", h, port),
+            None => h.to_string(),
+        });
+
+        Ok(Self {
+            protocol: Some(url.scheme().to_string()),
+            host,
+            profile: Some(profile.to_string()),
+        })
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 3] {
+        [
+            ("protocol", &self.protocol),
+            ("host", &self.host),
+            ("profile", &self.profile),
+        ]
+    }
+}
+
+pub struct CredentialHelper {
+    command: String,
+    target: Target,
+}
+
+impl CredentialHelper {
+    pub fn new(command: &str, target: Target) -> Self {
+        Self { command: command.to_string(), target }
+    }
+
+    pub fn get(&self) -> Result<Credentials> {
+        let output = self.run("get", &Credentials::default().to_helper_input(&self.target)?)?;
+        Credentials::parse_helper_output(&output)
+    }
+
+    pub fn store(&self, credentials: &Credentials) -> Result<()> {
+        self.run("store", &credentials.to_helper_input(&self.target)?)?;
+        Ok(())
+    }
+
+    pub fn erase(&self, credentials: &Credentials) -> Result<()> {
+        self.run("erase", &credentials.to_helper_input(&self.target)?)?;
+        Ok(())
+    }
+
+    /// Run helper through shell so that the command may contain arguments.
+    fn run(&self, action: &str, input: &str) -> Result<String> {
+        let mut child = shell_command(&self.command, action)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("failed to run credential helper '{}': {}", self.command, e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // Helper may exit without reading the input. Its status and stderr tell why
+            match stdin.write_all(input.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => (),
+            }
+        }
+
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            match stderr.trim() {
+                "" => bail!("credential helper '{}' {} failed: {}", self.command, action, output.status),
+                e => bail!("credential helper '{}' {} failed: {}\n{}", self.command, action, output.status, e),
+            }
+        }
+        eprint!("{}", stderr);
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str, action: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(format!("{} \"$@\"", command)).arg(command).arg(action);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str, action: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(format!("{} {}", command, action));
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Credentials, CredentialHelper, Target};
+
+    fn target() -> Target {
+        Target::new("https://api.zaim.net/v2", "default").unwrap()
+    }
+
+    #[test]
+    fn test_credentials_to_helper_input() {
+        let credentials = Credentials {
+            access_token: Some(String::from("token")),
+            access_token_secret: Some(String::from("secret")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            credentials.to_helper_input(&target()).unwrap(),
+            "protocol=https\nhost=api.zaim.net\nprofile=default\naccess_token=token\naccess_token_secret=secret\n\n"
+        );
+    }
+
+    #[test]
+    fn test_credentials_helper_input_of_profiles() {
+        let credentials = Credentials::default();
+        let personal = Target::new("https://api.zaim.net/v2", "personal").unwrap();
+        let local = Target::new("http://localhost:8080/v2", "local").unwrap();
+
+        assert_eq!(
+            credentials.to_helper_input(&personal).unwrap(),
+            "protocol=https\nhost=api.zaim.net\nprofile=personal\n\n"
+        );
+        assert_eq!(
+            credentials.to_helper_input(&local).unwrap(),
+            "protocol=http\nhost=localhost:8080\nprofile=local\n\n"
+        );
+    }
+
+    #[test]
+    fn test_credentials_rejects_newline() {
+        let credentials = Credentials {
+            consumer_key: Some(String::from("a\nb")),
+            ..Default::default()
+        };
+
+        assert!(credentials.to_helper_input(&target()).is_err());
+    }
+
+    #[test]
+    fn test_credentials_parse_helper_output() {
+        let output = "consumer_key=key\nconsumer_secret=a=b\nunknown=x\n\naccess_token=ignored\n";
+        let credentials = Credentials::parse_helper_output(output).unwrap();
+
+        assert_eq!(credentials.consumer_key.as_deref(), Some("key"));
+        assert_eq!(credentials.consumer_secret.as_deref(), Some("a=b"));
+        assert!(credentials.access_token.is_none());
+        assert!(credentials.consumer_info().is_some());
+        assert!(credentials.access_tokens().is_none());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_credential_helper_get() {
+        let helper = CredentialHelper::new(
+            "f() { cat > /dev/null; printf 'access_token=t\\naccess_token_secret=s\\n'; }; f",
+            target()
+        );
+        let credentials = helper.get().unwrap();
+        let access_tokens = credentials.access_tokens().unwrap();
+
+        assert_eq!(access_tokens.access_token, "t");
+        assert_eq!(access_tokens.access_token_secret, "s");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_credential_helper_exits_early() {
+        // Exits without reading the input, which is larger than the pipe buffer
+        let helper = CredentialHelper::new("f() { echo 'no such entry' >&2; exit 3; }; f", target());
+        let credentials = Credentials { consumer_key: Some("k".repeat(1 << 20)), ..Default::default() };
+        let error = helper.store(&credentials).unwrap_err().to_string();
+
+        assert!(error.ends_with("store failed: exit status: 3\nno such entry"), "{}", error);
+    }
+}
@@ -0,0 +1,375 @@
+//! SQLite mirror of household data
+//!
+//! Money records are keyed by (mode, id). Records which disappeared upstream
+//! are not removed but flagged by `deleted_at`.
+//! `money_view` joins names of category, genre and accounts to money records.
+
+use zaim_cli::models::{Account, Category, Genre, Money};
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension, Transaction};
+
+const SCHEMA_VERSION: i32 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS categories (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    sort INTEGER,
+    parent_category_id INTEGER,
+    active INTEGER,
+    modified TEXT
+);
+
+CREATE TABLE IF NOT EXISTS genres (
+    id INTEGER PRIMARY KEY,
+    category_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    sort INTEGER,
+    parent_genre_id INTEGER,
+    active INTEGER,
+    modified TEXT
+);
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    sort INTEGER,
+    parent_account_id INTEGER,
+    active INTEGER,
+    modified TEXT
+);
+
+CREATE TABLE IF NOT EXISTS money (
+    id INTEGER NOT NULL,
+    mode TEXT NOT NULL,
+    date TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    category_id INTEGER,
+    genre_id INTEGER,
+    from_account_id INTEGER,
+    to_account_id INTEGER,
+    name TEXT,
+    place TEXT,
+    comment TEXT,
+    receipt_id INTEGER,
+    currency_code TEXT,
+    active INTEGER,
+    created TEXT,
+    modified TEXT,
+    synced_at TEXT NOT NULL,
+    deleted_at TEXT,
+    PRIMARY KEY (mode, id)
+);
+
+CREATE INDEX IF NOT EXISTS money_date ON money (date);
+
+CREATE VIEW IF NOT EXISTS money_view AS
+SELECT m.id, m.mode, m.date, m.amount,
+       c.name AS category, g.name AS genre,
+       fa.name AS from_account, ta.name AS to_account,
+       m.name, m.place, m.comment, m.receipt_id, m.currency_code,
+       m.category_id, m.genre_id, m.from_account_id, m.to_account_id
+FROM money m
+LEFT JOIN categories c ON c.id = m.category_id
+LEFT JOIN genres g ON g.id = m.genre_id
+LEFT JOIN accounts fa ON fa.id = m.from_account_id
+LEFT JOIN accounts ta ON ta.id = m.to_account_id
+WHERE m.deleted_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS sync_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// Number of rows changed by saving fetched data
+#[derive(serde::Serialize, Debug, Default, PartialEq)]
+pub struct Changes {
+    pub fetched: usize,
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+pub fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)
+        .map_err(|e| anyhow!("failed to open {}\n{}", path.display(), e))?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+/// Open an existing database without creating or migrating it
+pub fn open_read_only(path: &Path) -> Result<Connection> {
+    if !path.is_file() {
+        bail!("database {} does not exist. Please run `sync --db {}`", path.display(), path.display());
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| anyhow!("failed to open {}\n{}", path.display(), e))?;
+    let version: i32 = conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+    if version != SCHEMA_VERSION {
+        bail!("database schema version {} of {} is not {}. Please run `sync --db {}`",
+              version, path.display(), SCHEMA_VERSION, path.display());
+    }
+    Ok(conn)
+}
+
+pub fn open_in_memory() -> Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: i32 = conn.pragma_query_value(None, "user_version", |r| r.get(0))?;
+    if version > SCHEMA_VERSION {
+        bail!("database schema version {} is newer than supported version {}", version, SCHEMA_VERSION);
+    }
+
+    conn.execute_batch(SCHEMA)?;
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+pub fn get_state(conn: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(conn.query_row("SELECT value FROM sync_state WHERE key = ?1", [key], |r| r.get(0)).optional()?)
+}
+
+pub fn set_state(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO sync_state (key, value) VALUES (?1, ?2)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+/// Insert or update rows by `sql`, which is an upsert updating only changed rows.
+/// `exists_sql` checks whether a row exists by the parameters of `key`.
+fn upsert<T>(
+    tx: &Transaction,
+    exists_sql: &str,
+    sql: &str,
+    rows: &[T],
+    key: impl Fn(&T) -> Vec<Value>,
+    bind: impl Fn(&mut rusqlite::Statement, &T) -> rusqlite::Result<usize>
+) -> Result<Changes> {
+    let mut changes = Changes { fetched: rows.len(), ..Default::default() };
+    let mut exists = tx.prepare(exists_sql)?;
+    let mut stmt = tx.prepare(sql)?;
+
+    for row in rows {
+        let existed = exists.exists(params_from_iter(key(row)))?;
+        if bind(&mut stmt, row)? > 0 {
+            if existed {
+                changes.updated += 1;
+            } else {
+                changes.inserted += 1;
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+pub fn save_categories(tx: &Transaction, categories: &[Category]) -> Result<Changes> {
+    upsert(tx, "SELECT 1 FROM categories WHERE id = ?1",
+        "INSERT INTO categories (id, name, mode, sort, parent_category_id, active, modified)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (id) DO UPDATE SET
+            name = excluded.name, mode = excluded.mode, sort = excluded.sort,
+            parent_category_id = excluded.parent_category_id, active = excluded.active,
+            modified = excluded.modified
+         WHERE (name, mode, sort, parent_category_id, active, modified)
+            IS NOT (excluded.name, excluded.mode, excluded.sort, excluded.parent_category_id,
+                    excluded.active, excluded.modified)",
+        categories,
+        |c| vec![Value::from(c.id as i64)],
+        |stmt, c| stmt.execute(params![
+            c.id, c.name, c.mode.as_str(), c.sort, c.parent_category_id, c.active, c.modified
+        ]),
+    )
+}
+
+pub fn save_genres(tx: &Transaction, genres: &[Genre]) -> Result<Changes> {
+    upsert(tx, "SELECT 1 FROM genres WHERE id = ?1",
+        "INSERT INTO genres (id, category_id, name, sort, parent_genre_id, active, modified)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (id) DO UPDATE SET
+            category_id = excluded.category_id, name = excluded.name, sort = excluded.sort,
+            parent_genre_id = excluded.parent_genre_id, active = excluded.active,
+            modified = excluded.modified
+         WHERE (category_id, name, sort, parent_genre_id, active, modified)
+            IS NOT (excluded.category_id, excluded.name, excluded.sort, excluded.parent_genre_id,
+                    excluded.active, excluded.modified)",
+        genres,
+        |g| vec![Value::from(g.id as i64)],
+        |stmt, g| stmt.execute(params![
+            g.id, g.category_id, g.name, g.sort, g.parent_genre_id, g.active, g.modified
+        ]),
+    )
+}
+
+pub fn save_accounts(tx: &Transaction, accounts: &[Account]) -> Result<Changes> {
+    upsert(tx, "SELECT 1 FROM accounts WHERE id = ?1",
+        "INSERT INTO accounts (id, name, sort, parent_account_id, active, modified)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (id) DO UPDATE SET
+            name = excluded.name, sort = excluded.sort,
+            parent_account_id = excluded.parent_account_id, active = excluded.active,
+            modified = excluded.modified
+         WHERE (name, sort, parent_account_id, active, modified)
+            IS NOT (excluded.name, excluded.sort, excluded.parent_account_id,
+                    excluded.active, excluded.modified)",
+        accounts,
+        |a| vec![Value::from(a.id as i64)],
+        |stmt, a| stmt.execute(params![
+            a.id, a.name, a.sort, a.parent_account_id, a.active, a.modified
+        ]),
+    )
+}
+
+/// Save money records fetched from `since` (None: all records).
+/// Records stored with a date in the same period and missing in `money` are flagged as deleted.
+/// A record moved to a date before `since` is also missing, and flagged until it is fetched again
+/// by a full sync, because Zaim API cannot fetch a record by id to tell it apart.
+pub fn save_money(tx: &Transaction, money: &[Money], since: Option<&str>, now: &str) -> Result<Changes> {
+    let mut changes = upsert(tx, "SELECT 1 FROM money WHERE mode = ?1 AND id = ?2",
+        "INSERT INTO money (id, mode, date, amount, category_id, genre_id, from_account_id,
+                to_account_id, name, place, comment, receipt_id, currency_code, active, created,
+                modified, synced_at, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, NULL)
+         ON CONFLICT (mode, id) DO UPDATE SET
+            date = excluded.date, amount = excluded.amount, category_id = excluded.category_id,
+            genre_id = excluded.genre_id, from_account_id = excluded.from_account_id,
+            to_account_id = excluded.to_account_id, name = excluded.name, place = excluded.place,
+            comment = excluded.comment, receipt_id = excluded.receipt_id,
+            currency_code = excluded.currency_code, active = excluded.active,
+            created = excluded.created, modified = excluded.modified,
+            synced_at = excluded.synced_at, deleted_at = NULL
+         WHERE (date, amount, category_id, genre_id, from_account_id, to_account_id, name, place,
+                comment, receipt_id, currency_code, active, created, modified, deleted_at)
+            IS NOT (excluded.date, excluded.amount, excluded.category_id, excluded.genre_id,
+                    excluded.from_account_id, excluded.to_account_id, excluded.name, excluded.place,
+                    excluded.comment, excluded.receipt_id, excluded.currency_code, excluded.active,
+                    excluded.created, excluded.modified, NULL)",
+        money,
+        |m| vec![Value::from(m.mode.to_string()), Value::from(m.id as i64)],
+        |stmt, m| stmt.execute(params![
+            m.id, m.mode.as_str(), m.date.to_string(), m.amount, m.category_id, m.genre_id,
+            m.from_account_id, m.to_account_id, m.name, m.place, m.comment, m.receipt_id,
+            m.currency_code, m.active, m.created, m.modified, now
+        ]),
+    )?;
+
+    tx.execute_batch("CREATE TEMP TABLE IF NOT EXISTS fetched (mode TEXT, id INTEGER, PRIMARY KEY (mode, id));
+                      DELETE FROM fetched;")?;
+    {
+        let mut stmt = tx.prepare("INSERT OR IGNORE INTO fetched (mode, id) VALUES (?1, ?2)")?;
+        for m in money {
+            stmt.execute(params![m.mode.as_str(), m.id])?;
+        }
+    }
+    changes.deleted = tx.execute(
+        "UPDATE money SET deleted_at = ?1
+         WHERE deleted_at IS NULL
+           AND (?2 IS NULL OR date >= ?2)
+           AND NOT EXISTS (SELECT 1 FROM fetched f WHERE f.mode = money.mode AND f.id = money.id)",
+        params![now, since],
+    )?;
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, open_in_memory, open_read_only, save_money, Changes};
+    use zaim_cli::models::{Mode, Money};
+
+    fn money(id: u64, date: &str, amount: i64) -> Money {
+        Money {
+            id,
+            mode: Mode::Payment,
+            date: date.parse().unwrap(),
+            amount,
+            category_id: Some(101),
+            genre_id: Some(10101),
+            from_account_id: Some(1),
+            to_account_id: None,
+            name: None,
+            place: Some(String::from("Cafe")),
+            comment: None,
+            receipt_id: None,
+            currency_code: Some(String::from("JPY")),
+            active: Some(1),
+            created: None,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_save_money_incremental() {
+        let mut conn = open_in_memory().unwrap();
+
+        let tx = conn.transaction().unwrap();
+        let changes = save_money(&tx, &[
+            money(1, "2026-01-10", 480), money(2, "2026-02-10", 1200), money(3, "2026-02-20", 300)
+        ], None, "t1").unwrap();
+        tx.commit().unwrap();
+        assert_eq!(changes, Changes { fetched: 3, inserted: 3, updated: 0, deleted: 0 });
+
+        // Record 1 is out of the window, 2 is changed and 3 is deleted
+        let tx = conn.transaction().unwrap();
+        let changes = save_money(&tx, &[
+            money(2, "2026-02-10", 1500), money(4, "2026-02-21", 100)
+        ], Some("2026-02-01"), "t2").unwrap();
+        tx.commit().unwrap();
+        assert_eq!(changes, Changes { fetched: 2, inserted: 1, updated: 1, deleted: 1 });
+
+        let deleted: Vec<u64> = conn.prepare("SELECT id FROM money WHERE deleted_at IS NOT NULL").unwrap()
+            .query_map([], |r| r.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(deleted, vec![3]);
+
+        // Unchanged records are not counted
+        let tx = conn.transaction().unwrap();
+        let changes = save_money(&tx, &[
+            money(2, "2026-02-10", 1500), money(4, "2026-02-21", 100)
+        ], Some("2026-02-01"), "t3").unwrap();
+        tx.commit().unwrap();
+        assert_eq!(changes, Changes { fetched: 2, inserted: 0, updated: 0, deleted: 0 });
+
+        // Record 2 moved before the period is flagged, and restored by a full sync
+        let tx = conn.transaction().unwrap();
+        let changes = save_money(&tx, &[money(4, "2026-02-21", 100)], Some("2026-02-01"), "t4").unwrap();
+        assert_eq!(changes.deleted, 1);
+        let changes = save_money(&tx, &[
+            money(1, "2026-01-10", 480), money(2, "2026-01-31", 1500), money(4, "2026-02-21", 100)
+        ], None, "t5").unwrap();
+        assert_eq!(changes, Changes { fetched: 3, inserted: 0, updated: 1, deleted: 0 });
+        let (date, deleted_at): (String, Option<String>) = tx.query_row(
+            "SELECT date, deleted_at FROM money WHERE id = 2", [], |r| Ok((r.get(0)?, r.get(1)?))
+        ).unwrap();
+        assert_eq!((date.as_str(), deleted_at), ("2026-01-31", None));
+    }
+
+    #[test]
+    fn test_open_read_only() {
+        let dir = std::env::temp_dir().join(format!("zaim-cli-db-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mirror.sqlite");
+
+        let err = open_read_only(&path).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+        assert!(!path.exists());
+
+        open(&path).unwrap();
+        let conn = open_read_only(&path).unwrap();
+        assert!(conn.execute("DELETE FROM money", []).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+//! Detection of duplicate money records
+//!
+//! Candidates are pairs of the same mode and amount within a few days, scored by
+//! closeness of dates and similarity of place or name.
+//!
+//! A decisions file lists money records to delete as `<mode>/<id>`.
+//!
+//! ```toml
+//! [[decisions]]
+//! keep = "payment/101"
+//! delete = "payment/102"
+//! ```
+
+use crate::config;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery};
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde_json::json;
+
+#[derive(clap::Args, Debug)]
+pub struct DedupeArgs {
+    /// Check money records dated on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Check money records dated on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub end_date: Option<NaiveDate>,
+
+    /// Maximum days between duplicates
+    #[arg(long, value_name = "DAYS", default_value_t = 3)]
+    pub max_days: i64,
+
+    /// Minimum score (0 to 1) of candidates
+    #[arg(long, value_name = "SCORE", default_value_t = 0.5)]
+    pub min_score: f64,
+
+    /// Decide to delete or keep each candidate
+    #[arg(long, conflicts_with = "decisions")]
+    pub interactive: bool,
+
+    /// Decisions file listing money records to delete (toml)
+    #[arg(long, value_name = "FILE")]
+    pub decisions: Option<PathBuf>,
+
+    /// Delete money records in the decisions file. Without this, they are only previewed
+    #[arg(long, requires = "decisions")]
+    pub commit: bool,
+}
+
+/// Pair of likely duplicates. `a` is the older one
+#[derive(Debug)]
+pub struct Candidate {
+    pub score: f64,
+    pub a: Money,
+    pub b: Money,
+}
+
+fn key(m: &Money) -> String {
+    format!("{}/{}", m.mode, m.id)
+}
+
+/// Lowercase characters without spaces and punctuations
+fn normalize(s: &str) -> Vec<char> {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Dice coefficient of character bigrams
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    for w in a.windows(2) {
+        *bigrams.entry((w[0], w[1])).or_insert(0) += 1;
+    }
+    let mut common = 0;
+    for w in b.windows(2) {
+        if let Some(n) = bigrams.get_mut(&(w[0], w[1])).filter(|n| **n > 0) {
+            *n -= 1;
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / (a.len() + b.len() - 2) as f64
+}
+
+/// Similarity of place or name. 0.5 if both have neither
+fn text_score(a: &Money, b: &Money) -> f64 {
+    let text = |s: &Option<String>| s.as_deref().unwrap_or("").trim().to_string();
+    let pairs: Vec<(String, String)> = [(text(&a.place), text(&b.place)), (text(&a.name), text(&b.name))]
+        .into_iter()
+        .filter(|(x, y)| !x.is_empty() || !y.is_empty())
+        .collect();
+    if pairs.is_empty() {
+        return 0.5;
+    }
+    pairs.iter().map(|(x, y)| similarity(x, y)).fold(0.0, f64::max)
+}
+
+/// Find pairs of the same mode and amount within `max_days`, ordered by score
+pub fn find_candidates(money: &[Money], max_days: i64, min_score: f64) -> Vec<Candidate> {
+    let mut groups: HashMap<(Mode, i64), Vec<&Money>> = HashMap::new();
+    for m in money {
+        groups.entry((m.mode, m.amount)).or_default().push(m);
+    }
+
+    let mut candidates = Vec::new();
+    for group in groups.values_mut() {
+        group.sort_by_key(|m| (m.date, m.id));
+        for (i, a) in group.iter().enumerate() {
+            for b in group[i + 1..].iter() {
+                let days = (b.date - a.date).num_days();
+                if days > max_days {
+                    break;
+                }
+                // Items of a receipt are not duplicates
+                if a.receipt_id.is_some() && a.receipt_id == b.receipt_id {
+                    continue;
+                }
+                let date_score = 1.0 - days as f64 / (max_days + 1) as f64;
+                let score = ((0.3 * date_score + 0.7 * text_score(a, b)) * 100.0).round() / 100.0;
+                if score >= min_score {
+                    candidates.push(Candidate { score, a: (*a).clone(), b: (*b).clone() });
+                }
+            }
+        }
+    }
+
+    candidates.sort_by(|x, y| y.score.total_cmp(&x.score).then_with(|| (x.a.date, x.a.id).cmp(&(y.a.date, y.a.id))));
+    candidates
+}
+
+fn candidates_table(candidates: &[Candidate]) -> Table {
+    let mut table = Table::new(&[
+        "score", "mode", "amount", "a", "a_date", "a_place", "a_name", "b", "b_date", "b_place", "b_name",
+    ]);
+    for c in candidates {
+        table.push(vec![
+            json!(c.score), json!(c.a.mode), json!(c.a.amount),
+            json!(key(&c.a)), json!(c.a.date), json!(c.a.place), json!(c.a.name),
+            json!(key(&c.b)), json!(c.b.date), json!(c.b.place), json!(c.b.name),
+        ]);
+    }
+    table
+}
+
+/// Fields of a candidate side by side
+fn side_by_side(c: &Candidate, masters: &Masters) -> Table {
+    let fields = |m: &Money| vec![
+        json!(key(m)), json!(m.date), json!(m.amount), json!(m.place), json!(m.name), json!(m.comment),
+        json!(masters.category_name(m.category_id)), json!(masters.genre_name(m.genre_id)),
+        json!(m.from_account_id.or(m.to_account_id).and_then(|i| masters.accounts.get(&i)).map(|a| &a.name)),
+        json!(m.created),
+    ];
+    let names = ["id", "date", "amount", "place", "name", "comment", "category", "genre", "account", "created"];
+
+    let mut table = Table::new(&["", "a", "b"]);
+    for ((name, a), b) in names.iter().zip(fields(&c.a)).zip(fields(&c.b)) {
+        table.push(vec![json!(name), a, b]);
+    }
+    table
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Choice {
+    DeleteA,
+    DeleteB,
+    Keep,
+    Quit,
+}
+
+fn ask<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<Choice> {
+    loop {
+        write!(output, "Delete [a], delete [b], [k]eep both or [q]uit? ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(Choice::Quit);
+        }
+        match line.trim() {
+            "a" => return Ok(Choice::DeleteA),
+            "b" => return Ok(Choice::DeleteB),
+            "k" | "" => return Ok(Choice::Keep),
+            "q" => return Ok(Choice::Quit),
+            _ => continue,
+        }
+    }
+}
+
+fn run_interactive(api: &ZaimApi, masters: &Masters, candidates: &[Candidate]) -> Result<usize> {
+    let mut stdin = std::io::stdin().lock();
+    let mut stderr = std::io::stderr();
+    let mut deleted: HashSet<String> = HashSet::new();
+
+    for (i, c) in candidates.iter().enumerate() {
+        if deleted.contains(&key(&c.a)) || deleted.contains(&key(&c.b)) {
+            continue;
+        }
+        eprintln!("\n[{}/{}] score {}", i + 1, candidates.len(), c.score);
+        eprint!("{}", side_by_side(c, masters).render_table());
+
+        let target = match ask(&mut stdin, &mut stderr)? {
+            Choice::DeleteA => &c.a,
+            Choice::DeleteB => &c.b,
+            Choice::Keep => continue,
+            Choice::Quit => break,
+        };
+        api.delete_money(target.mode, target.id)
+            .map_err(|e| anyhow!("{} money records deleted\n{}: {}", deleted.len(), key(target), e))?;
+        deleted.insert(key(target));
+    }
+
+    Ok(deleted.len())
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DecisionsFile {
+    #[serde(default)]
+    pub decisions: Vec<Decision>,
+}
+
+/// `<mode>/<id>` of money records
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Decision {
+    pub delete: String,
+    pub keep: Option<String>,
+}
+
+impl DecisionsFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        config::load_toml(path)
+    }
+}
+
+/// Money records to delete with the candidates deciding them.
+/// Only records found as candidates can be deleted, and each of them only once
+/// without being kept by another decision.
+fn check_decisions<'a>(file: &DecisionsFile, candidates: &'a [Candidate]) -> Result<Vec<&'a Candidate>> {
+    let decided: Vec<&Candidate> = file.decisions.iter().map(|d| {
+        candidates.iter()
+            .find(|c| {
+                let pair = [key(&c.a), key(&c.b)];
+                pair.contains(&d.delete) && d.keep.as_ref().is_none_or(|k| k != &d.delete && pair.contains(k))
+            })
+            .ok_or_else(|| match &d.keep {
+                Some(k) => anyhow!("{} and {} are not a candidate", d.delete, k),
+                None => anyhow!("{} is not a candidate", d.delete),
+            })
+    }).collect::<Result<_>>()?;
+
+    let mut deleted: HashSet<&str> = HashSet::new();
+    for d in file.decisions.iter() {
+        if !deleted.insert(&d.delete) {
+            return Err(anyhow!("{} is deleted more than once", d.delete));
+        }
+    }
+    for (d, c) in file.decisions.iter().zip(decided.iter()) {
+        let keep = if key(&c.a) == d.delete { key(&c.b) } else { key(&c.a) };
+        if deleted.contains(keep.as_str()) {
+            return Err(anyhow!("{} is both deleted and kept", keep));
+        }
+    }
+
+    Ok(decided)
+}
+
+pub fn run(args: &DedupeArgs, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let decisions = args.decisions.as_deref().map(DecisionsFile::load).transpose()?;
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+
+    let api = session.api()?;
+    let money = api.money(&MoneyQuery {
+        start_date: args.start_date,
+        end_date: args.end_date,
+        ..Default::default()
+    })?;
+    let candidates = find_candidates(&money, args.max_days, args.min_score);
+
+    if args.interactive {
+        let masters = Masters::fetch(&api)?;
+        let n = run_interactive(&api, &masters, &candidates)?;
+        eprintln!("{} money records deleted", n);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let file = match decisions {
+        Some(f) => f,
+        None => {
+            candidates_table(&candidates).print(format)?;
+            eprintln!("{} candidates. Use --interactive or --decisions FILE to delete duplicates", candidates.len());
+            return Ok(ExitCode::SUCCESS);
+        },
+    };
+
+    let decided = check_decisions(&file, &candidates)?;
+    let mut table = Table::new(&["delete", "keep", "score", "status"]);
+    let row = |d: &Decision, c: &Candidate, status: &str| {
+        let keep = d.keep.clone().unwrap_or_else(|| if key(&c.a) == d.delete { key(&c.b) } else { key(&c.a) });
+        vec![json!(d.delete), json!(keep), json!(c.score), json!(status)]
+    };
+
+    if !args.commit {
+        for (d, c) in file.decisions.iter().zip(decided) {
+            table.push(row(d, c, "pending"));
+        }
+        table.print(format)?;
+        eprintln!("{} money records to delete. Run with --commit to delete them", file.decisions.len());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for (i, (d, c)) in file.decisions.iter().zip(decided).enumerate() {
+        let target = if key(&c.a) == d.delete { &c.a } else { &c.b };
+        if let Err(e) = api.delete_money(target.mode, target.id) {
+            table.print(format)?;
+            return Err(anyhow!("{} of {} money records deleted\n{}: {}", i, file.decisions.len(), d.delete, e));
+        }
+        table.push(row(d, c, "deleted"));
+    }
+    table.print(format)?;
+    eprintln!("{} money records deleted", file.decisions.len());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ask, check_decisions, find_candidates, similarity, Choice, DecisionsFile};
+    use zaim_cli::models::Money;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Starbucks", "STARBUCKS "), 1.0);
+        assert!(similarity("Starbucks Coffee", "Starbucks") > 0.6);
+        assert!(similarity("スターバックス", "スタバ") < 0.5);
+        assert_eq!(similarity("", "cafe"), 0.0);
+    }
+
+    #[test]
+    fn test_find_candidates() {
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-05", "amount": 480, "place": "Starbucks"},
+            {"id": 2, "mode": "payment", "date": "2026-01-06", "amount": 480, "place": "STARBUCKS"},
+            {"id": 3, "mode": "payment", "date": "2026-01-05", "amount": 480, "place": "Doutor"},
+            {"id": 4, "mode": "payment", "date": "2026-01-20", "amount": 480, "place": "Starbucks"},
+            {"id": 5, "mode": "income", "date": "2026-01-05", "amount": 480},
+            {"id": 6, "mode": "payment", "date": "2026-01-07", "amount": 100, "receipt_id": 9},
+            {"id": 7, "mode": "payment", "date": "2026-01-07", "amount": 100, "receipt_id": 9},
+        ])).unwrap();
+
+        let candidates = find_candidates(&money, 3, 0.5);
+        let pairs: Vec<(u64, u64, f64)> = candidates.iter().map(|c| (c.a.id, c.b.id, c.score)).collect();
+        assert_eq!(pairs, vec![(1, 2, 0.93)]);
+        assert_eq!(find_candidates(&money, 3, 0.0).len(), 3);
+    }
+
+    #[test]
+    fn test_check_decisions() {
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-05", "amount": 480, "place": "Starbucks"},
+            {"id": 2, "mode": "payment", "date": "2026-01-05", "amount": 480, "place": "Starbucks"},
+        ])).unwrap();
+        let candidates = find_candidates(&money, 3, 0.5);
+
+        let file: DecisionsFile = toml::from_str("[[decisions]]\nkeep = \"payment/1\"\ndelete = \"payment/2\"\n").unwrap();
+        assert_eq!(check_decisions(&file, &candidates).unwrap().len(), 1);
+        let file: DecisionsFile = toml::from_str("[[decisions]]\ndelete = \"payment/3\"\n").unwrap();
+        assert_eq!(check_decisions(&file, &candidates).unwrap_err().to_string(), "payment/3 is not a candidate");
+
+        let file: DecisionsFile = toml::from_str(
+            "[[decisions]]\nkeep = \"payment/1\"\ndelete = \"payment/2\"\n[[decisions]]\nkeep = \"payment/2\"\ndelete = \"payment/1\"\n"
+        ).unwrap();
+        assert_eq!(check_decisions(&file, &candidates).unwrap_err().to_string(), "payment/1 is both deleted and kept");
+        let file: DecisionsFile = toml::from_str(
+            "[[decisions]]\ndelete = \"payment/2\"\n[[decisions]]\ndelete = \"payment/2\"\n"
+        ).unwrap();
+        assert_eq!(check_decisions(&file, &candidates).unwrap_err().to_string(), "payment/2 is deleted more than once");
+    }
+
+    #[test]
+    fn test_ask() {
+        let mut output = Vec::new();
+        assert_eq!(ask(&mut "x\nb\n".as_bytes(), &mut output).unwrap(), Choice::DeleteB);
+        assert_eq!(ask(&mut "".as_bytes(), &mut output).unwrap(), Choice::Quit);
+    }
+}
@@ -0,0 +1,429 @@
+//! export subcommand: convert money records into plain text accounting formats
+//!
+//! Account names are derived from Zaim accounts and categories/genres, and can be
+//! overridden by a mapping file.
+//!
+//! ```toml
+//! commodity = "JPY"
+//!
+//! [roots]
+//! assets = "Assets"
+//! expenses = "Expenses"
+//! income = "Income"
+//!
+//! # Keys are names or ids in Zaim
+//! [accounts]
+//! "お財布" = "Assets:Cash"
+//! "クレジットカード" = "Liabilities:CreditCard"
+//!
+//! [categories]
+//! "食費" = "Expenses:Food"
+//!
+//! [genres]
+//! "カフェ" = "Expenses:Food:Cafe"
+//! ```
+
+use crate::names::{FilterArgs, Masters};
+use crate::output::display_width;
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// Format of plain text accounting
+    #[arg(long, value_enum)]
+    pub format: ExportFormat,
+
+    /// Mapping file of account names (toml)
+    #[arg(long, value_name = "FILE")]
+    pub mapping: Option<PathBuf>,
+
+    /// Export money records dated on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Export money records dated on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub end_date: Option<NaiveDate>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct Mapping {
+    /// Commodity used when a record has no currency code [default: defaults.currency of configuration, or JPY]
+    pub commodity: Option<String>,
+    pub roots: Roots,
+    pub accounts: HashMap<String, String>,
+    pub categories: HashMap<String, String>,
+    pub genres: HashMap<String, String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct Roots {
+    pub assets: String,
+    pub expenses: String,
+    pub income: String,
+}
+
+impl Default for Roots {
+    fn default() -> Self {
+        Self {
+            assets: String::from("Assets"),
+            expenses: String::from("Expenses"),
+            income: String::from("Income"),
+        }
+    }
+}
+
+impl Mapping {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read {}\n{}", path.display(), e))?;
+        toml::from_str(&data).map_err(|e| anyhow!("failed to parse {}\n{}", path.display(), e))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Posting {
+    account: String,
+    amount: i64,
+    commodity: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Transaction {
+    date: NaiveDate,
+    payee: String,
+    narration: String,
+    /// "<mode>/<id>" of money records
+    ids: Vec<String>,
+    postings: Vec<Posting>,
+}
+
+/// Replace characters not allowed in account names. Components of beancount
+/// accounts consist of `[A-Za-z0-9-]` and non-ascii characters, and start with
+/// a capital letter, a digit or a non-ascii character
+fn sanitize(name: &str) -> String {
+    let s: String = name.trim().chars()
+        .map(|c| if c.is_whitespace() || (c.is_ascii() && !c.is_ascii_alphanumeric() && c != '-') { '-' } else { c })
+        .collect();
+    let mut s = s.trim_start_matches('-').to_string();
+    if let Some(first) = s.chars().next().filter(|c| c.is_ascii_lowercase()) {
+        s.replace_range(..1, &first.to_ascii_uppercase().to_string());
+    }
+    if s.is_empty() { String::from("Unknown") } else { s }
+}
+
+struct Namer<'a> {
+    masters: &'a Masters,
+    mapping: &'a Mapping,
+}
+
+impl Namer<'_> {
+    fn mapped(map: &HashMap<String, String>, id: u64, name: Option<&str>) -> Option<String> {
+        map.get(&id.to_string())
+            .or_else(|| name.and_then(|n| map.get(n)))
+            .cloned()
+    }
+
+    fn account(&self, id: Option<u64>) -> String {
+        let id = match id {
+            Some(i) => i,
+            None => return format!("{}:Unknown", self.mapping.roots.assets),
+        };
+        let name = self.masters.accounts.get(&id).map(|a| a.name.as_str());
+        Self::mapped(&self.mapping.accounts, id, name).unwrap_or_else(|| {
+            format!("{}:{}", self.mapping.roots.assets, sanitize(name.unwrap_or(&id.to_string())))
+        })
+    }
+
+    fn category(&self, mode: Mode, id: Option<u64>) -> String {
+        let root = match mode {
+            Mode::Income => &self.mapping.roots.income,
+            _ => &self.mapping.roots.expenses,
+        };
+        let id = match id {
+            Some(i) => i,
+            None => return format!("{}:Unknown", root),
+        };
+        let name = self.masters.categories.get(&id).map(|c| c.name.as_str());
+        Self::mapped(&self.mapping.categories, id, name).unwrap_or_else(|| {
+            format!("{}:{}", root, sanitize(name.unwrap_or(&id.to_string())))
+        })
+    }
+
+    /// Genre is put under its category
+    fn genre(&self, mode: Mode, category_id: Option<u64>, genre_id: Option<u64>) -> String {
+        let genre = genre_id.and_then(|i| self.masters.genres.get(&i));
+        let id = match genre_id {
+            Some(i) => i,
+            None => return self.category(mode, category_id),
+        };
+        Self::mapped(&self.mapping.genres, id, genre.map(|g| g.name.as_str())).unwrap_or_else(|| {
+            let category = self.category(mode, category_id.or(genre.map(|g| g.category_id)));
+            match genre {
+                Some(g) => format!("{}:{}", category, sanitize(&g.name)),
+                None => category,
+            }
+        })
+    }
+
+    fn commodity(&self, money: &Money) -> String {
+        money.currency_code.clone()
+            .or_else(|| self.mapping.commodity.clone())
+            .unwrap_or_else(|| String::from("JPY"))
+    }
+}
+
+fn non_empty(s: &Option<String>) -> Option<&str> {
+    s.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Convert money records into transactions.
+/// Payments sharing receipt_id are put into a transaction.
+fn build_transactions(money: &[Money], masters: &Masters, mapping: &Mapping) -> Vec<Transaction> {
+    let namer = Namer { masters, mapping };
+
+    let mut groups: Vec<Vec<&Money>> = Vec::new();
+    let mut receipts: HashMap<u64, usize> = HashMap::new();
+    for m in money {
+        match m.receipt_id.filter(|_| m.mode == Mode::Payment) {
+            Some(r) if receipts.contains_key(&r) => groups[receipts[&r]].push(m),
+            Some(r) => {
+                receipts.insert(r, groups.len());
+                groups.push(vec![m]);
+            },
+            None => groups.push(vec![m]),
+        }
+    }
+
+    let mut transactions: Vec<Transaction> = groups.into_iter().map(|items| {
+        let first = items[0];
+        let payee = non_empty(&first.place)
+            .or(non_empty(&first.name))
+            .map(String::from)
+            .unwrap_or_else(|| namer.genre(first.mode, first.category_id, first.genre_id));
+        let narration = items.iter()
+            .filter_map(|m| non_empty(&m.name).or(non_empty(&m.comment)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut postings = Vec::new();
+        // Credits of payments are summed by account
+        let mut credits: BTreeMap<String, (i64, String)> = BTreeMap::new();
+        for m in items.iter() {
+            let commodity = namer.commodity(m);
+            let (debit, credit) = match m.mode {
+                Mode::Payment => (namer.genre(m.mode, m.category_id, m.genre_id), namer.account(m.from_account_id)),
+                Mode::Income => (namer.account(m.to_account_id), namer.genre(m.mode, m.category_id, m.genre_id)),
+                Mode::Transfer => (namer.account(m.to_account_id), namer.account(m.from_account_id)),
+            };
+            postings.push(Posting { account: debit, amount: m.amount, commodity: commodity.clone() });
+            let entry = credits.entry(credit).or_insert((0, commodity));
+            entry.0 -= m.amount;
+        }
+        for (account, (amount, commodity)) in credits {
+            postings.push(Posting { account, amount, commodity });
+        }
+
+        Transaction {
+            date: first.date,
+            payee,
+            narration,
+            ids: items.iter().map(|m| format!("{}/{}", m.mode, m.id)).collect(),
+            postings,
+        }
+    }).collect();
+
+    transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.ids.cmp(&b.ids)));
+    transactions
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn render(transactions: &[Transaction], format: ExportFormat) -> String {
+    let width = transactions.iter()
+        .flat_map(|t| t.postings.iter().map(|p| display_width(&p.account)))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+
+    if format == ExportFormat::Beancount {
+        let accounts: BTreeSet<&str> = transactions.iter()
+            .flat_map(|t| t.postings.iter().map(|p| p.account.as_str()))
+            .collect();
+        if let Some(first) = transactions.first() {
+            for a in accounts {
+                let _ = writeln!(out, "{} open {}", first.date, a);
+            }
+            out.push('\n');
+        }
+    }
+
+    for t in transactions {
+        match format {
+            ExportFormat::Beancount => {
+                let _ = writeln!(out, "{} * {} {}", t.date, quote(&t.payee), quote(&t.narration));
+                let _ = writeln!(out, "  zaim_id: {}", quote(&t.ids.join(",")));
+            },
+            ExportFormat::Ledger | ExportFormat::Hledger => {
+                let description = match t.narration.as_str() {
+                    "" => t.payee.clone(),
+                    n => format!("{} | {}", t.payee, n),
+                };
+                let _ = writeln!(out, "{} * {}", t.date, description);
+                let sep = if format == ExportFormat::Ledger { ": " } else { ":" };
+                let _ = writeln!(out, "    ; zaim-id{}{}", sep, t.ids.join(","));
+            },
+        }
+
+        let indent = if format == ExportFormat::Beancount { "  " } else { "    " };
+        for p in t.postings.iter() {
+            let pad = width - display_width(&p.account) + 2;
+            let _ = writeln!(out, "{}{}{}{} {}", indent, p.account, " ".repeat(pad), p.amount, p.commodity);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+pub fn run(args: &ExportArgs, session: &Session) -> Result<ExitCode> {
+    let mut mapping = match &args.mapping {
+        Some(p) => Mapping::load(p)?,
+        None => Mapping::default(),
+    };
+    mapping.commodity = mapping.commodity.or_else(|| session.defaults.currency.clone());
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+
+    let api = session.api()?;
+    let masters = Masters::fetch(&api)?;
+    let mut query = MoneyQuery {
+        start_date: args.start_date,
+        end_date: args.end_date,
+        ..Default::default()
+    };
+    args.filter.apply(&mut query, &masters)?;
+    let money = api.money(&query)?;
+
+    print!("{}", render(&build_transactions(&money, &masters, &mapping), args.format));
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_transactions, render, sanitize, ExportFormat, Mapping};
+    use crate::names::Masters;
+    use zaim_cli::models::{Account, Category, Genre, Money};
+
+    use serde_json::json;
+
+    fn prepare() -> (Vec<Money>, Masters) {
+        let masters = Masters::new(
+            serde_json::from_value::<Vec<Category>>(json!([
+                {"id": 101, "name": "食費", "mode": "payment"},
+                {"id": 11, "name": "給与所得", "mode": "income"},
+            ])).unwrap(),
+            serde_json::from_value::<Vec<Genre>>(json!([
+                {"id": 10101, "category_id": 101, "name": "食料品"},
+                {"id": 10102, "category_id": 101, "name": "カフェ"},
+            ])).unwrap(),
+            serde_json::from_value::<Vec<Account>>(json!([
+                {"id": 1, "name": "お財布"},
+                {"id": 2, "name": "my bank"},
+            ])).unwrap(),
+        );
+        let money = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-05", "amount": 480, "category_id": 101,
+             "genre_id": 10102, "from_account_id": 1, "place": "Starbucks", "receipt_id": 0},
+            {"id": 2, "mode": "payment", "date": "2026-01-06", "amount": 1200, "category_id": 101,
+             "genre_id": 10101, "from_account_id": 1, "place": "スーパー", "name": "野菜", "receipt_id": 9},
+            {"id": 3, "mode": "payment", "date": "2026-01-06", "amount": 300, "category_id": 101,
+             "genre_id": 10102, "from_account_id": 1, "place": "スーパー", "name": "コーヒー", "receipt_id": 9},
+            {"id": 4, "mode": "income", "date": "2026-01-25", "amount": 300000, "category_id": 11,
+             "to_account_id": 2},
+        ])).unwrap();
+        (money, masters)
+    }
+
+    #[test]
+    fn test_build_transactions() {
+        let (money, masters) = prepare();
+        let mut mapping = Mapping::default();
+        mapping.genres.insert(String::from("カフェ"), String::from("Expenses:Cafe"));
+
+        let transactions = build_transactions(&money, &masters, &mapping);
+        assert_eq!(transactions.len(), 3);
+
+        let receipt = &transactions[1];
+        assert_eq!(receipt.ids, vec!["payment/2", "payment/3"]);
+        assert_eq!(receipt.narration, "野菜, コーヒー");
+        let postings: Vec<(&str, i64)> = receipt.postings.iter().map(|p| (p.account.as_str(), p.amount)).collect();
+        assert_eq!(postings, vec![
+            ("Expenses:食費:食料品", 1200),
+            ("Expenses:Cafe", 300),
+            ("Assets:お財布", -1500),
+        ]);
+
+        let income = &transactions[2];
+        assert_eq!(income.postings[0].account, "Assets:My-bank");
+        assert_eq!(income.postings[1].account, "Income:給与所得");
+    }
+
+    #[test]
+    fn test_render() {
+        let (money, masters) = prepare();
+        let transactions = build_transactions(&money[..1], &masters, &Mapping::default());
+
+        assert_eq!(render(&transactions, ExportFormat::Ledger), "\
+2026-01-05 * Starbucks
+    ; zaim-id: payment/1
+    Expenses:食費:カフェ  480 JPY
+    Assets:お財布         -480 JPY
+
+");
+        assert_eq!(render(&transactions, ExportFormat::Beancount), "\
+2026-01-05 open Assets:お財布
+2026-01-05 open Expenses:食費:カフェ
+
+2026-01-05 * \"Starbucks\" \"\"
+  zaim_id: \"payment/1\"
+  Expenses:食費:カフェ  480 JPY
+  Assets:お財布         -480 JPY
+
+");
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("my bank: main"), "My-bank--main");
+        assert_eq!(sanitize(" "), "Unknown");
+        assert_eq!(sanitize("Tom's Cafe & Bar/No.1"), "Tom-s-Cafe---Bar-No-1");
+        assert_eq!(sanitize("(株)食品"), "株-食品");
+        assert_eq!(sanitize("日用雑貨"), "日用雑貨");
+    }
+}
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use rand::distributions::Distribution;
 use std::time::{SystemTime, Duration, UNIX_EPOCH};
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
+use std::collections::HashMap;
 
 #[allow(dead_code)]
 pub fn type_of<T>(_: &T) -> &'static str {
@@ -34,3 +35,22 @@ pub fn percent_encode(string: &str) -> String {
     // utf8_percent_encode(string, NON_ALPHANUMERIC).to_string()
     utf8_percent_encode(string, FRAGMENT).to_string()
 }
+
+/// Characters other than unreserved ones of RFC 3986
+const NON_UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Percent encoding of OAuth 1.0 parameters (RFC 5849 3.6)
+pub fn percent_encode_rfc3986(string: &str) -> String {
+    utf8_percent_encode(string, NON_UNRESERVED).to_string()
+}
+
+/// application/x-www-form-urlencoded body of parameters sorted by key,
+/// encoded in the same way as the signature base string
+pub fn form_urlencode(params: &HashMap<String, String>) -> String {
+    let mut keys: Vec<&String> = params.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|k| format!("{}={}", percent_encode_rfc3986(k), percent_encode_rfc3986(&params[k])))
+        .collect::<Vec<_>>()
+        .join("&")
+}
@@ -0,0 +1,864 @@
+//! import subcommand: create money records from bank or credit card statements
+//!
+//! An import profile describes statements of an account.
+//!
+//! ```toml
+//! encoding = "shift_jis"
+//! date_format = "%Y/%m/%d"
+//! # Zaim account of the statement (name or id). defaults.account of configuration if omitted
+//! account = "銀行"
+//!
+//! # Columns of csv. Header names or 0-based indexes
+//! [columns]
+//! date = "取引日"
+//! withdrawal = "お引出し"
+//! deposit = "お預入れ"
+//! place = "摘要"
+//!
+//! [payment]
+//! category = "食費"
+//! genre = "食料品"
+//!
+//! [income]
+//! category = "給与所得"
+//!
+//! # The other account of transfers without it (e.g. XFER of OFX)
+//! [transfer]
+//! account = "お財布"
+//! ```
+//!
+//! Imported transactions are recorded in a state file, and skipped in later imports.
+
+use crate::config;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, NewIncome, NewMoney, NewPayment, NewTransfer};
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde_json::json;
+
+const STATE_FILE: &str = "imported.json";
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ImportCommand {
+    /// Import a csv statement
+    Csv(ImportArgs),
+    /// Import an OFX or QFX statement
+    Ofx(ImportArgs),
+    /// Import a QIF statement
+    Qif(ImportArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ImportArgs {
+    /// Import profile describing the statement (toml)
+    #[arg(long = "profile", value_name = "FILE")]
+    pub import_profile: PathBuf,
+
+    /// Create money records. Without this, records are only previewed
+    #[arg(long)]
+    pub commit: bool,
+
+    /// File recording imported transactions
+    /// [default: $XDG_DATA_HOME/zaim-cli/<profile>/imported.json]
+    #[arg(long, value_name = "FILE")]
+    pub state: Option<PathBuf>,
+
+    /// Statement file
+    pub file: PathBuf,
+}
+
+/// Sign of amounts in statements
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountSign {
+    /// Negative amounts are payments (e.g. bank statements)
+    #[default]
+    PaymentNegative,
+    /// Positive amounts are payments (e.g. credit card statements)
+    PaymentPositive,
+}
+
+impl AmountSign {
+    /// Amount whose negative means money going out of the account
+    fn apply(&self, amount: i64) -> i64 {
+        match self {
+            AmountSign::PaymentNegative => amount,
+            AmountSign::PaymentPositive => -amount,
+        }
+    }
+}
+
+/// Header name or 0-based index of a column
+#[derive(serde::Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Columns {
+    pub date: Column,
+    /// Signed amount. Use either this or withdrawal/deposit
+    pub amount: Option<Column>,
+    pub withdrawal: Option<Column>,
+    pub deposit: Option<Column>,
+    pub place: Option<Column>,
+    pub name: Option<Column>,
+    pub comment: Option<Column>,
+}
+
+/// Category (and genre for payments) of imported records. Names or ids
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub category: String,
+    pub genre: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TransferTarget {
+    /// Name or id
+    pub account: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportProfile {
+    /// Label of the encoding (e.g. "utf-8", "shift_jis")
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Lines of csv skipped before the header
+    #[serde(default)]
+    pub skip_rows: usize,
+    #[serde(default = "default_has_header")]
+    pub has_header: bool,
+    /// Date format of csv (default: %Y-%m-%d) and QIF (default: guessed)
+    pub date_format: Option<String>,
+    #[serde(default)]
+    pub amount_sign: AmountSign,
+    /// Zaim account of the statement. Name or id [default: defaults.account of configuration]
+    pub account: Option<String>,
+    /// Required for csv
+    pub columns: Option<Columns>,
+    pub payment: Option<Target>,
+    pub income: Option<Target>,
+    pub transfer: Option<TransferTarget>,
+}
+
+fn default_encoding() -> String {
+    String::from("utf-8")
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+impl ImportProfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let profile: Self = config::load_toml(path)?;
+
+        match profile.columns.as_ref().map(|c| (&c.amount, &c.withdrawal, &c.deposit)) {
+            None | Some((Some(_), None, None)) | Some((None, Some(_), _)) | Some((None, _, Some(_))) => Ok(profile),
+            _ => Err(anyhow!("{}: set either columns.amount or columns.withdrawal/deposit", path.display())),
+        }
+    }
+}
+
+/// Money record read from a statement
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Line in the statement
+    pub line: u64,
+    /// Key to find transactions imported before (e.g. FITID of OFX)
+    pub key: Option<String>,
+    pub date: NaiveDate,
+    pub mode: Mode,
+    /// Always positive
+    pub amount: i64,
+    /// Whether money goes out of the statement account
+    pub outgoing: bool,
+    /// The other account of a transfer. Name or id
+    pub counterpart: Option<String>,
+    pub place: Option<String>,
+    pub name: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Entry {
+    /// Payment or income by the sign of amount
+    fn new(line: u64, date: NaiveDate, signed: i64) -> Self {
+        Self {
+            line,
+            key: None,
+            date,
+            mode: if signed < 0 { Mode::Payment } else { Mode::Income },
+            amount: signed.abs(),
+            outgoing: signed < 0,
+            counterpart: None,
+            place: None,
+            name: None,
+            comment: None,
+        }
+    }
+}
+
+/// Parse amounts like "1,200", "¥1,200", "-1200" and "△1,200" (negative)
+fn parse_amount(s: &str) -> Result<Option<i64>> {
+    let s: String = s.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, ',' | '¥' | '￥' | '\\' | '円'))
+        .collect();
+    let (negative, digits) = match s.strip_prefix(['△', '▲', '-']) {
+        Some(d) => (true, d),
+        None => (false, s.as_str()),
+    };
+    if digits.is_empty() {
+        return Ok(None);
+    }
+    // Zaim records integer amounts, so that fractions are not rounded silently
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(anyhow!("invalid amount: {}", s));
+    }
+    if fraction.bytes().any(|b| b != b'0') {
+        return Err(anyhow!("fractional amount is not supported: {}", s));
+    }
+    let value: i64 = integer.parse().map_err(|_| anyhow!("invalid amount: {}", s))?;
+    Ok(Some(if negative { -value } else { value }))
+}
+
+fn decode(bytes: &[u8], label: &str) -> Result<String> {
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| anyhow!("unknown encoding: {}", label))?;
+    let (text, _, has_errors) = encoding.decode(bytes);
+    if has_errors {
+        return Err(anyhow!("failed to decode as {}", encoding.name()));
+    }
+    Ok(text.into_owned())
+}
+
+/// Key of a transaction without id, made of date, amount, payee and the order
+/// among transactions having them in the statement
+fn content_key(occurrences: &mut HashMap<String, usize>, date: NaiveDate, signed: i64, payee: Option<&str>) -> String {
+    let base = format!("{}|{}|{}", date, signed, payee.unwrap_or(""));
+    let n = occurrences.entry(base.clone()).or_insert(0);
+    *n += 1;
+    format!("{}|{}", base, n)
+}
+
+/// Read entries from a csv statement. Rows without date are skipped.
+pub fn read_csv(bytes: &[u8], profile: &ImportProfile) -> Result<Vec<Entry>> {
+    let c = profile.columns.as_ref().ok_or_else(|| anyhow!("columns are required for csv"))?;
+    let text = decode(bytes, &profile.encoding)?;
+    let body: String = text.split_inclusive('\n').skip(profile.skip_rows).collect();
+    let date_format = profile.date_format.as_deref().unwrap_or("%Y-%m-%d");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(profile.has_header)
+        .delimiter(u8::try_from(profile.delimiter).map_err(|_| anyhow!("delimiter must be ascii"))?)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+
+    let headers = if profile.has_header { Some(reader.headers()?.clone()) } else { None };
+    let index = |column: Option<&Column>| -> Result<Option<usize>> {
+        match column {
+            None => Ok(None),
+            Some(Column::Index(i)) => Ok(Some(*i)),
+            Some(Column::Name(n)) => headers.as_ref()
+                .and_then(|h| h.iter().position(|x| x == n))
+                .map(Some)
+                .ok_or_else(|| anyhow!("column not found: {}", n)),
+        }
+    };
+    let date_i = index(Some(&c.date))?;
+    let (amount_i, withdrawal_i, deposit_i) = (index(c.amount.as_ref())?, index(c.withdrawal.as_ref())?, index(c.deposit.as_ref())?);
+    let (place_i, name_i, comment_i) = (index(c.place.as_ref())?, index(c.name.as_ref())?, index(c.comment.as_ref())?);
+
+    let mut entries = Vec::new();
+    let mut occurrences = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or(0) + profile.skip_rows as u64;
+        let cell = |i: Option<usize>| i.and_then(|i| record.get(i)).filter(|s| !s.is_empty());
+
+        let date = match cell(date_i) {
+            Some(d) => NaiveDate::parse_from_str(d, date_format)
+                .map_err(|e| anyhow!("line {}: invalid date {}: {}", line, d, e))?,
+            None => continue,
+        };
+        let amount = |i: Option<usize>| -> Result<i64> {
+            Ok(parse_amount(cell(i).unwrap_or("")).map_err(|e| anyhow!("line {}: {}", line, e))?.unwrap_or(0))
+        };
+        let signed = match amount_i {
+            Some(_) => profile.amount_sign.apply(amount(amount_i)?),
+            None => amount(deposit_i)? - amount(withdrawal_i)?,
+        };
+        if signed == 0 {
+            continue;
+        }
+
+        let (place, name) = (cell(place_i), cell(name_i));
+        entries.push(Entry {
+            key: Some(content_key(&mut occurrences, date, signed, place.or(name))),
+            place: place.map(String::from),
+            name: name.map(String::from),
+            comment: cell(comment_i).map(String::from),
+            ..Entry::new(line, date, signed)
+        });
+    }
+
+    Ok(entries)
+}
+
+fn unescape_sgml(s: &str) -> String {
+    s.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+/// Read entries from an OFX (or QFX) statement of both SGML (1.x) and XML (2.x).
+/// FITID is used as the key.
+pub fn read_ofx(bytes: &[u8], profile: &ImportProfile) -> Result<Vec<Entry>> {
+    let text = decode(bytes, &profile.encoding)?;
+    let mut pieces = text.split('<');
+    let mut line = 1 + pieces.next().unwrap_or("").matches('\n').count() as u64;
+
+    let mut entries = Vec::new();
+    let mut current: Option<(u64, HashMap<String, String>)> = None;
+    for piece in pieces {
+        let (tag, value) = piece.split_once('>').unwrap_or((piece, ""));
+        match tag.trim().to_ascii_uppercase().as_str() {
+            "STMTTRN" => current = Some((line, HashMap::new())),
+            "/STMTTRN" => if let Some((l, fields)) = current.take() {
+                if let Some(e) = ofx_entry(l, &fields, profile).map_err(|e| anyhow!("line {}: {}", l, e))? {
+                    entries.push(e);
+                }
+            },
+            t if !t.starts_with('/') => if let Some((_, fields)) = current.as_mut() {
+                // The first value of a tag wins, e.g. NAME of the transaction over a later NAME in PAYEE
+                fields.entry(t.to_string()).or_insert_with(|| unescape_sgml(value.trim()));
+            },
+            _ => (),
+        }
+        line += piece.matches('\n').count() as u64;
+    }
+
+    Ok(entries)
+}
+
+fn ofx_entry(line: u64, fields: &HashMap<String, String>, profile: &ImportProfile) -> Result<Option<Entry>> {
+    let field = |k: &str| fields.get(k).map(String::as_str).filter(|s| !s.is_empty());
+
+    let posted = field("DTPOSTED").ok_or_else(|| anyhow!("DTPOSTED not found"))?;
+    let date = NaiveDate::parse_from_str(posted.get(..8).unwrap_or(posted), "%Y%m%d")
+        .map_err(|e| anyhow!("invalid DTPOSTED {}: {}", posted, e))?;
+    let amount = field("TRNAMT").map(parse_amount).transpose()?.flatten()
+        .ok_or_else(|| anyhow!("TRNAMT not found"))?;
+    let signed = profile.amount_sign.apply(amount);
+    if signed == 0 {
+        return Ok(None);
+    }
+
+    let mut entry = Entry {
+        key: field("FITID").map(String::from),
+        place: field("NAME").map(String::from),
+        comment: field("MEMO").map(String::from),
+        ..Entry::new(line, date, signed)
+    };
+    if field("TRNTYPE").is_some_and(|t| t.eq_ignore_ascii_case("XFER")) {
+        entry.mode = Mode::Transfer;
+    }
+    Ok(Some(entry))
+}
+
+/// QIF dates like "01/05/2026", "1/5'26" and "2026-01-05"
+fn parse_qif_date(s: &str, format: Option<&str>) -> Result<NaiveDate> {
+    let s = s.replace('\'', "/").replace(' ', "");
+    let formats = match format {
+        Some(f) => vec![f],
+        None => vec!["%m/%d/%y", "%m/%d/%Y", "%Y-%m-%d", "%Y/%m/%d"],
+    };
+    formats.iter()
+        .find_map(|f| NaiveDate::parse_from_str(&s, f).ok())
+        .ok_or_else(|| anyhow!("invalid date: {}", s))
+}
+
+/// `!Type:` sections of lists instead of transactions
+const QIF_LIST_TYPES: [&str; 5] = ["Cat", "Class", "Memorized", "Prices", "Security"];
+
+/// Read entries from a QIF statement. QIF has no id of transactions, so the key is
+/// made of the content by `content_key`.
+pub fn read_qif(bytes: &[u8], profile: &ImportProfile) -> Result<Vec<Entry>> {
+    let text = decode(bytes, &profile.encoding)?;
+    let mut entries = Vec::new();
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    let mut fields: Vec<(char, &str)> = Vec::new();
+    let mut start = 0;
+    // Records of accounts, categories and so on are in sections other than `!Type:` of transactions
+    let mut in_transactions = false;
+
+    for (i, raw) in text.lines().enumerate() {
+        let l = raw.trim();
+        if let Some(header) = l.strip_prefix('!') {
+            // Options such as "!Option:AutoSwitch" do not start a section
+            if !header.starts_with("Option:") && !header.starts_with("Clear:") {
+                in_transactions = header.strip_prefix("Type:")
+                    .is_some_and(|t| !QIF_LIST_TYPES.iter().any(|l| t.trim().eq_ignore_ascii_case(l)));
+                fields.clear();
+            }
+            continue;
+        }
+        if l.is_empty() || !in_transactions {
+            continue;
+        }
+        if fields.is_empty() {
+            start = i as u64 + 1;
+        }
+        if !l.starts_with('^') {
+            let mut chars = l.chars();
+            let code = chars.next().unwrap();
+            fields.push((code, chars.as_str().trim()));
+            continue;
+        }
+
+        let field = |c: char| fields.iter().find(|(k, v)| *k == c && !v.is_empty()).map(|(_, v)| *v);
+        let date = parse_qif_date(field('D').ok_or_else(|| anyhow!("line {}: date not found", start))?,
+                                  profile.date_format.as_deref())
+            .map_err(|e| anyhow!("line {}: {}", start, e))?;
+        let amount = field('T').or(field('U')).map(parse_amount).transpose()
+            .map_err(|e| anyhow!("line {}: {}", start, e))?
+            .flatten()
+            .ok_or_else(|| anyhow!("line {}: amount not found", start))?;
+        let signed = profile.amount_sign.apply(amount);
+        let (payee, memo, category) = (field('P'), field('M'), field('L'));
+        fields.clear();
+        if signed == 0 {
+            continue;
+        }
+
+        let mut entry = Entry {
+            key: Some(content_key(&mut occurrences, date, signed, payee)),
+            place: payee.map(String::from),
+            comment: memo.map(String::from),
+            ..Entry::new(start, date, signed)
+        };
+        // Category in brackets is the other account of a transfer
+        if let Some(account) = category.and_then(|c| c.strip_prefix('[')).and_then(|c| c.strip_suffix(']')) {
+            entry.mode = Mode::Transfer;
+            entry.counterpart = Some(account.to_string());
+        }
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Ids of account, categories and genre for imported records
+#[derive(Debug, Default)]
+struct Targets {
+    account_id: u64,
+    payment: Option<(u64, u64)>,
+    income: Option<u64>,
+    transfer: Option<u64>,
+}
+
+impl Targets {
+    fn resolve(profile: &ImportProfile, masters: &Masters) -> Result<Self> {
+        let payment = match &profile.payment {
+            Some(t) => {
+                let category_id = masters.category_id(Mode::Payment, &t.category)?;
+                let genre = t.genre.as_deref().ok_or_else(|| anyhow!("payment.genre is required"))?;
+                Some((category_id, masters.genre_id(Some(category_id), genre)?))
+            },
+            None => None,
+        };
+        let income = match &profile.income {
+            Some(t) => Some(masters.category_id(Mode::Income, &t.category)?),
+            None => None,
+        };
+        let transfer = match &profile.transfer {
+            Some(t) => Some(masters.account_id(&t.account)?),
+            None => None,
+        };
+
+        Ok(Self {
+            account_id: masters.account_id(profile.account.as_deref()
+                .ok_or_else(|| anyhow!("account is required in the import profile or defaults.account"))?)?,
+            payment,
+            income,
+            transfer,
+        })
+    }
+
+    fn request(&self, e: &Entry, masters: &Masters) -> Result<NewMoney> {
+        match e.mode {
+            Mode::Payment => {
+                let (category_id, genre_id) = self.payment
+                    .ok_or_else(|| anyhow!("line {}: payment needs [payment] in the profile", e.line))?;
+                Ok(NewMoney::Payment(NewPayment {
+                    date: e.date,
+                    amount: e.amount,
+                    category_id,
+                    genre_id,
+                    from_account_id: Some(self.account_id),
+                    name: e.name.clone(),
+                    place: e.place.clone(),
+                    comment: e.comment.clone(),
+                }))
+            },
+            Mode::Income => {
+                let category_id = self.income
+                    .ok_or_else(|| anyhow!("line {}: income needs [income] in the profile", e.line))?;
+                Ok(NewMoney::Income(NewIncome {
+                    date: e.date,
+                    amount: e.amount,
+                    category_id,
+                    to_account_id: Some(self.account_id),
+                    place: e.place.clone().or(e.name.clone()),
+                    comment: e.comment.clone(),
+                }))
+            },
+            Mode::Transfer => {
+                let other = match e.counterpart.as_deref() {
+                    Some(key) => masters.account_id(key).map_err(|err| anyhow!("line {}: {}", e.line, err))?,
+                    None => self.transfer
+                        .ok_or_else(|| anyhow!("line {}: transfer needs [transfer] in the profile", e.line))?,
+                };
+                let (from_account_id, to_account_id) = match e.outgoing {
+                    true => (self.account_id, other),
+                    false => (other, self.account_id),
+                };
+                Ok(NewMoney::Transfer(NewTransfer {
+                    date: e.date,
+                    amount: e.amount,
+                    from_account_id,
+                    to_account_id,
+                    comment: e.comment.clone().or(e.place.clone()),
+                }))
+            },
+        }
+    }
+}
+
+/// Ids of money records created by keys of transactions, for each account id
+type ImportState = BTreeMap<String, BTreeMap<String, u64>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Status {
+    New,
+    /// Imported before
+    Imported(u64),
+    /// Same key as a former transaction in the statement
+    Duplicate,
+    Created(u64),
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::New => "new",
+            Status::Imported(_) => "imported",
+            Status::Duplicate => "duplicate",
+            Status::Created(_) => "created",
+        }
+    }
+
+    fn id(&self) -> Option<u64> {
+        match self {
+            Status::Imported(i) | Status::Created(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Find transactions imported before or repeated in the statement
+fn check_imported(entries: &[Entry], imported: Option<&BTreeMap<String, u64>>) -> Vec<Status> {
+    let mut seen = HashSet::new();
+    entries.iter().map(|e| match e.key.as_ref() {
+        Some(k) if !seen.insert(k) => Status::Duplicate,
+        Some(k) => match imported.and_then(|m| m.get(k)) {
+            Some(id) => Status::Imported(*id),
+            None => Status::New,
+        },
+        None => Status::New,
+    }).collect()
+}
+
+fn entries_table(entries: &[Entry], statuses: &[Status]) -> Table {
+    let mut table = Table::new(&["line", "date", "mode", "amount", "place", "name", "comment", "status", "id"]);
+    for (e, s) in entries.iter().zip(statuses) {
+        table.push(vec![
+            json!(e.line), json!(e.date), json!(e.mode), json!(e.amount),
+            json!(e.place), json!(e.name), json!(e.comment), json!(s.as_str()), json!(s.id()),
+        ]);
+    }
+    table
+}
+
+/// Read entries from a statement
+type Reader = fn(&[u8], &ImportProfile) -> Result<Vec<Entry>>;
+
+pub fn run(command: &ImportCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let (args, read): (_, Reader) = match command {
+        ImportCommand::Csv(args) => (args, read_csv),
+        ImportCommand::Ofx(args) => (args, read_ofx),
+        ImportCommand::Qif(args) => (args, read_qif),
+    };
+    let mut profile = ImportProfile::load(&args.import_profile)?;
+    profile.account = profile.account.or_else(|| session.defaults.account.clone());
+    let bytes = std::fs::read(&args.file)
+        .map_err(|e| anyhow!("failed to read {}\n{}", args.file.display(), e))?;
+    let entries = read(&bytes, &profile)?;
+
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let state_path = match &args.state {
+        Some(p) => p.clone(),
+        None => session.data_dir()?.join(STATE_FILE),
+    };
+    import(&session.api()?, &entries, &profile, args.commit, &state_path, format)
+}
+
+/// Create money records of entries not imported before
+fn import(
+    api: &ZaimApi,
+    entries: &[Entry],
+    profile: &ImportProfile,
+    commit: bool,
+    state_path: &Path,
+    format: OutputFormat,
+) -> Result<ExitCode> {
+    let masters = Masters::fetch(api)?;
+    let targets = Targets::resolve(profile, &masters)?;
+    let requests = entries.iter().map(|e| targets.request(e, &masters)).collect::<Result<Vec<_>>>()?;
+
+    let mut state = config::load_json::<ImportState>(state_path)?;
+    let account_key = targets.account_id.to_string();
+    let mut statuses = check_imported(entries, state.get(&account_key));
+    let n_new = statuses.iter().filter(|s| **s == Status::New).count();
+
+    if !commit {
+        entries_table(entries, &statuses).print(format)?;
+        eprintln!("{} money records to create. Run with --commit to create them", n_new);
+        return Ok(ExitCode::SUCCESS);
+    }
+    // Requests are only printed in dry run, so that nothing is recorded as imported
+    if api.dry_run.is_some() {
+        for r in requests.iter().zip(&statuses).filter(|(_, s)| **s == Status::New).map(|(r, _)| r) {
+            api.create_money(r)?;
+        }
+        eprintln!("{} money records to create", n_new);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut created = 0;
+    for (i, e) in entries.iter().enumerate() {
+        if statuses[i] != Status::New {
+            continue;
+        }
+        let id = match api.create_money(&requests[i]) {
+            Ok(created) => created.id,
+            Err(err) => {
+                entries_table(entries, &statuses).print(format)?;
+                return Err(anyhow!("{} of {} money records created\nline {}: {}", created, n_new, e.line, err));
+            },
+        };
+        statuses[i] = Status::Created(id);
+        created += 1;
+        if let Some(k) = e.key.as_ref() {
+            state.entry(account_key.clone()).or_default().insert(k.clone(), id);
+            config::save_json(state_path, &state)?;
+        }
+    }
+    entries_table(entries, &statuses).print(format)?;
+    eprintln!("{} money records created", created);
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_imported, import, parse_amount, read_csv, read_ofx, read_qif, AmountSign, Entry, ImportProfile, Status};
+    use crate::output::OutputFormat;
+    use zaim_cli::models::Mode;
+    use zaim_cli::transport::{FakeTransport, HttpResponse};
+    use zaim_cli::zaim_api::{AccessTokens, ConsumerInfo, DryRun, ZaimApi};
+
+    use std::collections::BTreeMap;
+    use std::process::ExitCode;
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    fn profile(s: &str) -> ImportProfile {
+        toml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("¥1,200").unwrap(), Some(1200));
+        assert_eq!(parse_amount("△480").unwrap(), Some(-480));
+        assert_eq!(parse_amount("-300.00").unwrap(), Some(-300));
+        assert_eq!(parse_amount("1,200.").unwrap(), Some(1200));
+        assert!(parse_amount("12.50").unwrap_err().to_string().contains("fractional"));
+        assert!(parse_amount("1.2x").is_err());
+        assert_eq!(parse_amount("").unwrap(), None);
+        assert!(parse_amount("abc").is_err());
+    }
+
+    #[test]
+    fn test_read_csv_shift_jis() {
+        let text = "普通預金 入出金明細\n取引日,お引出し,お預入れ,摘要\n2026/01/05,\"1,200\",,スーパー\n2026/01/25,,300000,給与\n,,,合計\n";
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+        let profile = profile(r#"
+            encoding = "shift_jis"
+            skip_rows = 1
+            date_format = "%Y/%m/%d"
+            account = "銀行"
+            [columns]
+            date = "取引日"
+            withdrawal = "お引出し"
+            deposit = "お預入れ"
+            place = "摘要"
+        "#);
+
+        let entries = read_csv(&bytes, &profile).unwrap();
+        assert_eq!(entries, vec![
+            Entry {
+                key: Some(String::from("2026-01-05|-1200|スーパー|1")),
+                place: Some(String::from("スーパー")),
+                ..Entry::new(3, "2026-01-05".parse().unwrap(), -1200)
+            },
+            Entry {
+                key: Some(String::from("2026-01-25|300000|給与|1")),
+                place: Some(String::from("給与")),
+                ..Entry::new(4, "2026-01-25".parse().unwrap(), 300000)
+            },
+        ]);
+        assert_eq!((entries[0].mode, entries[0].amount), (Mode::Payment, 1200));
+    }
+
+    #[test]
+    fn test_read_csv_amount_sign() {
+        let profile = profile(r#"
+            has_header = false
+            amount_sign = "payment_positive"
+            account = "1"
+            [columns]
+            date = 0
+            amount = 2
+            name = 1
+        "#);
+        assert_eq!(profile.amount_sign, AmountSign::PaymentPositive);
+
+        let entries = read_csv(b"2026-01-05,Coffee,480\n2026-01-06,Refund,-480\n2026-01-05,Coffee,480\n", &profile).unwrap();
+        assert_eq!(entries.iter().map(|e| (e.mode, e.amount)).collect::<Vec<_>>(),
+                   vec![(Mode::Payment, 480), (Mode::Income, 480), (Mode::Payment, 480)]);
+        assert_eq!(entries[0].name.as_deref(), Some("Coffee"));
+        // Same rows are told apart by their order
+        assert_eq!(entries[0].key.as_deref(), Some("2026-01-05|-480|Coffee|1"));
+        assert_eq!(entries[2].key.as_deref(), Some("2026-01-05|-480|Coffee|2"));
+    }
+
+    #[test]
+    fn test_read_ofx_sgml() {
+        let ofx = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX>\n<BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>\n\
+<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20260105120000[+9:JST]\n<TRNAMT>-1200.00\n<FITID>A001\n<NAME>Tom &amp; Jerry\n<MEMO>card\n</STMTTRN>\n\
+<STMTTRN>\n<TRNTYPE>XFER\n<DTPOSTED>20260110\n<TRNAMT>5000\n<FITID>A002\n<NAME>ATM\n</STMTTRN>\n\
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n";
+        let entries = read_ofx(ofx.as_bytes(), &profile(r#"account = "銀行""#)).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, 6);
+        assert_eq!(entries[0].key.as_deref(), Some("A001"));
+        assert_eq!((entries[0].mode, entries[0].amount), (Mode::Payment, 1200));
+        assert_eq!(entries[0].place.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(entries[0].comment.as_deref(), Some("card"));
+        assert_eq!((entries[1].mode, entries[1].outgoing), (Mode::Transfer, false));
+
+        let ofx = "<STMTTRN><DTPOSTED>20260105<TRNAMT>-100<NAME>Shop<PAYEE><NAME>Payee</NAME></PAYEE></STMTTRN>";
+        let entries = read_ofx(ofx.as_bytes(), &profile(r#"account = "銀行""#)).unwrap();
+        assert_eq!(entries[0].place.as_deref(), Some("Shop"));
+    }
+
+    #[test]
+    fn test_read_qif() {
+        let qif = "!Type:Bank\nD1/5'26\nT-1,200.00\nPStarbucks\n^\nD01/05/2026\nT-1200\nPStarbucks\n^\nD1/7/26\nT-3000\nL[お財布]\n^\n";
+        let entries = read_qif(qif.as_bytes(), &profile(r#"account = "銀行""#)).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].date.to_string(), "2026-01-05");
+        assert_eq!(entries[0].key.as_deref(), Some("2026-01-05|-1200|Starbucks|1"));
+        assert_eq!(entries[1].key.as_deref(), Some("2026-01-05|-1200|Starbucks|2"));
+        assert_eq!(entries[2].line, 10);
+        assert_eq!(entries[2].mode, Mode::Transfer);
+        assert_eq!(entries[2].counterpart.as_deref(), Some("お財布"));
+    }
+
+    #[test]
+    fn test_read_qif_sections() {
+        let qif = "!Option:AutoSwitch\n!Account\nNChecking\nTBank\n^\nNCard\nTCCard\n^\n!Clear:AutoSwitch\n\
+                   !Account\nNChecking\nTBank\n^\n!Type:Bank\nD1/5'26\nT-480\nPStarbucks\n^\n\
+                   !Type:Cat\nNFood\nE\n^\n!Type:CCard \nD1/6'26\nT-1,200\n^\n";
+        let entries = read_qif(qif.as_bytes(), &profile(r#"account = "銀行""#)).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].line, entries[0].key.as_deref()), (15, Some("2026-01-05|-480|Starbucks|1")));
+        assert_eq!((entries[1].line, entries[1].key.as_deref()), (24, Some("2026-01-06|-1200||1")));
+    }
+
+    #[test]
+    fn test_check_imported() {
+        let entry = |key: &str| Entry { key: Some(key.to_string()), ..Entry::new(1, "2026-01-05".parse().unwrap(), -100) };
+        let entries = vec![entry("A001"), entry("A002"), entry("A002")];
+        let imported = BTreeMap::from([(String::from("A001"), 12)]);
+
+        assert_eq!(check_imported(&entries, Some(&imported)),
+                   vec![Status::Imported(12), Status::New, Status::Duplicate]);
+    }
+
+    #[test]
+    fn test_import_dry_run() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(200, &json!({"categories": [
+            {"id": 101, "name": "食費", "mode": "payment"},
+        ]}).to_string()));
+        transport.push_response(HttpResponse::new(200, &json!({"genres": [
+            {"id": 10101, "category_id": 101, "name": "食料品"},
+        ]}).to_string()));
+        transport.push_response(HttpResponse::new(200, &json!({"accounts": [{"id": 1, "name": "銀行"}]}).to_string()));
+        let mut api = ZaimApi::with_consumer_info(
+            ConsumerInfo { consumer_key: String::from("key"), consumer_secret: String::from("secret") },
+            Some(AccessTokens { access_token: String::from("token"), access_token_secret: String::from("secret") })
+        );
+        api.transport = transport.clone();
+        api.dry_run = Some(DryRun::default());
+
+        let profile = profile("account = \"銀行\"\n[payment]\ncategory = \"食費\"\ngenre = \"食料品\"");
+        let entries = vec![Entry::new(2, "2026-01-05".parse().unwrap(), -480)];
+        let state = std::env::temp_dir().join(format!("zaim-cli-import-dry-run-{}.json", std::process::id()));
+
+        let code = import(&api, &entries, &profile, true, &state, OutputFormat::Json).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        // Master data are read, and the payment is only printed
+        assert_eq!(transport.requests().iter().map(|r| r.method.as_str()).collect::<Vec<_>>(), vec!["GET"; 3]);
+        assert!(!state.exists());
+    }
+}
@@ -0,0 +1,9 @@
+//! Client library of Zaim API used by zaim-cli
+
+pub mod cassette;
+pub mod helper;
+pub mod master_cache;
+pub mod models;
+pub mod oauth1a;
+pub mod transport;
+pub mod zaim_api;
@@ -1,63 +1,173 @@
 //! zaim-cli
 
-mod helper;
-mod oauth1a;
-mod zaim_api;
-
-use std::collections::HashMap;
+mod add;
+mod auth;
+mod budget;
+mod cache;
+mod config;
+mod credential;
+mod db;
+mod dedupe;
+mod export;
+mod import;
+mod money;
+mod names;
+mod output;
+mod prompt;
+mod query;
+mod recurring;
+mod report;
+mod rules;
+mod session;
+mod sync;
+mod tui;
+
+use zaim_cli::{cassette, master_cache, transport, zaim_api};
+
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::process::ExitCode;
+use std::sync::Arc;
 
 use anyhow::Result;
-use clap::Parser;
-use serde_json;
+use clap::{CommandFactory, Parser, Subcommand};
+use tracing_subscriber::EnvFilter;
 
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Cli{
-    /// File path of consumer (client) information, which presented by json
-    #[arg(long, value_name = "FILE")]
-    consumer_info: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Configuration file [default: $XDG_CONFIG_HOME/zaim-cli/config.toml]
+    #[arg(long, value_name = "FILE", env = config::ENV_CONFIG)]
+    config: Option<PathBuf>,
+
+    /// Profile name in configuration file
+    #[arg(long, value_name = "NAME", env = config::ENV_PROFILE)]
+    profile: Option<String>,
+
+    /// Output format of subcommands [default: table]
+    #[arg(long, short = 'o', value_enum, global = true)]
+    output: Option<output::OutputFormat>,
+
+    #[command(flatten)]
+    credentials: session::CredentialArgs,
+
+    #[command(flatten)]
+    network: session::NetworkArgs,
+
+    /// Print signed requests (method, url, headers, body, signature base string and curl)
+    /// instead of sending them
+    #[arg(long, global = true)]
+    dry_run: bool,
 
-    /// File path of access tokens, which presented by json
-    #[arg(long, value_name = "FILE")]
-    access_token: Option<PathBuf>,
+    /// Log http requests into stderr (-v: requests, -vv: signing details).
+    /// RUST_LOG overrides this
+    #[arg(long, short = 'v', action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 
-    /// Zaim Rest API URI
-    #[arg(long, value_name = "URI")]
-    uri: String,
+    /// Record http requests and responses into a cassette file
+    #[arg(long, value_name = "FILE", global = true, conflicts_with = "replay_cassette")]
+    record_cassette: Option<PathBuf>,
+
+    /// Answer http requests from a cassette file instead of network
+    #[arg(long, value_name = "FILE", global = true)]
+    replay_cassette: Option<PathBuf>,
+
+    /// Fetch categories, genres, accounts and currencies again instead of using the cache
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    /// Show secrets in output of --dry-run
+    #[arg(long, global = true, requires = "dry_run")]
+    show_secrets: bool,
+
+    /// Zaim Rest API URI. A path (e.g. "/home/money") is resolved against the api base of the profile
+    #[arg(long, value_name = "URI", required = true)]
+    uri: Option<String>,
 
     /// HTTP Method: GET, POST
     /// Please a http method for the specified rest api
-    #[arg(long, value_name = "METHOD")]
-    method: String,
+    #[arg(long, value_name = "METHOD", required = true)]
+    method: Option<String>,
 
     /// (Optional) Query parameters for the rest api
     #[arg(long, value_name = "JSON STR")]
     query: Option<String>,
 
     /// File to save response
-    #[arg(long, value_name = "FILE")]
-    save: PathBuf,
+    #[arg(long, value_name = "FILE", required = true)]
+    save: Option<PathBuf>,
 }
 
-fn open_and_read_file(path: &Path) -> Result<String> {
-    let mut file = File::open(path)?;
-    let mut data = String::new();
-    let _ = file.read_to_string(&mut data)?;
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Log in, log out and check authentication
+    #[command(subcommand)]
+    Auth(auth::AuthCommand),
 
-    Ok(data)
-}
+    /// Show the user of current credentials
+    Whoami,
 
-fn save_access_tokens(access_tokens: &zaim_api::AccessTokens) -> Result<()> {
-    let mut file = File::create("access_tokens.json")?;
-    let data = serde_json::to_string(access_tokens)?;
-    file.write_all(data.as_bytes())?;
+    /// Manage profiles
+    #[command(subcommand)]
+    Profile(ProfileCommand),
 
-    Ok(())
+    /// Get and set configuration
+    #[command(subcommand)]
+    Config(config::ConfigCommand),
+
+    /// Mirror money records and master data into a local SQLite database
+    Sync(sync::SyncArgs),
+
+    /// Run SQL over money records and master data
+    Query(query::QueryArgs),
+
+    /// Export money records to ledger, hledger or beancount
+    Export(export::ExportArgs),
+
+    /// Create money records from bank or credit card statements
+    #[command(subcommand)]
+    Import(import::ImportCommand),
+
+    /// Categorize money records by a rules file
+    ApplyRules(rules::ApplyRulesArgs),
+
+    /// Create a payment from a text such as "cafe 480 starbucks yesterday #work"
+    Add(add::AddArgs),
+
+    /// Manage money records
+    #[command(subcommand)]
+    Money(money::MoneyCommand),
+
+    /// Aggregate money records
+    #[command(subcommand)]
+    Report(report::ReportCommand),
+
+    /// Check spending against budgets
+    #[command(subcommand)]
+    Budget(budget::BudgetCommand),
+
+    /// Create money records on schedules
+    #[command(subcommand)]
+    Recurring(recurring::RecurringCommand),
+
+    /// Browse and edit money records in a full-screen terminal UI
+    Tui(tui::TuiArgs),
+
+    /// Show or clear cached master data
+    #[command(subcommand)]
+    Cache(cache::CacheCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// List profiles and whether they are logged in
+    List,
 }
 
 fn save_api_response(save_file: &Path, response: &str) -> Result<()> {
@@ -67,38 +177,220 @@ fn save_api_response(save_file: &Path, response: &str) -> Result<()> {
     Ok(())
 }
 
+fn list_profiles(config: &config::Config, selected: Option<&str>, format: output::OutputFormat) -> Result<ExitCode> {
+    let selected = config.select_profile(selected)?.map(|(n, _)| n);
+
+    let mut table = output::Table::new(&["name", "selected", "status"]);
+    for (name, profile) in config.profiles.iter() {
+        let status = match session::Session::for_profile(config, name, profile) {
+            Ok(s) if s.is_logged_in() => String::from("logged in"),
+            Ok(s) if s.consumer_info.is_none() => String::from("no consumer information"),
+            Ok(_) => String::from("not logged in"),
+            Err(e) => format!("error: {}", e.to_string().replace('\n', " ")),
+        };
+        table.push(vec![
+            serde_json::json!(name),
+            serde_json::json!(selected.as_deref() == Some(name.as_str())),
+            serde_json::json!(status),
+        ]);
+    }
+    table.print(format)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// --output > ZAIM_OUTPUT > configuration of the profile > configuration
+fn output_format(cli: &Cli, config: &config::Config) -> Result<output::OutputFormat> {
+    if let Some(f) = cli.output {
+        return Ok(f);
+    }
+
+    let profile = match config.select_profile(cli.profile.as_deref())? {
+        Some((_, p)) => p,
+        None => config.global_profile(),
+    };
+    Ok(config.defaults(&profile)?.output.unwrap_or_default())
+}
+
+fn resolve_session(cli: &Cli, config: &config::Config, format: output::OutputFormat) -> Result<session::Session> {
+    let mut session = session::Session::resolve(&cli.credentials, config, cli.profile.as_deref())?;
+    if cli.dry_run {
+        session.dry_run = Some(zaim_api::DryRun {
+            show_secrets: cli.show_secrets,
+            json: format == output::OutputFormat::Json,
+        });
+    }
+    if let Some(cache) = session.cache.as_mut() {
+        cache.refresh = cli.refresh;
+    }
+    // Cassettes should have every request regardless of the cache
+    if let Some(path) = cli.record_cassette.as_deref() {
+        session.transport = Arc::new(cassette::Cassette::record(path, session.transport.clone()));
+        session.cache = None;
+    } else if let Some(path) = cli.replay_cassette.as_deref() {
+        session.transport = Arc::new(cassette::Cassette::replay(path)?);
+        session.cache = None;
+    }
+    Ok(session)
+}
+
+fn run_command(command: &Command, cli: &Cli, config: &config::Config) -> Result<ExitCode> {
+    let format = output_format(cli, config)?;
+    let session = || resolve_session(cli, config, format);
+
+    match command {
+        Command::Auth(c) => auth::run(c, session(), config, format),
+        Command::Whoami => auth::whoami(&session()?, format),
+        Command::Profile(ProfileCommand::List) => list_profiles(config, cli.profile.as_deref(), format),
+        Command::Sync(args) => sync::run(args, &session()?, format),
+        Command::Query(args) => query::run(args, session, format),
+        Command::Export(args) => export::run(args, &session()?),
+        Command::Import(c) => import::run(c, &session()?, format),
+        Command::ApplyRules(args) => rules::run(args, &session()?, format),
+        Command::Add(args) => add::run(args, &session()?, format),
+        Command::Money(c) => money::run(c, &session()?, format),
+        Command::Report(c) => report::run(c, &session()?, format),
+        Command::Budget(c) => budget::run(c, &session()?, format),
+        Command::Recurring(c) => recurring::run(c, &session()?, format),
+        Command::Tui(args) => tui::run(args, &session()?),
+        Command::Cache(c) => cache::run(c, &session()?, format),
+        Command::Config(_) => unreachable!("config command runs without loading configuration"),
+    }
+}
+
+/// Log into stderr. Secrets are redacted in every level.
+fn init_tracing(verbose: u8) {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(f) => f,
+        Err(_) => EnvFilter::new(match verbose {
+            0 => "warn",
+            1 => "warn,zaim_cli=debug",
+            _ => "warn,zaim_cli=trace",
+        }),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// Value of --config given before parsing arguments
+fn find_config_arg(args: &[String]) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    while let Some(a) = iter.next() {
+        if a == "--" {
+            break;
+        } else if a == "--config" {
+            return iter.next().map(PathBuf::from);
+        } else if let Some(v) = a.strip_prefix("--config=") {
+            return Some(PathBuf::from(v));
+        }
+    }
+
+    std::env::var_os(config::ENV_CONFIG).filter(|v| !v.is_empty()).map(PathBuf::from)
+}
+
+/// Replace an alias at the position of subcommand by its definition.
+/// Built-in subcommands cannot be overridden.
+fn expand_aliases(args: Vec<String>, aliases: &BTreeMap<String, String>) -> Vec<String> {
+    let cmd = Cli::command();
+    let value_options: Vec<String> = cmd.get_arguments()
+        .filter(|a| a.get_action().takes_values())
+        .flat_map(|a| {
+            let mut names = Vec::new();
+            if let Some(l) = a.get_long() {
+                names.push(format!("--{}", l));
+            }
+            if let Some(s) = a.get_short() {
+                names.push(format!("-{}", s));
+            }
+            names
+        })
+        .collect();
+
+    let mut i = 1;
+    while i < args.len() {
+        let a = &args[i];
+        if a == "--" {
+            break;
+        }
+        if a.starts_with('-') {
+            if value_options.contains(a) {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        if cmd.find_subcommand(a).is_none() {
+            if let Some(expansion) = aliases.get(a) {
+                let mut expanded = args[..i].to_vec();
+                expanded.extend(expansion.split_whitespace().map(String::from));
+                expanded.extend(args[i + 1..].iter().cloned());
+                return expanded;
+            }
+        }
+        break;
+    }
+
+    args
+}
+
 fn main() -> ExitCode {
-    let mut skip_user_confirm = false;
-    let access_tokens: zaim_api::AccessTokens;
     let mut api_query_params: Option<HashMap<String, String>> = None;
 
-    let cli = Cli::parse();
+    let args: Vec<String> = std::env::args().collect();
+    let config = config::Config::load(find_config_arg(&args).as_deref());
+    let args = match &config {
+        Ok(c) => expand_aliases(args, &c.aliases),
+        Err(_) => args,
+    };
 
-    let path_consumer_info = &cli.consumer_info;
-    let path_access_token = cli.access_token.as_deref();
+    let cli = Cli::parse_from(args);
+    init_tracing(cli.verbose);
 
-    if ! path_consumer_info.exists() {
-        eprintln!("Error: {} not found", path_consumer_info.display());
-        return ExitCode::FAILURE;
+    // Configuration may be broken, so that it is not loaded for config subcommand
+    if let Some(Command::Config(c)) = &cli.command {
+        let format = cli.output.unwrap_or_default();
+        return match config::run(c, cli.config.as_deref(), format) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
     }
 
-    let consumer_data = match open_and_read_file(&path_consumer_info) {
-        Ok(d) => d,
+    let mut config = match config {
+        Ok(c) => c,
         Err(e) => {
-            eprintln!("Error: failed to open and read {}\n{}", path_consumer_info.display(), e);
+            eprintln!("Error: failed to load configuration: {}", e);
             return ExitCode::FAILURE;
         }
     };
-    let consumer_info: zaim_api::ConsumerInfo = match serde_json::from_str(&consumer_data) {
-        Ok(j) => j,
+    cli.network.apply(&mut config.network);
+
+    if let Some(command) = &cli.command {
+        return match run_command(command, &cli, &config) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let session = match output_format(&cli, &config).and_then(|f| resolve_session(&cli, &config, f)) {
+        Ok(s) => s,
         Err(e) => {
-            eprintln!("Error: failed to parse consumer_info into json\n{}", e);
+            eprintln!("Error: {}", e);
             return ExitCode::FAILURE;
         }
     };
 
-    if cli.query.is_some() {
-        let ret = serde_json::from_str::<HashMap<String, String>>(cli.query.as_ref().unwrap());
+    if let Some(query) = cli.query.as_ref() {
+        let ret = serde_json::from_str::<HashMap<String, String>>(query);
         api_query_params = match ret {
             Ok(ret) => Some(ret),
             Err(e) => {
@@ -108,74 +400,60 @@ fn main() -> ExitCode {
         };
     }
 
-    if let Some(p) = path_access_token {
-        if p.exists() {
-            // println!("Debug: Provided access token");
-            skip_user_confirm = true;
-        } else {
-            eprintln!("Error: {} not found", p.display());
+    let mut api = match session.api() {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             return ExitCode::FAILURE;
         }
+    };
+    if cli.dry_run && ! api.is_authenticated() {
+        eprintln!("Error: access tokens are required for dry run");
+        return ExitCode::FAILURE;
     }
 
-    let oauth1 = oauth1a::OAuth1::new(
-        consumer_info.consumer_key.clone(),
-        consumer_info.consumer_secret.clone(),
-        String::from("oob"),
-        zaim_api::REQUEST_TOKEN_URL.to_string(),
-        zaim_api::AUTH_URL.to_string(),
-        zaim_api::ACCESS_TOKEN_URL.to_string()
-    );
-
-    if ! skip_user_confirm {
-        access_tokens = match zaim_api::authenticate(&oauth1) {
-            Ok(t) => t,
-            Err(e) => {
-                eprintln!("Error: {}", e);
-                return ExitCode::FAILURE;
-            }
-        };
+    if ! api.is_authenticated() {
+        if let Err(e) = api.authenticate() {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
 
-        if let Err(e) = save_access_tokens(&access_tokens) {
+        if let Err(e) = session.store_access_tokens(&api) {
             eprintln!("Failed to save access tokens: {}", e);
-            eprintln!("access_tokens:\n{:?}", access_tokens);
+            eprintln!("access_tokens:\n{:?}", api.access_tokens);
             return ExitCode::FAILURE;
         }
-    } else {
-        let p = path_access_token.unwrap();
-
-        let data = open_and_read_file(&p);
-        match data {
-            Ok(d) => {
-                access_tokens = match serde_json::from_str(&d) {
-                    Ok(j) => j,
-                    Err(e) => {
-                        eprintln!("Error: failed to parse access_token into json\n{}", e);
-                        return ExitCode::FAILURE;
-                    }
-                };
+    }
+
+    // uri, method and save are required without subcommand
+    if let Some(dry_run) = api.dry_run {
+        let preview = api.preview_rest_api(
+            &api.url(cli.uri.as_deref().unwrap()),
+            cli.method.as_deref().unwrap(),
+            api_query_params.as_ref(),
+            dry_run.show_secrets,
+        );
+        return match preview {
+            Ok(p) => {
+                p.print(dry_run.json);
+                ExitCode::SUCCESS
             },
             Err(e) => {
-                eprintln!("Error: failed to open and read {}\n{}", p.display(), e);
-                return ExitCode::FAILURE;
+                eprintln!("Error: failed to request to rest api: {}", e);
+                ExitCode::FAILURE
             }
-        }
-
-        // println!("Debug: access_tokens: {:?}", access_tokens);
+        };
     }
 
-    let fetched_data = zaim_api::request_rest_api(
-        &oauth1,
-        &cli.uri,
-        &cli.method,
-        &access_tokens.access_token,
-        &access_tokens.access_token_secret,
+    let fetched_data = api.request_rest_api(
+        &api.url(cli.uri.as_deref().unwrap()),
+        cli.method.as_deref().unwrap(),
         api_query_params.as_ref(),
     );
 
     match fetched_data {
         Ok(data) => {
-            if let Err(e) = save_api_response(&cli.save, &data) {
+            if let Err(e) = save_api_response(cli.save.as_deref().unwrap(), &data) {
                 eprintln!("Error: failed to save api response: {}", e);
                 return ExitCode::FAILURE;
             }
@@ -188,4 +466,3 @@ fn main() -> ExitCode {
 
     ExitCode::SUCCESS
 }
-
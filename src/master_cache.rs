@@ -0,0 +1,177 @@
+//! On-disk cache of master data (categories, genres, accounts and currencies)
+//!
+//! Each list is a json file in the directory of a profile, and is fresh while
+//! its modified time is within the TTL.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Names of cached lists, which are also the file stems
+pub const KINDS: [&str; 4] = ["categories", "genres", "accounts", "currencies"];
+
+#[derive(Clone, Debug)]
+pub struct MasterCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+    /// Ignore cached lists and save fetched ones
+    pub refresh: bool,
+}
+
+/// Cached list of a kind
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheEntry {
+    pub kind: &'static str,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub items: usize,
+    pub fresh: bool,
+}
+
+impl MasterCache {
+    pub fn new(dir: &Path, ttl: Duration) -> Self {
+        Self { dir: dir.to_path_buf(), ttl, refresh: false }
+    }
+
+    pub fn path(&self, kind: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", kind))
+    }
+
+    fn is_fresh(&self, modified: SystemTime) -> bool {
+        SystemTime::now().duration_since(modified).is_ok_and(|age| age < self.ttl)
+    }
+
+    /// Cached list if it is fresh and readable
+    pub fn get<T: serde::de::DeserializeOwned>(&self, kind: &str) -> Option<Vec<T>> {
+        if self.refresh {
+            return None;
+        }
+        let path = self.path(kind);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if !self.is_fresh(modified) {
+            return None;
+        }
+        let data = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&data) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "ignoring broken cache");
+                None
+            },
+        }
+    }
+
+    pub fn put<T: serde::Serialize>(&self, kind: &str, items: &[T]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // Write and rename, so that readers never see a partial file
+        let path = self.path(kind);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(items)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    /// Cached lists, fresh or not
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        KINDS.iter().filter_map(|kind| {
+            let path = self.path(kind);
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            let items = std::fs::read_to_string(&path).ok()
+                .and_then(|d| serde_json::from_str::<Vec<serde_json::Value>>(&d).ok())
+                .map_or(0, |v| v.len());
+            Some(CacheEntry { kind, path, modified, items, fresh: self.is_fresh(modified) })
+        }).collect()
+    }
+
+    /// Remove cached lists. Return the number of removed files
+    pub fn clear(&self) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for kind in KINDS {
+            match std::fs::remove_file(self.path(kind)) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MasterCache;
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zaim-cli-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_put_get() {
+        let dir = temp_dir("put");
+        let cache = MasterCache::new(&dir, Duration::from_secs(60));
+        assert_eq!(cache.get::<u64>("accounts"), None);
+
+        cache.put("accounts", &[1u64, 2]).unwrap();
+        assert_eq!(cache.get::<u64>("accounts"), Some(vec![1, 2]));
+        assert!(!cache.path("accounts").with_extension("json.tmp").exists());
+
+        let entries = cache.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].kind, entries[0].items, entries[0].fresh), ("accounts", 2, true));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expired() {
+        let dir = temp_dir("expired");
+        let cache = MasterCache::new(&dir, Duration::ZERO);
+        cache.put("genres", &[1u64]).unwrap();
+
+        assert_eq!(cache.get::<u64>("genres"), None);
+        assert!(!cache.entries()[0].fresh);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_refresh() {
+        let dir = temp_dir("refresh");
+        let mut cache = MasterCache::new(&dir, Duration::from_secs(60));
+        cache.put("categories", &[1u64]).unwrap();
+        cache.refresh = true;
+
+        assert_eq!(cache.get::<u64>("categories"), None);
+        cache.put("categories", &[2u64]).unwrap();
+        cache.refresh = false;
+        assert_eq!(cache.get::<u64>("categories"), Some(vec![2]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_broken() {
+        let dir = temp_dir("broken");
+        let cache = MasterCache::new(&dir, Duration::from_secs(60));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(cache.path("currencies"), "{broken").unwrap();
+
+        assert_eq!(cache.get::<u64>("currencies"), None);
+        assert_eq!(cache.entries()[0].items, 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = temp_dir("clear");
+        let cache = MasterCache::new(&dir, Duration::from_secs(60));
+        assert_eq!(cache.clear().unwrap(), 0);
+
+        cache.put("categories", &[1u64]).unwrap();
+        cache.put("genres", &[1u64]).unwrap();
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert!(cache.entries().is_empty());
+        assert_eq!(cache.get::<u64>("categories"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
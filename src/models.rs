@@ -0,0 +1,371 @@
+//! Household data returned by Zaim API
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+/// Kind of money records
+#[derive(serde::Deserialize, serde::Serialize, clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Payment,
+    Income,
+    Transfer,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Payment => "payment",
+            Mode::Income => "income",
+            Mode::Transfer => "transfer",
+        }
+    }
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "payment" => Ok(Mode::Payment),
+            "income" => Ok(Mode::Income),
+            "transfer" => Ok(Mode::Transfer),
+            _ => Err(format!("unknown mode: {}", s)),
+        }
+    }
+}
+
+/// Zaim uses 0 for "not set" in ids
+fn zero_as_none<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let id: Option<u64> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(id.filter(|i| *i != 0))
+}
+
+/// Money record (payment, income or transfer) of /v2/home/money
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Money {
+    pub id: u64,
+    pub mode: Mode,
+    pub date: NaiveDate,
+    pub amount: i64,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub category_id: Option<u64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub genre_id: Option<u64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub from_account_id: Option<u64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub to_account_id: Option<u64>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub place: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub receipt_id: Option<u64>,
+    #[serde(default)]
+    pub currency_code: Option<String>,
+    #[serde(default)]
+    pub active: Option<i64>,
+    #[serde(default)]
+    pub created: Option<String>,
+    #[serde(default)]
+    pub modified: Option<String>,
+}
+
+/// Category of /v2/home/category
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Category {
+    pub id: u64,
+    pub name: String,
+    pub mode: Mode,
+    #[serde(default)]
+    pub sort: Option<i64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub parent_category_id: Option<u64>,
+    #[serde(default)]
+    pub active: Option<i64>,
+    #[serde(default)]
+    pub modified: Option<String>,
+}
+
+/// Genre (sub category) of /v2/home/genre
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Genre {
+    pub id: u64,
+    pub category_id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub sort: Option<i64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub parent_genre_id: Option<u64>,
+    #[serde(default)]
+    pub active: Option<i64>,
+    #[serde(default)]
+    pub modified: Option<String>,
+}
+
+/// Account of /v2/home/account
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Account {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub sort: Option<i64>,
+    #[serde(default, deserialize_with = "zero_as_none")]
+    pub parent_account_id: Option<u64>,
+    #[serde(default)]
+    pub active: Option<i64>,
+    #[serde(default)]
+    pub modified: Option<String>,
+}
+
+/// Currency of /v2/currency
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Currency {
+    pub currency_code: String,
+    #[serde(default)]
+    pub unit: Option<String>,
+    pub name: String,
+    /// Number of decimal places
+    #[serde(default)]
+    pub point: Option<u32>,
+}
+
+impl Money {
+    /// Parameters of PUT /v2/home/money/{mode}/{id} to save this record
+    pub fn to_update_queries(&self) -> HashMap<String, String> {
+        let mut queries = money_queries(self.date, self.amount);
+        insert_some(&mut queries, "category_id", self.category_id);
+        insert_some(&mut queries, "genre_id", self.genre_id);
+        insert_some(&mut queries, "from_account_id", self.from_account_id);
+        insert_some(&mut queries, "to_account_id", self.to_account_id);
+        insert_some(&mut queries, "name", self.name.as_ref());
+        insert_some(&mut queries, "place", self.place.as_ref());
+        insert_some(&mut queries, "comment", self.comment.as_ref());
+        queries
+    }
+}
+
+/// Filters of /v2/home/money
+#[derive(Clone, Debug, Default)]
+pub struct MoneyQuery {
+    pub mode: Option<Mode>,
+    pub category_id: Option<u64>,
+    pub genre_id: Option<u64>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl MoneyQuery {
+    /// Query parameters of a page
+    pub fn to_queries(&self, page: u32, limit: u32) -> HashMap<String, String> {
+        let mut queries = HashMap::new();
+        queries.insert(String::from("mapping"), String::from("1"));
+        queries.insert(String::from("page"), page.to_string());
+        queries.insert(String::from("limit"), limit.to_string());
+        if let Some(m) = self.mode {
+            queries.insert(String::from("mode"), m.to_string());
+        }
+        if let Some(id) = self.category_id {
+            queries.insert(String::from("category_id"), id.to_string());
+        }
+        if let Some(id) = self.genre_id {
+            queries.insert(String::from("genre_id"), id.to_string());
+        }
+        if let Some(d) = self.start_date {
+            queries.insert(String::from("start_date"), d.to_string());
+        }
+        if let Some(d) = self.end_date {
+            queries.insert(String::from("end_date"), d.to_string());
+        }
+        queries
+    }
+}
+
+fn insert_some(queries: &mut HashMap<String, String>, key: &str, value: Option<impl ToString>) {
+    if let Some(v) = value {
+        queries.insert(String::from(key), v.to_string());
+    }
+}
+
+fn money_queries(date: NaiveDate, amount: i64) -> HashMap<String, String> {
+    let mut queries = HashMap::new();
+    queries.insert(String::from("mapping"), String::from("1"));
+    queries.insert(String::from("date"), date.to_string());
+    queries.insert(String::from("amount"), amount.to_string());
+    queries
+}
+
+/// Parameters of POST /v2/home/money/payment
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewPayment {
+    pub date: NaiveDate,
+    pub amount: i64,
+    pub category_id: u64,
+    pub genre_id: u64,
+    pub from_account_id: Option<u64>,
+    pub name: Option<String>,
+    pub place: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl NewPayment {
+    pub fn to_queries(&self) -> HashMap<String, String> {
+        let mut queries = money_queries(self.date, self.amount);
+        insert_some(&mut queries, "category_id", Some(self.category_id));
+        insert_some(&mut queries, "genre_id", Some(self.genre_id));
+        insert_some(&mut queries, "from_account_id", self.from_account_id);
+        insert_some(&mut queries, "name", self.name.as_ref());
+        insert_some(&mut queries, "place", self.place.as_ref());
+        insert_some(&mut queries, "comment", self.comment.as_ref());
+        queries
+    }
+}
+
+/// Parameters of POST /v2/home/money/income
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewIncome {
+    pub date: NaiveDate,
+    pub amount: i64,
+    pub category_id: u64,
+    pub to_account_id: Option<u64>,
+    pub place: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl NewIncome {
+    pub fn to_queries(&self) -> HashMap<String, String> {
+        let mut queries = money_queries(self.date, self.amount);
+        insert_some(&mut queries, "category_id", Some(self.category_id));
+        insert_some(&mut queries, "to_account_id", self.to_account_id);
+        insert_some(&mut queries, "place", self.place.as_ref());
+        insert_some(&mut queries, "comment", self.comment.as_ref());
+        queries
+    }
+}
+
+/// Parameters of POST /v2/home/money/transfer
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewTransfer {
+    pub date: NaiveDate,
+    pub amount: i64,
+    pub from_account_id: u64,
+    pub to_account_id: u64,
+    pub comment: Option<String>,
+}
+
+impl NewTransfer {
+    pub fn to_queries(&self) -> HashMap<String, String> {
+        let mut queries = money_queries(self.date, self.amount);
+        insert_some(&mut queries, "from_account_id", Some(self.from_account_id));
+        insert_some(&mut queries, "to_account_id", Some(self.to_account_id));
+        insert_some(&mut queries, "comment", self.comment.as_ref());
+        queries
+    }
+}
+
+/// Parameters of a money record to create
+#[derive(Clone, Debug, PartialEq)]
+pub enum NewMoney {
+    Payment(NewPayment),
+    Income(NewIncome),
+    Transfer(NewTransfer),
+}
+
+impl NewMoney {
+    pub fn mode(&self) -> Mode {
+        match self {
+            NewMoney::Payment(_) => Mode::Payment,
+            NewMoney::Income(_) => Mode::Income,
+            NewMoney::Transfer(_) => Mode::Transfer,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            NewMoney::Payment(p) => p.date,
+            NewMoney::Income(i) => i.date,
+            NewMoney::Transfer(t) => t.date,
+        }
+    }
+
+    pub fn to_queries(&self) -> HashMap<String, String> {
+        match self {
+            NewMoney::Payment(p) => p.to_queries(),
+            NewMoney::Income(i) => i.to_queries(),
+            NewMoney::Transfer(t) => t.to_queries(),
+        }
+    }
+}
+
+/// Money record created by POST /v2/home/money/{mode}, also returned by PUT
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct CreatedMoney {
+    pub id: u64,
+    #[serde(default)]
+    pub modified: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct MoneyResponse {
+    pub money: Vec<Money>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct CreateMoneyResponse {
+    pub money: CreatedMoney,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct CategoriesResponse {
+    pub categories: Vec<Category>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct GenresResponse {
+    pub genres: Vec<Genre>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct AccountsResponse {
+    pub accounts: Vec<Account>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub(crate) struct CurrenciesResponse {
+    pub currencies: Vec<Currency>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mode, Money, MoneyResponse};
+
+    #[test]
+    fn test_money_deserialize() {
+        let res: MoneyResponse = serde_json::from_str(r#"{"money": [{
+            "id": 382, "mode": "payment", "user_id": 1, "date": "2026-02-01",
+            "category_id": 101, "genre_id": 10101, "to_account_id": 0, "from_account_id": 1,
+            "amount": 480, "comment": "", "active": 1, "name": "Coffee", "receipt_id": 0,
+            "place": "Cafe", "created": "2026-02-01 10:00:00", "currency_code": "JPY"
+        }], "requested": 1}"#).unwrap();
+
+        let money: &Money = &res.money[0];
+        assert_eq!(money.mode, Mode::Payment);
+        assert_eq!(money.date.to_string(), "2026-02-01");
+        assert_eq!(money.to_account_id, None);
+        assert_eq!(money.from_account_id, Some(1));
+        assert_eq!(money.receipt_id, None);
+    }
+}
@@ -0,0 +1,412 @@
+//! money subcommand: manage money records
+
+use crate::config;
+use crate::add::{parse_amount, parse_date};
+use crate::dedupe;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::prompt::{Choice, Prompt};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, NewIncome, NewMoney, NewPayment, NewTransfer};
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde_json::json;
+
+/// Values of the last `money add` for each mode, offered as defaults
+const LAST_VALUES_FILE: &str = "money-add.json";
+
+#[derive(clap::Subcommand, Debug)]
+pub enum MoneyCommand {
+    /// Create a money record by options, or by prompts with --interactive
+    Add(AddArgs),
+    /// Find likely duplicates and delete them
+    Dedupe(dedupe::DedupeArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AddArgs {
+    /// Prompt for each field. Options and values of the last time are defaults
+    #[arg(long, short = 'i')]
+    pub interactive: bool,
+
+    /// Kind of the record [default: payment]
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Date (YYYY-MM-DD, M/D, today, yesterday or weekday) [default: today]
+    #[arg(long, value_name = "DATE")]
+    pub date: Option<String>,
+
+    #[arg(long)]
+    pub amount: Option<i64>,
+
+    /// Category (name or id). Required for income
+    #[arg(long)]
+    pub category: Option<String>,
+
+    /// Genre (name or id). Required for payment
+    #[arg(long)]
+    pub genre: Option<String>,
+
+    /// Account of payment or transfer (name or id)
+    #[arg(long, value_name = "ACCOUNT")]
+    pub from_account: Option<String>,
+
+    /// Account of income or transfer (name or id)
+    #[arg(long, value_name = "ACCOUNT")]
+    pub to_account: Option<String>,
+
+    #[arg(long)]
+    pub place: Option<String>,
+
+    #[arg(long)]
+    pub comment: Option<String>,
+}
+
+/// Values of a money record to create, with resolved ids
+#[derive(Clone, Debug, PartialEq)]
+struct Fields {
+    mode: Mode,
+    date: NaiveDate,
+    amount: Option<i64>,
+    category_id: Option<u64>,
+    genre_id: Option<u64>,
+    from_account_id: Option<u64>,
+    to_account_id: Option<u64>,
+    place: Option<String>,
+    comment: Option<String>,
+}
+
+impl Fields {
+    fn from_args(args: &AddArgs, masters: &Masters, today: NaiveDate) -> Result<Self> {
+        let mode = args.mode.unwrap_or(Mode::Payment);
+        // Positive as amounts of the prompt
+        if let Some(amount) = args.amount.filter(|a| *a <= 0) {
+            return Err(anyhow!("invalid amount: {}. It must be positive", amount));
+        }
+        let date = match args.date.as_deref() {
+            Some(d) => parse_date(d, today).ok_or_else(|| anyhow!("invalid date: {}", d))?,
+            None => today,
+        };
+        let account = |a: &Option<String>| a.as_deref().map(|a| masters.account_id(a)).transpose();
+        let mut category_id = args.category.as_deref().map(|c| masters.category_id(mode, c)).transpose()?;
+        let genre_id = args.genre.as_deref().map(|g| masters.genre_id(category_id, g)).transpose()?;
+        if let Some(g) = genre_id {
+            category_id = Some(masters.genres[&g].category_id);
+        }
+
+        Ok(Self {
+            mode,
+            date,
+            amount: args.amount,
+            category_id,
+            genre_id,
+            from_account_id: account(&args.from_account)?,
+            to_account_id: account(&args.to_account)?,
+            place: args.place.clone(),
+            comment: args.comment.clone(),
+        })
+    }
+
+    /// Fill the account paying a payment or a transfer, or receiving an income
+    fn or_account(self, account_id: Option<u64>) -> Self {
+        match self.mode {
+            Mode::Income => Self { to_account_id: self.to_account_id.or(account_id), ..self },
+            _ => Self { from_account_id: self.from_account_id.or(account_id), ..self },
+        }
+    }
+
+    fn to_new_money(&self) -> Result<NewMoney> {
+        let amount = self.amount.ok_or_else(|| anyhow!("amount is required"))?;
+        let required = |v: Option<u64>, field: &str| v.ok_or_else(|| anyhow!("{} is required for {}", field, self.mode));
+        Ok(match self.mode {
+            Mode::Payment => NewMoney::Payment(NewPayment {
+                date: self.date,
+                amount,
+                category_id: required(self.category_id, "category")?,
+                genre_id: required(self.genre_id, "genre")?,
+                from_account_id: self.from_account_id,
+                name: None,
+                place: self.place.clone(),
+                comment: self.comment.clone(),
+            }),
+            Mode::Income => NewMoney::Income(NewIncome {
+                date: self.date,
+                amount,
+                category_id: required(self.category_id, "category")?,
+                to_account_id: self.to_account_id,
+                place: self.place.clone(),
+                comment: self.comment.clone(),
+            }),
+            Mode::Transfer => NewMoney::Transfer(NewTransfer {
+                date: self.date,
+                amount,
+                from_account_id: required(self.from_account_id, "from_account")?,
+                to_account_id: required(self.to_account_id, "to_account")?,
+                comment: self.comment.clone(),
+            }),
+        })
+    }
+}
+
+/// Values remembered for a mode
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default, PartialEq)]
+struct Remembered {
+    category_id: Option<u64>,
+    genre_id: Option<u64>,
+    from_account_id: Option<u64>,
+    to_account_id: Option<u64>,
+    place: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, PartialEq)]
+struct LastValues {
+    mode: Option<Mode>,
+    #[serde(default)]
+    modes: BTreeMap<Mode, Remembered>,
+}
+
+impl LastValues {
+    fn remember(&mut self, f: &Fields) {
+        self.mode = Some(f.mode);
+        self.modes.insert(f.mode, Remembered {
+            category_id: f.category_id,
+            genre_id: f.genre_id,
+            from_account_id: f.from_account_id,
+            to_account_id: f.to_account_id,
+            place: f.place.clone(),
+        });
+    }
+}
+
+fn parse_mode(s: &str) -> Option<Mode> {
+    [Mode::Payment, Mode::Income, Mode::Transfer].into_iter().find(|m| m.as_str().starts_with(&s.to_lowercase()))
+}
+
+/// Active categories, genres or accounts in the order of Zaim
+fn choices<'a, T: 'a>(
+    items: impl Iterator<Item = &'a T>,
+    key: impl Fn(&T) -> (Option<i64>, Option<i64>, u64, &str),
+) -> Vec<Choice> {
+    let mut items: Vec<(Option<i64>, Option<i64>, u64, &str)> = items.map(key)
+        .filter(|(active, _, _, _)| active.is_none_or(|a| a > 0))
+        .collect();
+    items.sort_by_key(|(_, sort, id, _)| (*sort, *id));
+    items.into_iter().map(|(_, _, id, name)| Choice::new(id, name)).collect()
+}
+
+/// Prompt for fields. `given` by options, or else the last values are defaults
+fn prompt_fields<R: BufRead, W: Write>(
+    prompt: &mut Prompt<R, W>,
+    masters: &Masters,
+    given: &Fields,
+    mode_given: bool,
+    last: &LastValues,
+    today: NaiveDate,
+    default_account: Option<u64>,
+) -> Result<Fields> {
+    let default_mode = if mode_given { given.mode } else { last.mode.unwrap_or(given.mode) };
+    let mode = prompt.parse("Mode", Some(default_mode.as_str()), parse_mode)?;
+    let remembered = last.modes.get(&mode).cloned().unwrap_or_default();
+    let date = prompt.parse("Date", Some(&given.date.to_string()), |s| parse_date(s, today))?;
+    let amount = prompt.parse("Amount", given.amount.map(|a| a.to_string()).as_deref(), parse_amount)?;
+
+    let categories = choices(masters.categories.values().filter(|c| c.mode == mode), |c| (c.active, c.sort, c.id, &c.name));
+    let accounts = choices(masters.accounts.values(), |a| (a.active, a.sort, a.id, &a.name));
+    let mut fields = Fields {
+        mode,
+        date,
+        amount: Some(amount),
+        category_id: None,
+        genre_id: None,
+        from_account_id: None,
+        to_account_id: None,
+        place: None,
+        comment: None,
+    };
+    if mode != Mode::Transfer {
+        fields.category_id = prompt.pick("Category", &categories, given.category_id.or(remembered.category_id), false)?;
+    }
+    if let (Mode::Payment, Some(category_id)) = (mode, fields.category_id) {
+        let genres = choices(masters.genres.values().filter(|g| g.category_id == category_id), |g| (g.active, g.sort, g.id, &g.name));
+        fields.genre_id = prompt.pick("Genre", &genres, given.genre_id.or(remembered.genre_id), false)?;
+    }
+    if mode != Mode::Income {
+        let default = given.from_account_id.or(remembered.from_account_id).or(default_account);
+        fields.from_account_id = prompt.pick("From account", &accounts, default, mode == Mode::Payment)?;
+    }
+    if mode != Mode::Payment {
+        let default = given.to_account_id.or(remembered.to_account_id).or(default_account);
+        fields.to_account_id = prompt.pick("To account", &accounts, default, mode == Mode::Income)?;
+    }
+
+    // `-` clears the default
+    let mut text = |label: &str, default: Option<&String>| -> Result<Option<String>> {
+        let line = prompt.line(label, default.map(|d| d.as_str()))?;
+        Ok(match line.as_str() {
+            "" => default.cloned(),
+            "-" => None,
+            l => Some(l.to_string()),
+        })
+    };
+    if mode != Mode::Transfer {
+        fields.place = text("Place", given.place.as_ref().or(remembered.place.as_ref()))?;
+    }
+    fields.comment = text("Comment", given.comment.as_ref())?;
+    Ok(fields)
+}
+
+fn fields_table(f: &Fields, masters: &Masters, id: Option<u64>) -> Table {
+    let mut table = Table::new(&[
+        "mode", "date", "amount", "category", "genre", "from_account", "to_account", "place", "comment", "id",
+    ]);
+    table.push(vec![
+        json!(f.mode), json!(f.date), json!(f.amount),
+        json!(masters.category_name(f.category_id)), json!(masters.genre_name(f.genre_id)),
+        json!(masters.account_name(f.from_account_id)), json!(masters.account_name(f.to_account_id)),
+        json!(f.place), json!(f.comment), json!(id),
+    ]);
+    table
+}
+
+fn run_add(args: &AddArgs, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let api = session.api()?;
+    let masters = Masters::fetch(&api)?;
+    let today = session.defaults.today()?;
+    let given = Fields::from_args(args, &masters, today)?;
+    let default_account = session.defaults.account.as_deref().map(|a| masters.account_id(a)).transpose()?;
+    let last_path = session.data_dir()?.join(LAST_VALUES_FILE);
+    let mut last = config::load_json::<LastValues>(&last_path)?;
+
+    let fields = if args.interactive {
+        let mut prompt = Prompt::new(std::io::stdin().lock(), std::io::stderr());
+        let fields = prompt_fields(&mut prompt, &masters, &given, args.mode.is_some(), &last, today, default_account)?;
+        fields.to_new_money()?;
+        eprint!("{}", fields_table(&fields, &masters, None).render_table());
+        if !prompt.confirm(&format!("Create this {}?", fields.mode))? {
+            eprintln!("canceled");
+            return Ok(ExitCode::FAILURE);
+        }
+        fields
+    } else {
+        given.or_account(default_account)
+    };
+
+    let created = api.create_money(&fields.to_new_money()?)?;
+    if api.dry_run.is_none() {
+        last.remember(&fields);
+        config::save_json(&last_path, &last)?;
+    }
+    fields_table(&fields, &masters, Some(created.id)).print(format)?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn run(command: &MoneyCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    match command {
+        MoneyCommand::Add(args) => run_add(args, session, format),
+        MoneyCommand::Dedupe(args) => dedupe::run(args, session, format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prompt_fields, AddArgs, Fields, LastValues, Remembered};
+    use crate::names::test_masters as masters;
+    use crate::prompt::Prompt;
+    use zaim_cli::models::{Mode, NewMoney};
+
+    use chrono::NaiveDate;
+
+    fn empty_args() -> AddArgs {
+        AddArgs {
+            interactive: false, mode: None, date: None, amount: None, category: None, genre: None,
+            from_account: None, to_account: None, place: None, comment: None,
+        }
+    }
+
+    fn today() -> NaiveDate {
+        "2026-10-18".parse().unwrap()
+    }
+
+    #[test]
+    fn test_fields_from_args() {
+        let args = AddArgs {
+            amount: Some(480), genre: Some(String::from("カフェ")), from_account: Some(String::from("Wallet")),
+            date: Some(String::from("yesterday")), ..empty_args()
+        };
+        let fields = Fields::from_args(&args, &masters(), today()).unwrap();
+        match fields.to_new_money().unwrap() {
+            NewMoney::Payment(p) => assert_eq!(
+                (p.date.to_string(), p.amount, p.category_id, p.genre_id, p.from_account_id),
+                (String::from("2026-10-17"), 480, 101, 10102, Some(1))
+            ),
+            m => panic!("unexpected {:?}", m),
+        }
+
+        let args = AddArgs { mode: Some(Mode::Transfer), amount: Some(1000), from_account: Some(String::from("Bank")), ..empty_args() };
+        let fields = Fields::from_args(&args, &masters(), today()).unwrap();
+        assert_eq!(fields.to_new_money().unwrap_err().to_string(), "to_account is required for transfer");
+        assert_eq!(fields.clone().or_account(Some(2)).from_account_id, Some(2));
+        let income = Fields { mode: Mode::Income, ..fields };
+        assert_eq!(income.or_account(Some(2)).to_account_id, Some(2));
+
+        for amount in [0, -480] {
+            let args = AddArgs { amount: Some(amount), ..empty_args() };
+            assert!(Fields::from_args(&args, &masters(), today()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_prompt_fields() {
+        let mut last = LastValues { mode: Some(Mode::Payment), ..Default::default() };
+        last.modes.insert(Mode::Payment, Remembered {
+            category_id: Some(101), genre_id: Some(10102), from_account_id: Some(1), place: Some(String::from("Cafe")),
+            ..Default::default()
+        });
+        let given = Fields::from_args(&empty_args(), &masters(), today()).unwrap();
+
+        // Mode, date, amount, category, genre, from account, place and comment
+        let input: &[u8] = "\nyesterday\n480\n\n\nban\n-\n#work\n".as_bytes();
+        let mut output = Vec::new();
+        let fields = prompt_fields(&mut Prompt::new(input, &mut output), &masters(), &given, false, &last, today(), None).unwrap();
+        assert_eq!(fields, Fields {
+            mode: Mode::Payment,
+            date: "2026-10-17".parse().unwrap(),
+            amount: Some(480),
+            category_id: Some(101),
+            genre_id: Some(10102),
+            from_account_id: Some(2),
+            to_account_id: None,
+            place: None,
+            comment: Some(String::from("#work")),
+        });
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Mode [payment]: Date [2026-10-18]: Amount: Category [食費]: Genre [カフェ]: From account [Wallet]: "));
+
+        // Relative dates are from today, not from the given date
+        let given_date = Fields { date: "2026-01-01".parse().unwrap(), ..given.clone() };
+        let input: &[u8] = "\nyesterday\n480\n\n\n\n\n\n".as_bytes();
+        let fields = prompt_fields(&mut Prompt::new(input, Vec::new()), &masters(), &given_date, false, &last, today(), None).unwrap();
+        assert_eq!(fields.date.to_string(), "2026-10-17");
+
+        // Income asks the category of incomes and to account
+        let input: &[u8] = "i\n\n300000\n給与\n\n\n\n".as_bytes();
+        let fields = prompt_fields(&mut Prompt::new(input, Vec::new()), &masters(), &given, false, &last, today(), None).unwrap();
+        assert_eq!((fields.mode, fields.category_id, fields.to_account_id), (Mode::Income, Some(11), None));
+
+        // The default account comes after the last values
+        let input: &[u8] = "i\n\n300000\n給与\n\n\n\n".as_bytes();
+        let fields = prompt_fields(&mut Prompt::new(input, Vec::new()), &masters(), &given, false, &last, today(), Some(2)).unwrap();
+        assert_eq!(fields.to_account_id, Some(2));
+    }
+}
@@ -0,0 +1,231 @@
+//! Master data (categories, genres and accounts) and resolution of their names
+
+use zaim_cli::models::{Account, Category, Genre, Mode, MoneyQuery};
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+/// Katakana of half-width ones from U+FF66 to U+FF9D
+const HALF_WIDTH_KATAKANA: &str = "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+/// Filters of money records by names or ids
+#[derive(clap::Args, Debug, Default)]
+pub struct FilterArgs {
+    /// Only records of the mode
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Only records of the category (name or id)
+    #[arg(long)]
+    pub category: Option<String>,
+
+    /// Only records of the genre (name or id)
+    #[arg(long)]
+    pub genre: Option<String>,
+}
+
+impl FilterArgs {
+    /// Set filters of the query with resolved ids
+    pub fn apply(&self, query: &mut MoneyQuery, masters: &Masters) -> Result<()> {
+        query.mode = self.mode;
+        if let Some(key) = self.category.as_deref() {
+            let id = masters.any_category_id(self.mode, key)?;
+            query.mode = Some(masters.categories[&id].mode);
+            query.category_id = Some(id);
+        }
+        if let Some(key) = self.genre.as_deref() {
+            let id = masters.genre_id(query.category_id, key)?;
+            query.mode = Some(Mode::Payment);
+            query.genre_id = Some(id);
+        }
+        Ok(())
+    }
+}
+
+/// Master data by id
+#[derive(Debug, Default)]
+pub struct Masters {
+    pub categories: HashMap<u64, Category>,
+    pub genres: HashMap<u64, Genre>,
+    pub accounts: HashMap<u64, Account>,
+}
+
+impl Masters {
+    pub fn new(categories: Vec<Category>, genres: Vec<Genre>, accounts: Vec<Account>) -> Self {
+        Self {
+            categories: categories.into_iter().map(|c| (c.id, c)).collect(),
+            genres: genres.into_iter().map(|g| (g.id, g)).collect(),
+            accounts: accounts.into_iter().map(|a| (a.id, a)).collect(),
+        }
+    }
+
+    pub fn fetch(api: &ZaimApi) -> Result<Self> {
+        Ok(Self::new(api.categories()?, api.genres()?, api.accounts()?))
+    }
+
+    /// Account id by name or id
+    pub fn account_id(&self, key: &str) -> Result<u64> {
+        resolve("account", key, self.accounts.values(), |a| a.id, |a| &a.name)
+    }
+
+    /// Category id of the mode by name or id
+    pub fn category_id(&self, mode: Mode, key: &str) -> Result<u64> {
+        let items = self.categories.values().filter(|c| c.mode == mode);
+        resolve(&format!("{} category", mode), key, items, |c| c.id, |c| &c.name)
+    }
+
+    /// Category id by name or id, of the mode if given
+    pub fn any_category_id(&self, mode: Option<Mode>, key: &str) -> Result<u64> {
+        match mode {
+            Some(m) => self.category_id(m, key),
+            None => resolve("category", key, self.categories.values(), |c| c.id, |c| &c.name),
+        }
+    }
+
+    /// Genre id by name or id, in the category if given
+    pub fn genre_id(&self, category_id: Option<u64>, key: &str) -> Result<u64> {
+        let items = self.genres.values().filter(|g| category_id.is_none_or(|c| g.category_id == c));
+        resolve("genre", key, items, |g| g.id, |g| &g.name)
+    }
+
+    pub fn category_name(&self, id: Option<u64>) -> Option<&str> {
+        id.and_then(|i| self.categories.get(&i)).map(|c| c.name.as_str())
+    }
+
+    pub fn genre_name(&self, id: Option<u64>) -> Option<&str> {
+        id.and_then(|i| self.genres.get(&i)).map(|g| g.name.as_str())
+    }
+
+    pub fn account_name(&self, id: Option<u64>) -> Option<&str> {
+        id.and_then(|i| self.accounts.get(&i)).map(|a| a.name.as_str())
+    }
+}
+
+/// Name compared case-insensitively, with full-width alphanumerics and half-width katakana
+/// as the usual ones, and spaces squeezed
+pub fn name_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\u{3000}' => key.push(' '),
+            '\u{ff01}'..='\u{ff5e}' => key.push(char::from_u32(c as u32 - 0xfee0).unwrap().to_ascii_lowercase()),
+            '\u{ff66}'..='\u{ff9d}' => key.push(HALF_WIDTH_KATAKANA.chars().nth(c as usize - 0xff66).unwrap()),
+            // Voiced and semi-voiced sound marks following half-width katakana
+            '\u{ff9e}' | '\u{ff9f}' => {
+                let voiced = c == '\u{ff9e}';
+                let last = key.pop();
+                let combined = match last {
+                    Some('ウ') if voiced => Some('ヴ'),
+                    Some(k @ ('カ'..='ト' | 'ハ'..='ホ')) if voiced => char::from_u32(k as u32 + 1),
+                    Some(k @ 'ハ'..='ホ') => char::from_u32(k as u32 + 2),
+                    _ => None,
+                };
+                match combined {
+                    Some(k) => key.push(k),
+                    None => key.extend(last.into_iter().chain([if voiced { '\u{309b}' } else { '\u{309c}' }])),
+                }
+            },
+            _ => key.extend(c.to_lowercase()),
+        }
+    }
+    key.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Find id by id or name. Names are exact, or else compared by `name_key`
+pub fn resolve<'a, T: 'a>(
+    kind: &str,
+    key: &str,
+    items: impl IntoIterator<Item = &'a T>,
+    id: impl Fn(&T) -> u64,
+    name: impl Fn(&T) -> &str
+) -> Result<u64> {
+    let items: Vec<&T> = items.into_iter().collect();
+    if let Ok(i) = key.trim().parse::<u64>() {
+        if items.iter().any(|x| id(x) == i) {
+            return Ok(i);
+        }
+    }
+    let mut found: Vec<&T> = items.iter().copied().filter(|x| name(x) == key).collect();
+    if found.is_empty() {
+        let k = name_key(key);
+        found = items.iter().copied().filter(|x| name_key(name(x)) == k).collect();
+    }
+    found.sort_by_key(|x| id(x));
+    match found.as_slice() {
+        [x] => Ok(id(x)),
+        [] => Err(anyhow!("unknown {}: {}", kind, key)),
+        _ => Err(anyhow!("ambiguous {}: {} ({}). Use one of the ids", kind, key,
+                         found.iter().map(|x| format!("{}: {}", id(x), name(x))).collect::<Vec<_>>().join(", "))),
+    }
+}
+
+/// Master data shared by tests of commands
+#[cfg(test)]
+pub(crate) fn test_masters() -> Masters {
+    use serde_json::json;
+
+    Masters::new(
+        serde_json::from_value::<Vec<Category>>(json!([
+            {"id": 101, "name": "食費", "mode": "payment", "sort": 1},
+            {"id": 102, "name": "日用雑貨", "mode": "payment", "sort": 2},
+            {"id": 103, "name": "交際費", "mode": "payment", "sort": 3},
+            {"id": 104, "name": "住宅", "mode": "payment", "sort": 4},
+            {"id": 11, "name": "給与所得", "mode": "income"},
+        ])).unwrap(),
+        serde_json::from_value::<Vec<Genre>>(json!([
+            {"id": 10101, "category_id": 101, "name": "食料品"},
+            {"id": 10102, "category_id": 101, "name": "カフェ"},
+            {"id": 10103, "category_id": 101, "name": "Cafe"},
+            {"id": 10104, "category_id": 101, "name": "Groceries"},
+            {"id": 10199, "category_id": 101, "name": "その他"},
+            {"id": 10201, "category_id": 102, "name": "消耗品"},
+            {"id": 10299, "category_id": 102, "name": "その他"},
+            {"id": 10301, "category_id": 103, "name": "Dining out"},
+            {"id": 10302, "category_id": 103, "name": "Dinner"},
+            {"id": 10401, "category_id": 104, "name": "家賃"},
+        ])).unwrap(),
+        serde_json::from_value::<Vec<Account>>(json!([
+            {"id": 1, "name": "Wallet"},
+            {"id": 2, "name": "Bank"},
+            {"id": 3, "name": "ゆうちょ銀行"},
+        ])).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{name_key, test_masters};
+    use zaim_cli::models::Mode;
+
+    #[test]
+    fn test_name_key() {
+        assert_eq!(name_key("  Ｄａｉｌｙ\u{3000}Goods "), "daily goods");
+        assert_eq!(name_key("ｶﾌｪ"), "カフェ");
+        assert_eq!(name_key("ｶﾞｿﾘﾝ ﾊﾟﾝ ｳﾞ"), "ガソリン パン ヴ");
+        assert_eq!(name_key("食費"), "食費");
+    }
+
+    #[test]
+    fn test_resolve() {
+        let masters = test_masters();
+        assert_eq!(masters.account_id("wallet").unwrap(), 1);
+        assert_eq!(masters.account_id("2").unwrap(), 2);
+        assert_eq!(masters.account_id("ゆうちょ銀行").unwrap(), 3);
+        assert_eq!(masters.category_id(Mode::Payment, " 日用雑貨\u{3000}").unwrap(), 102);
+        assert_eq!(masters.genre_id(None, "ｶﾌｪ").unwrap(), 10102);
+        assert_eq!(masters.genre_id(None, "CAFE").unwrap(), 10103);
+        assert_eq!(masters.genre_id(Some(102), "その他").unwrap(), 10299);
+        assert_eq!(masters.any_category_id(None, "給与所得").unwrap(), 11);
+
+        assert_eq!(
+            masters.genre_id(None, "その他").unwrap_err().to_string(),
+            "ambiguous genre: その他 (10199: その他, 10299: その他). Use one of the ids"
+        );
+        assert_eq!(
+            masters.category_id(Mode::Income, "食費").unwrap_err().to_string(),
+            "unknown income category: 食費"
+        );
+    }
+}
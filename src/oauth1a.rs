@@ -10,6 +10,48 @@ use hmac::{Hmac, Mac};
 use sha1::Sha1;
 type HmacSha1 = Hmac<Sha1>;
 
+/// Parameters which must not be shown in logs or debug output
+pub const SECRET_PARAMS: [&str; 4] = [
+    "oauth_consumer_key", "oauth_token", "oauth_signature", "oauth_verifier"
+];
+
+/// Authorization header with intermediate values for debugging
+#[derive(Debug, Clone)]
+pub struct SignedAuth {
+    pub header: String,
+    pub signature_base_string: String,
+}
+
+/// Replace values of secret parameters in Authorization header by "***"
+pub fn redact_auth_header(header: &str) -> String {
+    let params = match header.strip_prefix("OAuth ") {
+        Some(p) => p,
+        None => return String::from("***"),
+    };
+
+    let params: Vec<String> = params.split(", ").map(|kv| {
+        match kv.split_once('=') {
+            Some((k, _)) if SECRET_PARAMS.contains(&k) => format!("{}=\"***\"", k),
+            _ => kv.to_string(),
+        }
+    }).collect();
+
+    format!("OAuth {}", params.join(", "))
+}
+
+/// Replace values of secret parameters in signature base string by "***"
+pub fn redact_signature_base_string(base_string: &str) -> String {
+    let mut redacted = base_string.to_string();
+    for k in SECRET_PARAMS {
+        let key = format!("{}%3D", k);
+        if let Some(start) = redacted.find(&key).map(|i| i + key.len()) {
+            let end = redacted[start..].find("%26").map(|i| start + i).unwrap_or(redacted.len());
+            redacted.replace_range(start..end, "***");
+        }
+    }
+    redacted
+}
+
 pub struct OAuth1 {
     pub consumer_key: String,
     pub consumer_secret: String,
@@ -32,14 +74,14 @@ impl OAuth1 {
         access_token_url: String,
     ) -> Self {
         Self {
-            consumer_key: consumer_key,
-            consumer_secret: consumer_secret,
-            request_token_url: request_token_url,
-            auth_url: auth_url,
-            access_token_url: access_token_url,
+            consumer_key,
+            consumer_secret,
+            request_token_url,
+            auth_url,
+            access_token_url,
             signature_method: String::from("HMAC-SHA1"),
             version: String::from("1.0"),
-            callback: callback,
+            callback,
         }
     }
     
@@ -83,8 +125,11 @@ impl OAuth1 {
     ) -> String {
         let mut request_params: HashMap<String, String> = HashMap::new();
         request_params.extend(auth_params.clone());
-        if queries.is_some() {
-            request_params.extend(queries.unwrap().clone());
+        // Request parameters (query string or form body) are encoded before normalization
+        if let Some(q) = queries {
+            request_params.extend(q.iter().map(|(k, v)| {
+                (helper::percent_encode_rfc3986(k), helper::percent_encode_rfc3986(v))
+            }));
         }
 
         let mut base_str = String::from(protocol);
@@ -136,16 +181,27 @@ impl OAuth1 {
         &self,
         url: &str,
         protocol: &str,
-        mut params: HashMap<String, String>,
+        params: HashMap<String, String>,
         token_secret: Option<&str>,
         queries: Option<&HashMap<String, String>>
     ) -> Result<String> {
+        Ok(self._sign(url, protocol, params, token_secret, queries)?.header)
+    }
+
+    fn _sign(
+        &self,
+        url: &str,
+        protocol: &str,
+        mut params: HashMap<String, String>,
+        token_secret: Option<&str>,
+        queries: Option<&HashMap<String, String>>
+    ) -> Result<SignedAuth> {
         let signing_key = OAuth1::gen_signing_key(self.consumer_secret.as_str(), token_secret);
         let signature_base_string = OAuth1::gen_signature_base_string(
             &params, protocol, url, queries
         );
         let signature = OAuth1::gen_signature(
-            signature_base_string, signing_key
+            signature_base_string.clone(), signing_key
         )?;
         params.insert(
             String::from("oauth_signature"),
@@ -163,19 +219,25 @@ impl OAuth1 {
         // remove ", "
         let _ = auth.pop();
         let _ = auth.pop();
+
+        tracing::trace!(
+            signature_base_string = %redact_signature_base_string(&signature_base_string),
+            authorization = %redact_auth_header(&auth),
+            "signed request"
+        );
         
-        Ok(auth)
+        Ok(SignedAuth { header: auth, signature_base_string })
     }
 
     pub fn gen_auth_for_request_token(&self) -> Result<String> {
         let mut params = self._into_oauth_base_params();
         params.insert(
             String::from("oauth_nonce"),
-            String::from(helper::get_random_string(32)),
+            helper::get_random_string(32),
         );
         params.insert(
             String::from("oauth_timestamp"),
-            String::from(helper::get_unix_timestamp()?.to_string()),
+            helper::get_unix_timestamp()?.to_string(),
         );
         params.insert(
             String::from("oauth_callback"),
@@ -205,11 +267,11 @@ impl OAuth1 {
         );
         params.insert(
             String::from("oauth_nonce"),
-            String::from(helper::get_random_string(32)),
+            helper::get_random_string(32),
         );
         params.insert(
             String::from("oauth_timestamp"),
-            String::from(helper::get_unix_timestamp()?.to_string()),
+            helper::get_unix_timestamp()?.to_string(),
         );
         params.insert(
             String::from("oauth_verifier"),
@@ -233,6 +295,18 @@ impl OAuth1 {
         access_token_secret: &str,
         queries: Option<&HashMap<String, String>>
     ) -> Result<String> {
+        Ok(self.sign_for_rest_api(url, protocol, access_token, access_token_secret, queries)?.header)
+    }
+
+    /// Same as gen_auth_for_rest_api, but also returns signature base string
+    pub fn sign_for_rest_api(
+        &self,
+        url: &str,
+        protocol: &str,
+        access_token: &str,
+        access_token_secret: &str,
+        queries: Option<&HashMap<String, String>>
+    ) -> Result<SignedAuth> {
         let mut params = self._into_oauth_base_params();
 
         params.insert(
@@ -241,14 +315,14 @@ impl OAuth1 {
         );
         params.insert(
             String::from("oauth_nonce"),
-            String::from(helper::get_random_string(32)),
+            helper::get_random_string(32),
         );
         params.insert(
             String::from("oauth_timestamp"),
-            String::from(helper::get_unix_timestamp()?.to_string()),
+            helper::get_unix_timestamp()?.to_string(),
         );
 
-        self._gen_auth_common(
+        self._sign(
             url,
             protocol,
             params,
@@ -261,7 +335,7 @@ impl OAuth1 {
 #[cfg(test)]
 mod tests {
     use crate::helper;
-    use super::OAuth1;
+    use super::{OAuth1, redact_auth_header, redact_signature_base_string};
     use std::collections::HashMap;
 
     // Dummy data
@@ -343,4 +417,23 @@ mod tests {
 
         assert_eq!(signature, String::from(ANS_SIGNATURE));
     }
+
+    #[test]
+    fn test_redact_auth_header() {
+        let header = "OAuth oauth_consumer_key=\"key\", oauth_nonce=\"123\", oauth_signature=\"sig\", oauth_token=\"token\"";
+
+        assert_eq!(
+            redact_auth_header(header),
+            "OAuth oauth_consumer_key=\"***\", oauth_nonce=\"123\", oauth_signature=\"***\", oauth_token=\"***\""
+        );
+    }
+
+    #[test]
+    fn test_redact_signature_base_string() {
+        let redacted = redact_signature_base_string(ANS_SIGNATURE_BASE_STRING);
+
+        assert!(!redacted.contains(CONSUMER_KEY));
+        assert!(redacted.contains("oauth_consumer_key%3D***%26oauth_nonce%3D"));
+        assert!(redacted.ends_with("oauth_version%3D1.0"));
+    }
 }
@@ -0,0 +1,166 @@
+//! Output formatters shared by subcommands
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde_json::Value;
+
+#[derive(clap::ValueEnum, serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable table
+    #[default]
+    Table,
+    /// JSON for scripts
+    Json,
+    /// CSV for spreadsheets
+    Csv,
+}
+
+/// Rows of cells with headers.
+/// In JSON, a table is rendered as an array of objects keyed by headers.
+#[derive(Debug, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        self.rows.push(row);
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.rows.iter().map(|row| {
+            Value::Object(self.headers.iter().cloned().zip(row.iter().cloned()).collect())
+        }).collect())
+    }
+
+    pub fn render_table(&self) -> String {
+        let cells: Vec<Vec<String>> = self.rows.iter()
+            .map(|row| row.iter().map(cell_to_string).collect())
+            .collect();
+
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| display_width(h)).collect();
+        for row in cells.iter() {
+            for (i, c) in row.iter().enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(display_width(c));
+                }
+            }
+        }
+
+        let mut out = String::new();
+        push_line(&mut out, &self.headers.iter().map(|h| h.to_uppercase()).collect::<Vec<_>>(), &widths);
+        for row in cells.iter() {
+            push_line(&mut out, row, &widths);
+        }
+
+        out
+    }
+
+    pub fn render_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&self.headers)?;
+        for row in self.rows.iter() {
+            writer.write_record(row.iter().map(cell_to_string))?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    pub fn print(&self, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Table => print!("{}", self.render_table()),
+            OutputFormat::Json => print_json(&self.to_json())?,
+            OutputFormat::Csv => print!("{}", self.render_csv()?),
+        }
+
+        Ok(())
+    }
+}
+
+/// Print a serializable value as pretty json into stdout
+pub fn print_json<T: serde::Serialize>(value: &T) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+
+    Ok(())
+}
+
+pub fn cell_to_string(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Width on terminal, counting wide (e.g. Japanese) characters as 2 columns
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF |
+        0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF |
+        0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F |
+        0x20000..=0x3FFFD)
+}
+
+fn push_line(out: &mut String, cells: &[String], widths: &[usize]) {
+    let mut line = String::new();
+    for (i, c) in cells.iter().enumerate() {
+        line.push_str(c);
+        if i + 1 < cells.len() {
+            let pad = widths.get(i).copied().unwrap_or(0).saturating_sub(display_width(c));
+            line.push_str(&" ".repeat(pad + 2));
+        }
+    }
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Table;
+    use serde_json::json;
+
+    fn prepare_table() -> Table {
+        let mut table = Table::new(&["name", "amount"]);
+        table.push(vec![json!("Cafe"), json!(480)]);
+        table.push(vec![json!("食費"), json!(null)]);
+        table
+    }
+
+    #[test]
+    fn test_table_render_table() {
+        assert_eq!(
+            prepare_table().render_table(),
+            "NAME  AMOUNT\nCafe  480\n食費\n"
+        );
+    }
+
+    #[test]
+    fn test_table_render_csv() {
+        let mut table = prepare_table();
+        table.push(vec![json!("a,b"), json!(1)]);
+        assert_eq!(table.render_csv().unwrap(), "name,amount\nCafe,480\n食費,\n\"a,b\",1\n");
+    }
+
+    #[test]
+    fn test_table_to_json() {
+        assert_eq!(
+            prepare_table().to_json(),
+            json!([{"name": "Cafe", "amount": 480}, {"name": "食費", "amount": null}])
+        );
+    }
+}
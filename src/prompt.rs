@@ -0,0 +1,198 @@
+//! Line based prompts of interactive commands
+
+use std::io::{BufRead, Write};
+
+use anyhow::{anyhow, Result};
+
+/// Max number of choices listed at once
+const MAX_LISTED: usize = 20;
+
+/// Item of a picker
+#[derive(Clone, Debug, PartialEq)]
+pub struct Choice {
+    pub id: u64,
+    pub name: String,
+}
+
+impl Choice {
+    pub fn new(id: u64, name: &str) -> Self {
+        Self { id, name: name.to_string() }
+    }
+}
+
+/// Score of `text` containing characters of `query` in order, case-insensitively.
+/// Higher is better. None if not matched
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = position + text[position..].iter().position(|c| *c == q)?;
+        score += match previous {
+            _ if i == 0 => 8,
+            Some(p) if p + 1 == i => 5,
+            _ => 1,
+        };
+        previous = Some(i);
+        position = i + 1;
+    }
+    Some(score)
+}
+
+/// Choices matching `query`, better ones first
+pub fn fuzzy_filter<'a>(query: &str, choices: &'a [Choice]) -> Vec<&'a Choice> {
+    let mut found: Vec<(i64, &Choice)> = choices.iter()
+        .filter_map(|c| fuzzy_score(query, &c.name).map(|s| (s, c)))
+        .collect();
+    found.sort_by_key(|(s, c)| (-s, c.name.chars().count(), c.id));
+    found.into_iter().map(|(_, c)| c).collect()
+}
+
+pub struct Prompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Trimmed line answered to `label`. Error at the end of input
+    pub fn line(&mut self, label: &str, default: Option<&str>) -> Result<String> {
+        match default {
+            Some(d) => write!(self.output, "{} [{}]: ", label, d)?,
+            None => write!(self.output, "{}: ", label)?,
+        }
+        self.output.flush()?;
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            writeln!(self.output)?;
+            return Err(anyhow!("canceled"));
+        }
+        Ok(line.trim().to_string())
+    }
+
+    /// Ask until `parse` accepts the answer. Empty answer is the default if given
+    pub fn parse<T, F>(&mut self, label: &str, default: Option<&str>, parse: F) -> Result<T>
+    where
+        F: Fn(&str) -> Option<T>,
+    {
+        loop {
+            let line = self.line(label, default)?;
+            let answer = match (line.as_str(), default) {
+                ("", Some(d)) => d,
+                ("", None) => continue,
+                (l, _) => l,
+            };
+            match parse(answer) {
+                Some(v) => return Ok(v),
+                None => writeln!(self.output, "invalid {}: {}", label.to_lowercase(), answer)?,
+            }
+        }
+    }
+
+    pub fn confirm(&mut self, question: &str) -> Result<bool> {
+        write!(self.output, "{} [y/N] ", question)?;
+        self.output.flush()?;
+        let mut line = String::new();
+        self.input.read_line(&mut line)?;
+        Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+    }
+
+    fn list(&mut self, choices: &[&Choice]) -> Result<()> {
+        for (i, c) in choices.iter().take(MAX_LISTED).enumerate() {
+            writeln!(self.output, "  {}) {}", i + 1, c.name)?;
+        }
+        if choices.len() > MAX_LISTED {
+            writeln!(self.output, "  ... {} more. Type to narrow down", choices.len() - MAX_LISTED)?;
+        }
+        Ok(())
+    }
+
+    /// Pick a choice by fuzzy search, or by number of the listed ones.
+    /// `?` lists all. If `optional`, empty answer without default and `-` pick nothing
+    pub fn pick(&mut self, label: &str, choices: &[Choice], default: Option<u64>, optional: bool) -> Result<Option<u64>> {
+        let default = default.and_then(|d| choices.iter().find(|c| c.id == d));
+        let mut listed: Vec<&Choice> = Vec::new();
+        loop {
+            let line = self.line(label, default.map(|c| c.name.as_str()))?;
+            if line.is_empty() {
+                if let Some(d) = default {
+                    return Ok(Some(d.id));
+                } else if optional {
+                    return Ok(None);
+                }
+            }
+            if line == "-" && optional {
+                return Ok(None);
+            }
+            if let Some(c) = line.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| listed.get(i)) {
+                return Ok(Some(c.id));
+            }
+
+            let found = match line.as_str() {
+                "" | "?" => choices.iter().collect(),
+                q => fuzzy_filter(q, choices),
+            };
+            if let Some(c) = found.iter().find(|c| c.name.to_lowercase() == line.to_lowercase()) {
+                return Ok(Some(c.id));
+            }
+            match found.as_slice() {
+                [] => writeln!(self.output, "no {} matches {}", label.to_lowercase(), line)?,
+                [c] if !line.is_empty() && line != "?" => return Ok(Some(c.id)),
+                _ => {
+                    self.list(&found)?;
+                    listed = found;
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_filter, Choice, Prompt};
+
+    fn choices() -> Vec<Choice> {
+        vec![
+            Choice::new(1, "Groceries"),
+            Choice::new(2, "Cafe"),
+            Choice::new(3, "Cafe & Bakery"),
+            Choice::new(4, "Restaurant"),
+            Choice::new(5, "カフェ"),
+        ]
+    }
+
+    #[test]
+    fn test_fuzzy_filter() {
+        let choices = choices();
+        let names = |q| fuzzy_filter(q, &choices).iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names("caf"), vec!["Cafe", "Cafe & Bakery"]);
+        assert_eq!(names("rst"), vec!["Restaurant"]);
+        assert_eq!(names("cb"), vec!["Cafe & Bakery"]);
+        assert_eq!(names("かふぇ"), Vec::<&str>::new());
+        assert_eq!(names("フェ"), vec!["カフェ"]);
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut output = Vec::new();
+        let input: &[u8] = b"ca\n2\n";
+        let picked = Prompt::new(input, &mut output).pick("Genre", &choices(), None, false).unwrap();
+        assert_eq!(picked, Some(3));
+        assert_eq!(String::from_utf8(output).unwrap(), "Genre:   1) Cafe\n  2) Cafe & Bakery\nGenre: ");
+
+        let input: &[u8] = b"zzz\nrest\n";
+        assert_eq!(Prompt::new(input, Vec::new()).pick("Genre", &choices(), None, false).unwrap(), Some(4));
+        let input: &[u8] = b"cafe\n";
+        assert_eq!(Prompt::new(input, Vec::new()).pick("Genre", &choices(), None, false).unwrap(), Some(2));
+        let input: &[u8] = b"\n";
+        assert_eq!(Prompt::new(input, Vec::new()).pick("Genre", &choices(), Some(5), false).unwrap(), Some(5));
+        let input: &[u8] = b"-\n";
+        assert_eq!(Prompt::new(input, Vec::new()).pick("Account", &choices(), Some(5), true).unwrap(), None);
+        let input: &[u8] = b"";
+        assert!(Prompt::new(input, Vec::new()).pick("Genre", &choices(), None, false).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! query subcommand: run SQL over household data
+
+use crate::db;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::MoneyQuery;
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate};
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+#[derive(clap::Args, Debug)]
+pub struct QueryArgs {
+    /// SQL to run. Tables are money (with names of category, genre, from_account
+    /// and to_account), categories, genres and accounts
+    pub sql: String,
+
+    /// Query a database saved by `sync` instead of fetching from Zaim
+    #[arg(long, value_name = "FILE")]
+    pub db: Option<PathBuf>,
+
+    /// Fetch money records dated on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE", conflicts_with = "db")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Fetch money records dated on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE", conflicts_with = "db")]
+    pub end_date: Option<NaiveDate>,
+}
+
+pub fn run<F>(args: &QueryArgs, session: F, format: OutputFormat) -> Result<ExitCode>
+where
+    F: FnOnce() -> Result<Session>
+{
+    let conn = match &args.db {
+        Some(path) => db::open_read_only(path)?,
+        None => {
+            let session = session()?;
+            if !session.is_logged_in() {
+                return Err(anyhow!("not logged in. Please run `auth login`"));
+            }
+            let mut conn = db::open_in_memory()?;
+            load(&mut conn, &session.api()?, &MoneyQuery {
+                start_date: args.start_date,
+                end_date: args.end_date,
+                ..Default::default()
+            })?;
+            conn
+        },
+    };
+
+    // money of queries has names instead of raw records
+    conn.execute_batch("CREATE TEMP VIEW money AS SELECT * FROM main.money_view;
+                        PRAGMA query_only = ON;")?;
+
+    query(&conn, &args.sql)?.print(format)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Fetch money records and master data into the database
+fn load(conn: &mut Connection, api: &ZaimApi, query: &MoneyQuery) -> Result<()> {
+    let categories = api.categories()?;
+    let genres = api.genres()?;
+    let accounts = api.accounts()?;
+    let money = api.money(query)?;
+
+    let tx = conn.transaction()?;
+    db::save_categories(&tx, &categories)?;
+    db::save_genres(&tx, &genres)?;
+    db::save_accounts(&tx, &accounts)?;
+    db::save_money(&tx, &money, None, &Local::now().to_rfc3339())?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Run SQL and collect the result into a table
+pub fn query(conn: &Connection, sql: &str) -> Result<Table> {
+    let mut stmt = conn.prepare(sql).map_err(|e| anyhow!("invalid query\n{}", e))?;
+    let headers: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let n = headers.len();
+
+    let mut table = Table { headers, rows: Vec::new() };
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut cells = Vec::with_capacity(n);
+        for i in 0..n {
+            cells.push(to_json(row.get_ref(i)?));
+        }
+        table.push(cells);
+    }
+
+    Ok(table)
+}
+
+fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(i) => json!(i),
+        ValueRef::Real(f) => json!(f),
+        ValueRef::Text(t) => json!(String::from_utf8_lossy(t)),
+        ValueRef::Blob(b) => json!(b.iter().map(|x| format!("{:02x}", x)).collect::<String>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::query;
+    use crate::db;
+    use zaim_cli::models::{Category, Mode, Money};
+
+    use serde_json::json;
+
+    #[test]
+    fn test_query_with_names() {
+        let mut conn = db::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        db::save_categories(&tx, &[Category {
+            id: 101, name: String::from("食費"), mode: Mode::Payment,
+            sort: None, parent_category_id: None, active: Some(1), modified: None,
+        }]).unwrap();
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-05", "amount": 480, "category_id": 101},
+            {"id": 2, "mode": "payment", "date": "2026-01-06", "amount": 1200, "category_id": 101},
+            {"id": 3, "mode": "payment", "date": "2025-12-31", "amount": 300, "category_id": 101},
+        ])).unwrap();
+        db::save_money(&tx, &money, None, "now").unwrap();
+        tx.commit().unwrap();
+        conn.execute_batch("CREATE TEMP VIEW money AS SELECT * FROM main.money_view").unwrap();
+
+        let table = query(
+            &conn, "SELECT category, SUM(amount) AS total FROM money WHERE date >= '2026-01-01' GROUP BY 1"
+        ).unwrap();
+        assert_eq!(table.to_json(), json!([{"category": "食費", "total": 1680}]));
+    }
+}
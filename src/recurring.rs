@@ -0,0 +1,654 @@
+//! recurring subcommand: create money records on schedules
+//!
+//! Schedules are cron expressions (`minute hour day-of-month month day-of-week`, only dates are used)
+//! or RRULEs of RFC 5545 (FREQ, INTERVAL, BYDAY, BYMONTHDAY, BYMONTH, COUNT and UNTIL).
+//! The state file records the date through which records were created for each definition,
+//! so instances are created only once.
+//!
+//! ```toml
+//! [[recurring]]
+//! name = "rent"
+//! cron = "0 0 27 * *"
+//! start = "2026-01-01"
+//! mode = "payment"
+//! amount = 80000
+//! from_account = "銀行"
+//! genre = "家賃"
+//!
+//! [[recurring]]
+//! name = "salary"
+//! rrule = "FREQ=MONTHLY;BYMONTHDAY=-1"
+//! start = "2026-01-01"
+//! mode = "income"
+//! amount = 300000
+//! to_account = "銀行"
+//! category = "給与所得"
+//! ```
+
+use crate::config;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, NewIncome, NewMoney, NewPayment, NewTransfer};
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde_json::json;
+
+const RECURRING_FILE: &str = "recurring.toml";
+const STATE_FILE: &str = "recurring.json";
+
+#[derive(clap::Subcommand, Debug)]
+pub enum RecurringCommand {
+    /// Create money records due since the last run. With --dry-run, due records are only listed
+    Run(RunArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    /// Recurring definitions [default: $XDG_CONFIG_HOME/zaim-cli/recurring.toml]
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// File recording created instances [default: $XDG_DATA_HOME/zaim-cli/<profile>/recurring.json]
+    #[arg(long, value_name = "FILE")]
+    pub state: Option<PathBuf>,
+}
+
+/// Dates matching a cron expression. Minute and hour are checked but not used
+#[derive(Debug, PartialEq)]
+struct Cron {
+    days: u64,
+    last_day: bool,
+    months: u64,
+    weekdays: u64,
+    /// Day of month or day of week is `*`. Otherwise either of them matches like cron
+    any_day: bool,
+}
+
+/// Bits of values in a cron field, e.g. `1,15`, `1-5`, `*/2`
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let error = || anyhow!("invalid cron field: {}", field);
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<usize>().ok().filter(|s| *s > 0).ok_or_else(error)?),
+            None => (part, 1),
+        };
+        let (low, high) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((l, h)) => (l.parse().map_err(|_| error())?, h.parse().map_err(|_| error())?),
+            None => {
+                let v = range.parse().map_err(|_| error())?;
+                (v, if step > 1 { max } else { v })
+            },
+        };
+        if low < min || high > max || low > high {
+            return Err(error());
+        }
+        for v in (low..=high).step_by(step) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+fn is_last_day(d: NaiveDate) -> bool {
+    d.succ_opt().is_none_or(|n| n.month() != d.month())
+}
+
+fn days_in_month(d: NaiveDate) -> u32 {
+    let mut last = d;
+    while !is_last_day(last) {
+        last = last.succ_opt().unwrap();
+    }
+    last.day()
+}
+
+impl Cron {
+    fn parse(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(anyhow!("cron needs 5 fields: {}", s));
+        };
+        parse_cron_field(minute, 0, 59)?;
+        parse_cron_field(hour, 0, 23)?;
+
+        // `L` is the last day of month
+        let last_day = day.split(',').any(|p| p == "L");
+        let days = day.split(',').filter(|p| *p != "L").collect::<Vec<_>>().join(",");
+        let days = if days.is_empty() { 0 } else { parse_cron_field(&days, 1, 31)? };
+        let mut weekdays = parse_cron_field(weekday, 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(Self {
+            days,
+            last_day,
+            months: parse_cron_field(month, 1, 12)?,
+            weekdays,
+            any_day: day.starts_with('*') || weekday.starts_with('*'),
+        })
+    }
+
+    fn matches(&self, d: NaiveDate) -> bool {
+        let day = self.days & (1 << d.day()) != 0 || (self.last_day && is_last_day(d));
+        let weekday = self.weekdays & (1 << d.weekday().num_days_from_sunday()) != 0;
+        let day = if self.any_day { day && weekday } else { day || weekday };
+        day && self.months & (1 << d.month()) != 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Subset of RFC 5545 recurrence rules
+#[derive(Debug, PartialEq)]
+struct RRule {
+    freq: Freq,
+    interval: u32,
+    /// Weekdays with optional ordinals in the month, e.g. `-1FR` for the last Friday
+    by_day: Vec<(Option<i32>, Weekday)>,
+    /// Negative days count from the end of month
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+    count: Option<usize>,
+    until: Option<NaiveDate>,
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+impl RRule {
+    fn parse(s: &str) -> Result<Self> {
+        let error = |part: &str| anyhow!("invalid rrule part: {}", part);
+        let mut rule = Self {
+            freq: Freq::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            count: None,
+            until: None,
+        };
+        let mut freq = None;
+
+        for part in s.strip_prefix("RRULE:").unwrap_or(s).split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| error(part))?;
+            let list = value.split(',');
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Freq::Daily,
+                    "WEEKLY" => Freq::Weekly,
+                    "MONTHLY" => Freq::Monthly,
+                    "YEARLY" => Freq::Yearly,
+                    _ => return Err(error(part)),
+                }),
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0).ok_or_else(|| error(part))?,
+                "BYDAY" => for d in list {
+                    let d = d.to_ascii_uppercase();
+                    let (ordinal, weekday) = d.split_at(d.len().saturating_sub(2));
+                    let weekday = parse_weekday(weekday).ok_or_else(|| error(part))?;
+                    let ordinal = match ordinal {
+                        "" => None,
+                        o => Some(o.parse().ok().filter(|o: &i32| *o != 0 && o.abs() <= 5).ok_or_else(|| error(part))?),
+                    };
+                    rule.by_day.push((ordinal, weekday));
+                },
+                "BYMONTHDAY" => for d in list {
+                    rule.by_month_day.push(d.parse().ok().filter(|d: &i32| *d != 0 && d.abs() <= 31).ok_or_else(|| error(part))?);
+                },
+                "BYMONTH" => for m in list {
+                    rule.by_month.push(m.parse().ok().filter(|m| (1..=12).contains(m)).ok_or_else(|| error(part))?);
+                },
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| error(part))?),
+                "UNTIL" => rule.until = Some(
+                    NaiveDate::parse_from_str(value.get(..8).unwrap_or(value), "%Y%m%d").map_err(|_| error(part))?
+                ),
+                _ => return Err(anyhow!("unsupported rrule part: {}", part)),
+            }
+        }
+        rule.freq = freq.ok_or_else(|| anyhow!("rrule needs FREQ: {}", s))?;
+        Ok(rule)
+    }
+
+    /// Whether `d` is an occurrence of the rule starting from `start`, without COUNT and UNTIL
+    fn matches(&self, d: NaiveDate, start: NaiveDate) -> bool {
+        let interval = self.interval as i64;
+        let month_index = |x: NaiveDate| x.year() as i64 * 12 + x.month0() as i64;
+        let monday = |x: NaiveDate| x - Days::new(x.weekday().num_days_from_monday() as u64);
+        let in_interval = match self.freq {
+            Freq::Daily => (d - start).num_days() % interval == 0,
+            Freq::Weekly => (monday(d) - monday(start)).num_days() / 7 % interval == 0,
+            Freq::Monthly => (month_index(d) - month_index(start)) % interval == 0,
+            Freq::Yearly => (d.year() - start.year()) as i64 % interval == 0,
+        };
+
+        // Without rules of days, the day of start is repeated
+        let day_rules = !self.by_day.is_empty() || !self.by_month_day.is_empty();
+        let month = match (self.by_month.is_empty(), self.freq) {
+            (false, _) => self.by_month.contains(&d.month()),
+            (true, Freq::Yearly) => day_rules || d.month() == start.month(),
+            (true, _) => true,
+        };
+        let day = if day_rules {
+            let days = days_in_month(d) as i32;
+            let day = d.day() as i32;
+            let month_day = self.by_month_day.is_empty()
+                || self.by_month_day.iter().any(|m| if *m > 0 { *m == day } else { days + 1 + m == day });
+            let weekday = self.by_day.is_empty() || self.by_day.iter().any(|(o, w)| *w == d.weekday() && match o {
+                None => true,
+                Some(n) if *n > 0 => (day - 1) / 7 + 1 == *n,
+                Some(n) => (days - day) / 7 + 1 == -n,
+            });
+            month_day && weekday
+        } else {
+            match self.freq {
+                Freq::Daily => true,
+                Freq::Weekly => d.weekday() == start.weekday(),
+                Freq::Monthly | Freq::Yearly => d.day() == start.day(),
+            }
+        };
+
+        d >= start && in_interval && month && day
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Schedule {
+    Cron(Cron),
+    RRule(RRule),
+}
+
+impl Schedule {
+    /// Occurrences in `[from, to]` of the schedule starting from `start`
+    fn occurrences(&self, start: NaiveDate, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let (count, to) = match self {
+            Schedule::Cron(_) => (None, to),
+            Schedule::RRule(r) => (r.count, r.until.map_or(to, |u| u.min(to))),
+        };
+        let mut dates = Vec::new();
+        let mut n = 0;
+        let mut d = start;
+        while d <= to && count.is_none_or(|c| n < c) {
+            let matches = match self {
+                Schedule::Cron(c) => c.matches(d),
+                Schedule::RRule(r) => r.matches(d, start),
+            };
+            if matches {
+                n += 1;
+                if d >= from {
+                    dates.push(d);
+                }
+            }
+            d = d + Days::new(1);
+        }
+        dates
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RecurringFile {
+    #[serde(default)]
+    pub recurring: Vec<RecurringSpec>,
+}
+
+/// Schedule and the money record of a definition. Names or ids for accounts, category and genre
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RecurringSpec {
+    /// Unique name recorded in the state file
+    pub name: String,
+    pub cron: Option<String>,
+    pub rrule: Option<String>,
+    /// First date of the schedule (YYYY-MM-DD)
+    pub start: NaiveDate,
+    /// Last date of the schedule (YYYY-MM-DD)
+    pub end: Option<NaiveDate>,
+    pub mode: Mode,
+    pub amount: i64,
+    pub from_account: Option<String>,
+    pub to_account: Option<String>,
+    pub category: Option<String>,
+    pub genre: Option<String>,
+    pub place: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl RecurringFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        config::load_toml(path)
+    }
+
+    /// Fill the account paying payments and transfers, or receiving incomes
+    fn or_account(mut self, account: Option<&str>) -> Self {
+        for spec in self.recurring.iter_mut() {
+            let field = match spec.mode {
+                Mode::Income => &mut spec.to_account,
+                _ => &mut spec.from_account,
+            };
+            if field.is_none() {
+                *field = account.map(String::from);
+            }
+        }
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Recurring {
+    name: String,
+    schedule: Schedule,
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+    /// Money record to create. The date is replaced by each occurrence
+    money: NewMoney,
+}
+
+impl Recurring {
+    fn resolve(spec: &RecurringSpec, masters: &Masters) -> Result<Self> {
+        let schedule = match (&spec.cron, &spec.rrule) {
+            (Some(c), None) => Schedule::Cron(Cron::parse(c)?),
+            (None, Some(r)) => Schedule::RRule(RRule::parse(r)?),
+            _ => return Err(anyhow!("either of cron or rrule is required")),
+        };
+        let account = |a: &Option<String>| a.as_deref().map(|a| masters.account_id(a)).transpose();
+        let unexpected = |fields: &[(&str, bool)]| match fields.iter().find(|(_, given)| *given) {
+            Some((f, _)) => Err(anyhow!("{} is not used for {}", f, spec.mode)),
+            None => Ok(()),
+        };
+
+        let money = match spec.mode {
+            Mode::Payment => {
+                unexpected(&[("to_account", spec.to_account.is_some())])?;
+                let genre = spec.genre.as_deref().ok_or_else(|| anyhow!("genre is required for payment"))?;
+                let category_id = spec.category.as_deref().map(|c| masters.category_id(Mode::Payment, c)).transpose()?;
+                let genre_id = masters.genre_id(category_id, genre)?;
+                NewMoney::Payment(NewPayment {
+                    date: spec.start,
+                    amount: spec.amount,
+                    category_id: masters.genres[&genre_id].category_id,
+                    genre_id,
+                    from_account_id: account(&spec.from_account)?,
+                    name: None,
+                    place: spec.place.clone(),
+                    comment: spec.comment.clone(),
+                })
+            },
+            Mode::Income => {
+                unexpected(&[("from_account", spec.from_account.is_some()), ("genre", spec.genre.is_some())])?;
+                let category = spec.category.as_deref().ok_or_else(|| anyhow!("category is required for income"))?;
+                NewMoney::Income(NewIncome {
+                    date: spec.start,
+                    amount: spec.amount,
+                    category_id: masters.category_id(Mode::Income, category)?,
+                    to_account_id: account(&spec.to_account)?,
+                    place: spec.place.clone(),
+                    comment: spec.comment.clone(),
+                })
+            },
+            Mode::Transfer => {
+                unexpected(&[
+                    ("category", spec.category.is_some()), ("genre", spec.genre.is_some()), ("place", spec.place.is_some()),
+                ])?;
+                let required = |a: &Option<String>, f: &str| account(a)?.ok_or_else(|| anyhow!("{} is required for transfer", f));
+                NewMoney::Transfer(NewTransfer {
+                    date: spec.start,
+                    amount: spec.amount,
+                    from_account_id: required(&spec.from_account, "from_account")?,
+                    to_account_id: required(&spec.to_account, "to_account")?,
+                    comment: spec.comment.clone(),
+                })
+            },
+        };
+
+        Ok(Self { name: spec.name.clone(), schedule, start: spec.start, end: spec.end, money })
+    }
+
+    /// Dates due after the date recorded in the state, through `today`
+    fn due(&self, done: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+        let from = done.and_then(|d| d.succ_opt()).map_or(self.start, |d| d.max(self.start));
+        let to = self.end.map_or(today, |e| e.min(today));
+        self.schedule.occurrences(self.start, from, to)
+    }
+
+    fn on(&self, date: NaiveDate) -> NewMoney {
+        let mut money = self.money.clone();
+        match &mut money {
+            NewMoney::Payment(p) => p.date = date,
+            NewMoney::Income(i) => i.date = date,
+            NewMoney::Transfer(t) => t.date = date,
+        }
+        money
+    }
+}
+
+fn resolve_all(file: &RecurringFile, masters: &Masters) -> Result<Vec<Recurring>> {
+    let mut names = HashSet::new();
+    file.recurring.iter().map(|spec| {
+        if !names.insert(spec.name.as_str()) {
+            return Err(anyhow!("duplicate recurring name: {}", spec.name));
+        }
+        Recurring::resolve(spec, masters).map_err(|e| anyhow!("recurring {}: {}", spec.name, e))
+    }).collect()
+}
+
+/// Date through which records were created, by name of definitions
+type RecurringState = BTreeMap<String, NaiveDate>;
+
+/// Due record of a definition, and its id once created
+struct Instance<'a> {
+    recurring: &'a Recurring,
+    money: NewMoney,
+    id: Option<u64>,
+}
+
+fn instances_table(instances: &[Instance], masters: &Masters) -> Table {
+    let mut table = Table::new(&[
+        "name", "date", "mode", "amount", "category", "genre", "from_account", "to_account", "place", "status", "id",
+    ]);
+    for i in instances {
+        let (date, amount, category_id, genre_id, from, to, place) = match &i.money {
+            NewMoney::Payment(p) => (p.date, p.amount, Some(p.category_id), Some(p.genre_id), p.from_account_id, None, &p.place),
+            NewMoney::Income(m) => (m.date, m.amount, Some(m.category_id), None, None, m.to_account_id, &m.place),
+            NewMoney::Transfer(t) => (t.date, t.amount, None, None, Some(t.from_account_id), Some(t.to_account_id), &None),
+        };
+        table.push(vec![
+            json!(i.recurring.name), json!(date), json!(i.money.mode()), json!(amount),
+            json!(masters.category_name(category_id)), json!(masters.genre_name(genre_id)),
+            json!(masters.account_name(from)), json!(masters.account_name(to)), json!(place),
+            json!(if i.id.is_some() { "created" } else { "pending" }), json!(i.id),
+        ]);
+    }
+    table
+}
+
+pub fn run(command: &RecurringCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let RecurringCommand::Run(args) = command;
+    let path = match &args.file {
+        Some(p) => p.clone(),
+        None => config::config_dir()?.join(RECURRING_FILE),
+    };
+    let file = RecurringFile::load(&path)?.or_account(session.defaults.account.as_deref());
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+
+    // In dry run, master data are still read to list due records, and nothing is written
+    let mut api = session.api()?;
+    let dry_run = api.dry_run.take().is_some();
+    let masters = Masters::fetch(&api)?;
+    let definitions = resolve_all(&file, &masters)?;
+
+    let state_path = match &args.state {
+        Some(p) => p.clone(),
+        None => session.data_dir()?.join(STATE_FILE),
+    };
+    let mut state = config::load_json::<RecurringState>(&state_path)?;
+    let today = session.defaults.today()?;
+
+    let mut instances: Vec<Instance> = definitions.iter()
+        .flat_map(|r| r.due(state.get(&r.name).copied(), today).into_iter().map(move |d| Instance {
+            recurring: r,
+            money: r.on(d),
+            id: None,
+        }))
+        .collect();
+
+    if dry_run {
+        instances_table(&instances, &masters).print(format)?;
+        eprintln!("{} money records to create", instances.len());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let n = instances.len();
+    for i in 0..n {
+        match api.create_money(&instances[i].money) {
+            Ok(created) => instances[i].id = Some(created.id),
+            Err(e) => {
+                instances_table(&instances, &masters).print(format)?;
+                let r = instances[i].recurring;
+                return Err(anyhow!("{} of {} money records created\n{} on {}: {}", i, n, r.name, instances[i].money.date(), e));
+            },
+        }
+        state.insert(instances[i].recurring.name.clone(), instances[i].money.date());
+        config::save_json(&state_path, &state)?;
+    }
+    // Nothing is due until today even if no records were created
+    for r in definitions.iter().filter(|r| r.start <= today) {
+        state.insert(r.name.clone(), r.end.map_or(today, |e| e.min(today)));
+    }
+    config::save_json(&state_path, &state)?;
+
+    instances_table(&instances, &masters).print(format)?;
+    eprintln!("{} money records created", n);
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_all, Cron, RRule, RecurringFile, Schedule};
+    use crate::names::test_masters;
+    use zaim_cli::models::NewMoney;
+
+    use chrono::NaiveDate;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn dates(schedule: &Schedule, start: &str, to: &str) -> Vec<String> {
+        schedule.occurrences(date(start), date(start), date(to)).iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cron() {
+        let cron = Schedule::Cron(Cron::parse("0 9 L * *").unwrap());
+        assert_eq!(dates(&cron, "2026-01-15", "2026-03-31"), vec!["2026-01-31", "2026-02-28", "2026-03-31"]);
+
+        // Day of month or Sunday
+        let cron = Schedule::Cron(Cron::parse("* * 1,15 2 0").unwrap());
+        assert_eq!(dates(&cron, "2026-02-01", "2026-02-16"), vec!["2026-02-01", "2026-02-08", "2026-02-15"]);
+
+        let cron = Schedule::Cron(Cron::parse("0 0 * * 1-5/2").unwrap());
+        assert_eq!(dates(&cron, "2026-01-05", "2026-01-11"), vec!["2026-01-05", "2026-01-07", "2026-01-09"]);
+
+        assert!(Cron::parse("0 0 32 * *").is_err());
+        assert!(Cron::parse("0 0 * *").is_err());
+    }
+
+    #[test]
+    fn test_rrule() {
+        let rule = |s| Schedule::RRule(RRule::parse(s).unwrap());
+        assert_eq!(
+            dates(&rule("FREQ=MONTHLY;INTERVAL=2"), "2026-01-31", "2026-08-01"),
+            vec!["2026-01-31", "2026-03-31", "2026-05-31", "2026-07-31"]
+        );
+        assert_eq!(
+            dates(&rule("RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=2"), "2026-01-01", "2026-12-31"),
+            vec!["2026-01-30", "2026-02-27"]
+        );
+        assert_eq!(
+            dates(&rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH;UNTIL=20260115"), "2026-01-01", "2026-02-28"),
+            vec!["2026-01-01", "2026-01-12", "2026-01-15"]
+        );
+        assert_eq!(dates(&rule("FREQ=YEARLY"), "2024-02-29", "2028-12-31"), vec!["2024-02-29", "2028-02-29"]);
+
+        assert_eq!(RRule::parse("INTERVAL=2").unwrap_err().to_string(), "rrule needs FREQ: INTERVAL=2");
+        assert_eq!(RRule::parse("FREQ=DAILY;BYHOUR=9").unwrap_err().to_string(), "unsupported rrule part: BYHOUR=9");
+    }
+
+    #[test]
+    fn test_due() {
+        let masters = test_masters();
+        let file: RecurringFile = toml::from_str(r#"
+            [[recurring]]
+            name = "rent"
+            cron = "0 0 27 * *"
+            start = "2026-01-01"
+            end = "2026-05-31"
+            mode = "payment"
+            amount = 80000
+            from_account = "Bank"
+            genre = "家賃"
+        "#).unwrap();
+        let definitions = resolve_all(&file, &masters).unwrap();
+        let rent = &definitions[0];
+
+        assert_eq!(rent.due(None, date("2026-02-27")), vec![date("2026-01-27"), date("2026-02-27")]);
+        assert_eq!(rent.due(Some(date("2026-02-27")), date("2026-10-18")), vec![date("2026-03-27"), date("2026-04-27"), date("2026-05-27")]);
+        assert_eq!(rent.due(Some(date("2026-05-31")), date("2026-10-18")), Vec::<NaiveDate>::new());
+        match rent.on(date("2026-03-27")) {
+            NewMoney::Payment(p) => assert_eq!((p.date, p.category_id, p.genre_id, p.from_account_id), (date("2026-03-27"), 104, 10401, Some(2))),
+            m => panic!("unexpected {:?}", m),
+        }
+
+        let file: RecurringFile = toml::from_str(r#"
+            [[recurring]]
+            name = "saving"
+            rrule = "FREQ=MONTHLY"
+            start = "2026-01-01"
+            mode = "transfer"
+            amount = 10000
+            from_account = "Bank"
+            genre = "家賃"
+        "#).unwrap();
+        assert_eq!(resolve_all(&file, &masters).unwrap_err().to_string(), "recurring saving: genre is not used for transfer");
+
+        // Transfers take the default account as from_account
+        let file: RecurringFile = toml::from_str(r#"
+            [[recurring]]
+            name = "saving"
+            rrule = "FREQ=MONTHLY"
+            start = "2026-01-01"
+            mode = "transfer"
+            amount = 10000
+            to_account = "Bank"
+        "#).unwrap();
+        let file = file.or_account(Some("1"));
+        assert_eq!(file.recurring[0].from_account.as_deref(), Some("1"));
+        assert!(resolve_all(&file, &masters).is_ok());
+    }
+}
@@ -0,0 +1,294 @@
+//! report subcommand: aggregate money records
+//!
+//! Transfers are not counted. Changes are percentages from the previous month (monthly)
+//! or the previous period of the same length (category).
+
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery};
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde_json::json;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Income, expense and net of each month in a year
+    Monthly(MonthlyArgs),
+    /// Amounts of each category (and genre) in a period
+    Category(CategoryArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct MonthlyArgs {
+    /// Year to report [default: this year]
+    #[arg(long)]
+    pub year: Option<i32>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CategoryArgs {
+    /// First date of the period (YYYY-MM-DD) [default: first day of this month]
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<NaiveDate>,
+
+    /// Last date of the period (YYYY-MM-DD) [default: today]
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<NaiveDate>,
+
+    /// Break down categories into genres
+    #[arg(long)]
+    pub genre: bool,
+}
+
+/// Percentage of change rounded to 0.1. None if nothing to compare.
+/// Increase is positive also from a negative net.
+fn change(previous: i64, current: i64) -> Option<f64> {
+    if previous == 0 {
+        return None;
+    }
+    Some(((current - previous) as f64 / previous.abs() as f64 * 1000.0).round() / 10.0)
+}
+
+fn first_day_of_month(d: NaiveDate) -> NaiveDate {
+    d.with_day(1).unwrap()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Totals {
+    income: i64,
+    expense: i64,
+}
+
+impl Totals {
+    fn add(&mut self, m: &Money) {
+        match m.mode {
+            Mode::Income => self.income += m.amount,
+            Mode::Payment => self.expense += m.amount,
+            Mode::Transfer => (),
+        }
+    }
+
+    fn net(&self) -> i64 {
+        self.income - self.expense
+    }
+}
+
+/// Totals of each month from `first` to `last`, and of the month before `first`
+fn monthly_totals(money: &[Money], first: NaiveDate, last: NaiveDate) -> (Totals, BTreeMap<NaiveDate, Totals>) {
+    let mut months = BTreeMap::new();
+    let mut month = first;
+    while month <= last {
+        months.insert(month, Totals::default());
+        month = month + Months::new(1);
+    }
+
+    let previous_month = first - Months::new(1);
+    let mut previous = Totals::default();
+    for m in money {
+        let month = first_day_of_month(m.date);
+        if month == previous_month {
+            previous.add(m);
+        } else if let Some(t) = months.get_mut(&month) {
+            t.add(m);
+        }
+    }
+    (previous, months)
+}
+
+fn monthly_table(previous: Totals, months: &BTreeMap<NaiveDate, Totals>) -> Table {
+    let mut table = Table::new(&["month", "income", "expense", "net", "income_change", "expense_change"]);
+    let mut previous = previous;
+    let mut total = Totals::default();
+    for (month, t) in months {
+        table.push(vec![
+            json!(month.format("%Y-%m").to_string()), json!(t.income), json!(t.expense), json!(t.net()),
+            json!(change(previous.income, t.income)), json!(change(previous.expense, t.expense)),
+        ]);
+        total.income += t.income;
+        total.expense += t.expense;
+        previous = *t;
+    }
+    table.push(vec![json!("total"), json!(total.income), json!(total.expense), json!(total.net()), json!(null), json!(null)]);
+    table
+}
+
+/// Key of category rows: mode, category id and genre id
+type CategoryKey = (Mode, Option<u64>, Option<u64>);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct CategoryTotal {
+    count: usize,
+    amount: i64,
+    previous: i64,
+}
+
+/// Totals of categories in `[from, to]` with amounts of the previous period
+fn category_totals(money: &[Money], from: NaiveDate, to: NaiveDate, genre: bool) -> BTreeMap<CategoryKey, CategoryTotal> {
+    let mut totals: BTreeMap<CategoryKey, CategoryTotal> = BTreeMap::new();
+    for m in money.iter().filter(|m| m.mode != Mode::Transfer) {
+        let key = (m.mode, m.category_id, if genre { m.genre_id } else { None });
+        let t = totals.entry(key).or_default();
+        if m.date >= from && m.date <= to {
+            t.count += 1;
+            t.amount += m.amount;
+        } else if m.date < from {
+            t.previous += m.amount;
+        }
+    }
+    totals
+}
+
+fn category_table(totals: &BTreeMap<CategoryKey, CategoryTotal>, masters: &Masters, genre: bool) -> Table {
+    let mut headers = vec!["mode", "category"];
+    if genre {
+        headers.push("genre");
+    }
+    headers.extend(["count", "amount", "ratio", "change"]);
+    let mut table = Table::new(&headers);
+
+    let name = |n: Option<&str>, id: Option<u64>| match (n, id) {
+        (Some(n), _) => json!(n),
+        (None, Some(i)) => json!(i.to_string()),
+        (None, None) => json!(null),
+    };
+    let mut sums: BTreeMap<Mode, CategoryTotal> = BTreeMap::new();
+    for ((mode, _, _), t) in totals.iter() {
+        let s = sums.entry(*mode).or_default();
+        s.count += t.count;
+        s.amount += t.amount;
+        s.previous += t.previous;
+    }
+
+    // Income first, and larger amounts first
+    let mut rows: Vec<(&CategoryKey, &CategoryTotal)> = totals.iter().collect();
+    rows.sort_by_key(|((mode, category, genre), t)| (*mode != Mode::Income, -t.amount, *category, *genre));
+    for ((mode, category_id, genre_id), t) in rows {
+        let sum = sums[mode].amount;
+        let mut row = vec![json!(mode), name(masters.category_name(*category_id), *category_id)];
+        if genre {
+            row.push(name(masters.genre_name(*genre_id), *genre_id));
+        }
+        row.extend([
+            json!(t.count), json!(t.amount),
+            json!(if sum == 0 { None } else { Some((t.amount as f64 / sum as f64 * 1000.0).round() / 10.0) }),
+            json!(change(t.previous, t.amount)),
+        ]);
+        table.push(row);
+    }
+
+    let income = sums.get(&Mode::Income).copied().unwrap_or_default();
+    let expense = sums.get(&Mode::Payment).copied().unwrap_or_default();
+    let net = CategoryTotal {
+        count: income.count + expense.count,
+        amount: income.amount - expense.amount,
+        previous: income.previous - expense.previous,
+    };
+    for (label, t) in [("income", income), ("payment", expense), ("net", net)] {
+        let mut row = vec![json!(label), json!("total")];
+        if genre {
+            row.push(json!(null));
+        }
+        row.extend([json!(t.count), json!(t.amount), json!(null), json!(change(t.previous, t.amount))]);
+        table.push(row);
+    }
+    table
+}
+
+pub fn run(command: &ReportCommand, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let api = session.api()?;
+    let today = session.defaults.today()?;
+
+    let table = match command {
+        ReportCommand::Monthly(args) => {
+            let year = args.year.unwrap_or(today.year());
+            let first = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| anyhow!("invalid year: {}", year))?;
+            let last = match year == today.year() {
+                true => first_day_of_month(today),
+                false => NaiveDate::from_ymd_opt(year, 12, 1).unwrap(),
+            };
+            let money = api.money(&MoneyQuery {
+                start_date: Some(first - Months::new(1)),
+                end_date: Some(last + Months::new(1) - Days::new(1)),
+                ..Default::default()
+            })?;
+            let (previous, months) = monthly_totals(&money, first, last);
+            monthly_table(previous, &months)
+        },
+        ReportCommand::Category(args) => {
+            let from = args.from.unwrap_or(first_day_of_month(today));
+            let to = args.to.unwrap_or(today);
+            if from > to {
+                return Err(anyhow!("--from is after --to"));
+            }
+            let days = (to - from).num_days() as u64 + 1;
+            let masters = Masters::fetch(&api)?;
+            let money = api.money(&MoneyQuery {
+                start_date: Some(from - Days::new(days)),
+                end_date: Some(to),
+                ..Default::default()
+            })?;
+            category_table(&category_totals(&money, from, to, args.genre), &masters, args.genre)
+        },
+    };
+
+    table.print(format)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{category_table, category_totals, change, monthly_table, monthly_totals};
+    use crate::names::test_masters;
+    use zaim_cli::models::Money;
+
+    use serde_json::json;
+
+    fn money() -> Vec<Money> {
+        serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2025-12-10", "amount": 1000, "category_id": 101},
+            {"id": 2, "mode": "payment", "date": "2026-01-05", "amount": 1500, "category_id": 101},
+            {"id": 3, "mode": "income", "date": "2026-01-25", "amount": 3000, "category_id": 11},
+            {"id": 4, "mode": "payment", "date": "2026-02-03", "amount": 500, "category_id": 199},
+            {"id": 5, "mode": "transfer", "date": "2026-02-03", "amount": 9999},
+        ])).unwrap()
+    }
+
+    #[test]
+    fn test_change() {
+        assert_eq!(change(1000, 1500), Some(50.0));
+        assert_eq!(change(3, 2), Some(-33.3));
+        assert_eq!(change(0, 100), None);
+        assert_eq!(change(-100, 100), Some(200.0));
+    }
+
+    #[test]
+    fn test_monthly() {
+        let (previous, months) = monthly_totals(&money(), "2026-01-01".parse().unwrap(), "2026-02-01".parse().unwrap());
+        assert_eq!(monthly_table(previous, &months).to_json(), json!([
+            {"month": "2026-01", "income": 3000, "expense": 1500, "net": 1500, "income_change": null, "expense_change": 50.0},
+            {"month": "2026-02", "income": 0, "expense": 500, "net": -500, "income_change": -100.0, "expense_change": -66.7},
+            {"month": "total", "income": 3000, "expense": 2000, "net": 1000, "income_change": null, "expense_change": null},
+        ]));
+    }
+
+    #[test]
+    fn test_category() {
+        let masters = test_masters();
+        let totals = category_totals(&money(), "2026-01-01".parse().unwrap(), "2026-02-28".parse().unwrap(), false);
+        let rows = category_table(&totals, &masters, false).to_json();
+
+        assert_eq!(rows[0], json!({"mode": "income", "category": "給与所得", "count": 1, "amount": 3000, "ratio": 100.0, "change": null}));
+        assert_eq!(rows[1], json!({"mode": "payment", "category": "食費", "count": 1, "amount": 1500, "ratio": 75.0, "change": 50.0}));
+        assert_eq!(rows[2], json!({"mode": "payment", "category": "199", "count": 1, "amount": 500, "ratio": 25.0, "change": null}));
+        assert_eq!(rows[5], json!({"mode": "net", "category": "total", "count": 3, "amount": 1000, "ratio": null, "change": 200.0}));
+    }
+}
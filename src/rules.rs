@@ -0,0 +1,288 @@
+//! apply-rules subcommand: categorize money records by rules
+//!
+//! Rules decide category and genre by patterns of texts, amount and account.
+//! The first matching rule wins. Rules with genre apply to payments, and rules with
+//! only category apply to incomes.
+//!
+//! ```toml
+//! [[rules]]
+//! # Regex of place, name and comment
+//! place = "(?i)starbucks|スターバックス"
+//! max_amount = 2000
+//! genre = "カフェ"
+//!
+//! [[rules]]
+//! name = "給与"
+//! account = "銀行"
+//! category = "給与所得"
+//! ```
+
+use crate::config;
+use crate::names::Masters;
+use crate::output::{OutputFormat, Table};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery};
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde_json::json;
+
+#[derive(clap::Args, Debug)]
+pub struct ApplyRulesArgs {
+    /// Rules file (toml)
+    #[arg(long, value_name = "FILE")]
+    pub rules: PathBuf,
+
+    /// Update money records. Without this, changes are only previewed
+    #[arg(long)]
+    pub commit: bool,
+
+    /// Check money records dated on or after this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Check money records dated on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub end_date: Option<NaiveDate>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RulesFile {
+    #[serde(default)]
+    pub rules: Vec<RuleSpec>,
+}
+
+/// Conditions and the category of a rule. Names or ids for category, genre and account
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSpec {
+    pub place: Option<String>,
+    pub name: Option<String>,
+    pub comment: Option<String>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    /// Matches either of from and to account
+    pub account: Option<String>,
+    pub category: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl RulesFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        config::load_toml(path)
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    place: Option<Regex>,
+    name: Option<Regex>,
+    comment: Option<Regex>,
+    min_amount: Option<i64>,
+    max_amount: Option<i64>,
+    account_id: Option<u64>,
+    mode: Mode,
+    category_id: u64,
+    genre_id: Option<u64>,
+}
+
+fn text_matches(pattern: &Option<Regex>, text: &Option<String>) -> bool {
+    pattern.as_ref().is_none_or(|p| p.is_match(text.as_deref().unwrap_or("")))
+}
+
+impl Rule {
+    fn compile(spec: &RuleSpec, masters: &Masters) -> Result<Self> {
+        let regex = |p: &Option<String>| p.as_deref().map(Regex::new).transpose();
+
+        let (mode, category_id, genre_id) = match (&spec.category, &spec.genre) {
+            (category, Some(genre)) => {
+                let category_id = category.as_deref().map(|c| masters.category_id(Mode::Payment, c)).transpose()?;
+                let genre_id = masters.genre_id(category_id, genre)?;
+                (Mode::Payment, masters.genres[&genre_id].category_id, Some(genre_id))
+            },
+            // Payments need a genre, so that a rule without it is for incomes
+            (Some(category), None) => match masters.category_id(Mode::Income, category) {
+                Ok(id) => (Mode::Income, id, None),
+                Err(e) => match masters.category_id(Mode::Payment, category) {
+                    Ok(_) => return Err(anyhow!("genre is required for payment category {}", category)),
+                    Err(_) => return Err(e),
+                },
+            },
+            (None, None) => return Err(anyhow!("category or genre is required")),
+        };
+
+        Ok(Self {
+            place: regex(&spec.place)?,
+            name: regex(&spec.name)?,
+            comment: regex(&spec.comment)?,
+            min_amount: spec.min_amount,
+            max_amount: spec.max_amount,
+            account_id: spec.account.as_deref().map(|a| masters.account_id(a)).transpose()?,
+            mode,
+            category_id,
+            genre_id,
+        })
+    }
+
+    fn matches(&self, m: &Money) -> bool {
+        m.mode == self.mode
+            && text_matches(&self.place, &m.place)
+            && text_matches(&self.name, &m.name)
+            && text_matches(&self.comment, &m.comment)
+            && self.min_amount.is_none_or(|a| m.amount >= a)
+            && self.max_amount.is_none_or(|a| m.amount <= a)
+            && self.account_id.is_none_or(|a| m.from_account_id == Some(a) || m.to_account_id == Some(a))
+    }
+}
+
+/// Compiled rules
+#[derive(Debug)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn compile(file: &RulesFile, masters: &Masters) -> Result<Self> {
+        file.rules.iter().enumerate()
+            .map(|(i, spec)| Rule::compile(spec, masters).map_err(|e| anyhow!("rule {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    /// Number (1-based) of the first matching rule, category id and genre id
+    pub fn categorize(&self, m: &Money) -> Option<(usize, u64, Option<u64>)> {
+        self.0.iter().enumerate()
+            .find(|(_, r)| r.matches(m))
+            .map(|(i, r)| (i + 1, r.category_id, r.genre_id))
+    }
+}
+
+/// Money record whose category differs from the matching rule
+#[derive(Debug)]
+struct Change {
+    rule: usize,
+    before: Money,
+    after: Money,
+}
+
+fn find_changes(rules: &Rules, money: &[Money]) -> Vec<Change> {
+    money.iter().filter_map(|m| {
+        let (rule, category_id, genre_id) = rules.categorize(m)?;
+        if m.category_id == Some(category_id) && (genre_id.is_none() || m.genre_id == genre_id) {
+            return None;
+        }
+        let after = Money { category_id: Some(category_id), genre_id: genre_id.or(m.genre_id), ..m.clone() };
+        Some(Change { rule, before: m.clone(), after })
+    }).collect()
+}
+
+fn changes_table(changes: &[Change], masters: &Masters, updated: usize) -> Table {
+    let mut table = Table::new(&[
+        "id", "date", "mode", "amount", "place", "name", "rule",
+        "category", "genre", "new_category", "new_genre", "status",
+    ]);
+    for (i, c) in changes.iter().enumerate() {
+        let m = &c.before;
+        table.push(vec![
+            json!(m.id), json!(m.date), json!(m.mode), json!(m.amount), json!(m.place), json!(m.name), json!(c.rule),
+            json!(masters.category_name(m.category_id)), json!(masters.genre_name(m.genre_id)),
+            json!(masters.category_name(c.after.category_id)), json!(masters.genre_name(c.after.genre_id)),
+            json!(if i < updated { "updated" } else { "pending" }),
+        ]);
+    }
+    table
+}
+
+pub fn run(args: &ApplyRulesArgs, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    let file = RulesFile::load(&args.rules)?;
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+
+    let api = session.api()?;
+    let masters = Masters::fetch(&api)?;
+    let rules = Rules::compile(&file, &masters)?;
+    let money = api.money(&MoneyQuery {
+        start_date: args.start_date,
+        end_date: args.end_date,
+        ..Default::default()
+    })?;
+    let changes = find_changes(&rules, &money);
+
+    if !args.commit {
+        changes_table(&changes, &masters, 0).print(format)?;
+        eprintln!("{} money records to update. Run with --commit to update them", changes.len());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for (i, c) in changes.iter().enumerate() {
+        if let Err(e) = api.update_money(&c.after) {
+            changes_table(&changes, &masters, i).print(format)?;
+            return Err(anyhow!("{} of {} money records updated\n{} {}: {}", i, changes.len(), c.after.mode, c.after.id, e));
+        }
+    }
+    changes_table(&changes, &masters, changes.len()).print(format)?;
+    eprintln!("{} money records updated", changes.len());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_changes, Rules, RulesFile};
+    use crate::names::test_masters as masters;
+    use zaim_cli::models::Money;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_rules() {
+        let file: RulesFile = toml::from_str(r#"
+            [[rules]]
+            place = "(?i)starbucks"
+            max_amount = 2000
+            genre = "カフェ"
+
+            [[rules]]
+            place = "(?i)starbucks"
+            genre = "食料品"
+
+            [[rules]]
+            account = "Bank"
+            category = "給与所得"
+        "#).unwrap();
+        let rules = Rules::compile(&file, &masters()).unwrap();
+
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-05", "amount": 480, "place": "STARBUCKS",
+             "category_id": 101, "genre_id": 10101},
+            {"id": 2, "mode": "payment", "date": "2026-01-05", "amount": 3000, "place": "Starbucks",
+             "category_id": 101, "genre_id": 10101},
+            {"id": 3, "mode": "income", "date": "2026-01-25", "amount": 300000, "to_account_id": 2},
+            {"id": 4, "mode": "payment", "date": "2026-01-05", "amount": 300, "place": "Cafe"},
+        ])).unwrap();
+        assert_eq!(rules.categorize(&money[0]), Some((1, 101, Some(10102))));
+        assert_eq!(rules.categorize(&money[1]), Some((2, 101, Some(10101))));
+        assert_eq!(rules.categorize(&money[2]), Some((3, 11, None)));
+        assert_eq!(rules.categorize(&money[3]), None);
+
+        let changes = find_changes(&rules, &money);
+        assert_eq!(changes.iter().map(|c| c.before.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(changes[1].after.category_id, Some(11));
+    }
+
+    #[test]
+    fn test_rules_error() {
+        let file: RulesFile = toml::from_str("[[rules]]\nplace = \"cafe\"\n").unwrap();
+        assert_eq!(Rules::compile(&file, &masters()).unwrap_err().to_string(), "rule 1: category or genre is required");
+        let file: RulesFile = toml::from_str("[[rules]]\nplace = \"(\"\ngenre = \"カフェ\"\n").unwrap();
+        assert!(Rules::compile(&file, &masters()).is_err());
+        let file: RulesFile = toml::from_str("[[rules]]\nplace = \"cafe\"\ncategory = \"食費\"\n").unwrap();
+        assert_eq!(Rules::compile(&file, &masters()).unwrap_err().to_string(),
+                   "rule 1: genre is required for payment category 食費");
+    }
+}
@@ -0,0 +1,388 @@
+//! Resolution of credentials from command line, environment, profiles and credential helper
+
+use crate::config::{self, Config, Defaults, Profile};
+use crate::credential::{CredentialHelper, Credentials, Target};
+use crate::master_cache::MasterCache;
+use crate::transport::{NetworkOptions, ReqwestTransport, Transport};
+use crate::zaim_api::{self, AccessTokens, ConsumerInfo, DryRun, RetryPolicy, ZaimApi};
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use sha1::{Digest, Sha1};
+
+/// File to save access tokens when neither profile nor credential helper is used
+const LEGACY_ACCESS_TOKEN_FILE: &str = "access_tokens.json";
+
+#[derive(clap::Args, Debug, Default)]
+pub struct CredentialArgs {
+    /// File path of consumer (client) information, which presented by json.
+    /// If omitted, ZAIM_CONSUMER_KEY and ZAIM_CONSUMER_SECRET are used
+    #[arg(long, value_name = "FILE")]
+    pub consumer_info: Option<PathBuf>,
+
+    /// File path of access tokens, which presented by json.
+    /// If omitted, ZAIM_ACCESS_TOKEN and ZAIM_ACCESS_TOKEN_SECRET are used
+    #[arg(long, value_name = "FILE")]
+    pub access_token: Option<PathBuf>,
+
+    /// External credential helper to get, store and erase credentials
+    /// (e.g. "pass-zaim"). It is used for items not given by files or environment variables.
+    #[arg(long, value_name = "COMMAND")]
+    pub credential_helper: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub struct NetworkArgs {
+    /// Proxy url for all requests (e.g. "http://proxy.example.com:8080")
+    #[arg(long, value_name = "URL", global = true)]
+    pub proxy: Option<String>,
+
+    /// Comma separated hosts not to use the proxy (e.g. "localhost,.example.com")
+    #[arg(long, value_name = "HOSTS", global = true)]
+    pub no_proxy: Option<String>,
+
+    /// PEM file of additional root certificates. Can be given multiple times
+    #[arg(long, value_name = "FILE", global = true)]
+    pub cacert: Vec<PathBuf>,
+
+    /// Timeout to connect in seconds (0: no timeout) [default: 10]
+    #[arg(long, value_name = "SECS", global = true)]
+    pub connect_timeout: Option<u64>,
+
+    /// Timeout of a whole request in seconds (0: no timeout) [default: 60]
+    #[arg(long, value_name = "SECS", global = true)]
+    pub timeout: Option<u64>,
+
+    /// User-Agent header
+    #[arg(long, value_name = "STRING", global = true)]
+    pub user_agent: Option<String>,
+}
+
+impl NetworkArgs {
+    /// Override options of configuration by command line
+    pub fn apply(&self, options: &mut NetworkOptions) {
+        if let Some(p) = &self.proxy {
+            options.proxy = Some(p.clone());
+        }
+        if let Some(p) = &self.no_proxy {
+            options.no_proxy = Some(p.clone());
+        }
+        options.ca_certificates.extend(self.cacert.iter().cloned());
+        if let Some(t) = self.connect_timeout {
+            options.connect_timeout_secs = t;
+        }
+        if let Some(t) = self.timeout {
+            options.timeout_secs = t;
+        }
+        if let Some(ua) = &self.user_agent {
+            options.user_agent = Some(ua.clone());
+        }
+    }
+}
+
+pub fn open_and_read_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut data = String::new();
+    let _ = file.read_to_string(&mut data)?;
+
+    Ok(data)
+}
+
+fn read_json_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    if ! path.exists() {
+        bail!("{} not found", path.display());
+    }
+
+    let data = open_and_read_file(path)
+        .map_err(|e| anyhow!("failed to open and read {}\n{}", path.display(), e))?;
+    serde_json::from_str(&data)
+        .map_err(|e| anyhow!("failed to parse {} into json\n{}", path.display(), e))
+}
+
+/// Save access tokens readable only by the user (0600, in directories of 0700 if created)
+fn save_access_tokens(path: &Path, access_tokens: &AccessTokens) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(dir)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode is applied only to new files
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+
+    let data = serde_json::to_string(access_tokens)?;
+    file.write_all(data.as_bytes())?;
+
+    Ok(())
+}
+
+/// Directory name of the master cache. Credentials given by command line or
+/// environment may be of another Zaim user than the profile, so that the cache
+/// is separated by api base and credentials, not only by profile name.
+fn cache_key(api_base: &str, consumer_info: Option<&ConsumerInfo>, access_tokens: Option<&AccessTokens>) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(api_base.as_bytes());
+    for v in [consumer_info.map(|c| &c.consumer_key), access_tokens.map(|t| &t.access_token)] {
+        hasher.update(b"\n");
+        hasher.update(v.map_or("", |v| v.as_str()).as_bytes());
+    }
+    hasher.finalize()[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn transport_for(options: &NetworkOptions) -> Result<ReqwestTransport> {
+    ReqwestTransport::with_options(options)
+        .map_err(|e| anyhow!("failed to build http client\n{}", e))
+}
+
+pub struct Session {
+    /// Selected profile name, None if no profile is configured
+    pub profile_name: Option<String>,
+    pub profile: Profile,
+    /// Effective defaults of the profile
+    pub defaults: Defaults,
+    pub consumer_info: Option<ConsumerInfo>,
+    pub access_tokens: Option<AccessTokens>,
+    pub retry: RetryPolicy,
+    pub dry_run: Option<DryRun>,
+    /// Cache of master data of the profile. None if disabled
+    pub cache: Option<MasterCache>,
+    /// Shared by api clients built from this session
+    pub transport: Arc<dyn Transport>,
+    credential_helper: Option<CredentialHelper>,
+    access_token_path: PathBuf,
+}
+
+impl Session {
+    /// Resolve credentials. Each of consumer information and access tokens is
+    /// searched in following order.
+    /// 1. Files given by --consumer-info / --access-token
+    /// 2. Environment variables (ZAIM_CONSUMER_KEY, ZAIM_CONSUMER_SECRET,
+    ///    ZAIM_ACCESS_TOKEN, ZAIM_ACCESS_TOKEN_SECRET)
+    /// 3. Files of the selected profile, or `[credential]` of configuration
+    /// 4. Credential helper (--credential-helper, or the one of the profile)
+    pub fn resolve(args: &CredentialArgs, config: &Config, profile_name: Option<&str>) -> Result<Self> {
+        let (profile_name, profile) = match config.select_profile(profile_name)? {
+            Some((n, p)) => (Some(n), p),
+            None => (None, config.global_profile()),
+        };
+
+        let consumer_info = match args.consumer_info.as_deref() {
+            Some(p) => Some(read_json_file::<ConsumerInfo>(p)?),
+            None => ConsumerInfo::from_env()?,
+        };
+        let access_tokens = match args.access_token.as_deref() {
+            Some(p) => Some(read_json_file::<AccessTokens>(p)?),
+            None => AccessTokens::from_env()?,
+        };
+        let credential_helper = args.credential_helper.clone()
+            .or_else(|| profile.credential_helper.clone());
+
+        Self::resolve_with(config, profile_name, profile, consumer_info, access_tokens, credential_helper)
+    }
+
+    /// Resolve credentials only from the profile, ignoring command line and environment.
+    pub fn for_profile(config: &Config, name: &str, profile: &Profile) -> Result<Self> {
+        let credential_helper = profile.credential_helper.clone();
+        Self::resolve_with(config, Some(name.to_string()), profile.clone(), None, None, credential_helper)
+    }
+
+    fn resolve_with(
+        config: &Config,
+        profile_name: Option<String>,
+        profile: Profile,
+        mut consumer_info: Option<ConsumerInfo>,
+        mut access_tokens: Option<AccessTokens>,
+        credential_helper: Option<String>
+    ) -> Result<Self> {
+        let api_base = profile.api_base.as_deref().unwrap_or(zaim_api::API_BASE);
+        let credential_helper = match credential_helper {
+            Some(command) => {
+                let target = Target::new(api_base, profile_name.as_deref().unwrap_or(config::DEFAULT_PROFILE))?;
+                Some(CredentialHelper::new(&command, target))
+            },
+            None => None,
+        };
+
+        let access_token_path = match (&profile_name, &profile.access_token) {
+            (Some(n), _) => profile.access_token_path(n)?,
+            (None, Some(p)) => p.clone(),
+            (None, None) => PathBuf::from(LEGACY_ACCESS_TOKEN_FILE),
+        };
+        let stored = profile_name.is_some() || profile.access_token.is_some();
+
+        if consumer_info.is_none() {
+            if let Some(p) = profile.consumer_info.as_deref() {
+                consumer_info = Some(read_json_file(p)?);
+            }
+        }
+        if access_tokens.is_none() && stored && access_token_path.exists() {
+            access_tokens = Some(read_json_file(&access_token_path)?);
+        }
+
+        if let Some(helper) = &credential_helper {
+            if consumer_info.is_none() || access_tokens.is_none() {
+                let credentials = helper.get()
+                    .map_err(|e| anyhow!("failed to get credentials from helper\n{}", e))?;
+                consumer_info = consumer_info.or_else(|| credentials.consumer_info());
+                access_tokens = access_tokens.or_else(|| credentials.access_tokens());
+            }
+        }
+
+        let cache = match config.cache.ttl_secs {
+            0 => None,
+            ttl => {
+                let dir = config::cache_dir()?
+                    .join(profile_name.as_deref().unwrap_or(config::DEFAULT_PROFILE))
+                    .join(cache_key(api_base, consumer_info.as_ref(), access_tokens.as_ref()));
+                Some(MasterCache::new(&dir, std::time::Duration::from_secs(ttl)))
+            },
+        };
+
+        let defaults = config.defaults(&profile)?;
+        defaults.tz()?;
+
+        Ok(Self {
+            profile_name,
+            profile,
+            defaults,
+            consumer_info,
+            access_tokens,
+            retry: config.retry.clone(),
+            dry_run: None,
+            cache,
+            transport: Arc::new(transport_for(&config.network)?),
+            credential_helper,
+            access_token_path,
+        })
+    }
+
+    /// Directory for state of commands of the profile, e.g. `$XDG_DATA_HOME/zaim-cli/<profile>`
+    pub fn data_dir(&self) -> Result<PathBuf> {
+        Ok(config::data_dir()?.join(self.profile_name.as_deref().unwrap_or(config::DEFAULT_PROFILE)))
+    }
+
+    pub fn is_logged_in(&self) -> bool {
+        self.consumer_info.is_some() && self.access_tokens.is_some()
+    }
+
+    /// Build api client. Access tokens may be None.
+    pub fn api(&self) -> Result<ZaimApi> {
+        let consumer_info = match &self.consumer_info {
+            Some(c) => c.clone(),
+            None => bail!(
+                "consumer information is not provided by --consumer-info, {}/{}, profile or credential helper",
+                zaim_api::ENV_CONSUMER_KEY, zaim_api::ENV_CONSUMER_SECRET
+            ),
+        };
+        let api_base = self.profile.api_base.as_deref().unwrap_or(zaim_api::API_BASE);
+
+        let mut api = ZaimApi::with_api_base(consumer_info, self.access_tokens.clone(), api_base);
+        api.retry = self.retry.clone();
+        api.dry_run = self.dry_run;
+        api.cache = self.cache.clone();
+        api.transport = self.transport.clone();
+        Ok(api)
+    }
+
+    /// Save access tokens of api into credential helper if available, otherwise into file.
+    pub fn store_access_tokens(&self, api: &ZaimApi) -> Result<()> {
+        let access_tokens = match &api.access_tokens {
+            Some(t) => t,
+            None => bail!("User authentication not done"),
+        };
+
+        match &self.credential_helper {
+            Some(helper) => {
+                let mut credentials = Credentials::from_access_tokens(access_tokens);
+                credentials.consumer_key = Some(api.consumer_info.consumer_key.clone());
+                helper.store(&credentials)
+            },
+            None => save_access_tokens(&self.access_token_path, access_tokens),
+        }
+    }
+
+    /// Remove stored access tokens from credential helper or file.
+    /// Return false if nothing was stored.
+    pub fn erase_access_tokens(&self) -> Result<bool> {
+        if let Some(helper) = &self.credential_helper {
+            let mut credentials = match &self.access_tokens {
+                Some(t) => Credentials::from_access_tokens(t),
+                None => Credentials::default(),
+            };
+            credentials.consumer_key = self.consumer_info.as_ref().map(|c| c.consumer_key.clone());
+            helper.erase(&credentials)?;
+            return Ok(true);
+        }
+
+        if self.access_token_path.exists() {
+            std::fs::remove_file(&self.access_token_path)
+                .map_err(|e| anyhow!("failed to remove {}\n{}", self.access_token_path.display(), e))?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_key, save_access_tokens, Session};
+    use crate::config::{Config, Profile};
+    use crate::zaim_api::{AccessTokens, ConsumerInfo};
+
+    #[test]
+    fn test_cache_key() {
+        let consumer = ConsumerInfo { consumer_key: String::from("key"), consumer_secret: String::from("secret") };
+        let tokens = |t: &str| AccessTokens { access_token: t.to_string(), access_token_secret: String::from("s") };
+        let base = "https://api.zaim.net/v2";
+
+        let key = cache_key(base, Some(&consumer), Some(&tokens("a")));
+        assert_eq!(key.len(), 16);
+        assert_eq!(key, cache_key(base, Some(&consumer), Some(&tokens("a"))));
+        assert_ne!(key, cache_key(base, Some(&consumer), Some(&tokens("b"))));
+        assert_ne!(key, cache_key("http://localhost:8080/v2", Some(&consumer), Some(&tokens("a"))));
+        assert_ne!(key, cache_key(base, Some(&consumer), None));
+    }
+
+    #[test]
+    fn test_data_dir_of_profiles() {
+        let config = Config::default();
+        let data_dir = |name: &str| Session::for_profile(&config, name, &Profile::default()).unwrap().data_dir().unwrap();
+
+        assert!(data_dir("personal").ends_with("zaim-cli/personal"));
+        assert_ne!(data_dir("personal"), data_dir("household"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_access_tokens_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("zaim-cli-test-tokens-{}", std::process::id()));
+        let path = dir.join("profiles").join("p").join("access_tokens.json");
+        let tokens = AccessTokens { access_token: String::from("t"), access_token_secret: String::from("s") };
+
+        save_access_tokens(&path, &tokens).unwrap();
+        let mode = |p: &std::path::Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+        // Existing files are also made private
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        save_access_tokens(&path, &tokens).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
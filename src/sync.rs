@@ -0,0 +1,181 @@
+//! sync subcommand: mirror household data into a local SQLite database
+
+use crate::db;
+use crate::output::{OutputFormat, Table, print_json};
+use crate::session::Session;
+
+use zaim_cli::models::MoneyQuery;
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Days, FixedOffset, NaiveDate};
+use rusqlite::Connection;
+use serde_json::json;
+
+/// Date of the last sync in the timezone of defaults
+const STATE_LAST_SYNC_DATE: &str = "last_sync_date";
+/// Time of the last sync (RFC 3339)
+const STATE_LAST_SYNC_AT: &str = "last_sync_at";
+
+#[derive(clap::Args, Debug)]
+pub struct SyncArgs {
+    /// SQLite database file. It is created if not exists
+    #[arg(long, value_name = "FILE")]
+    pub db: PathBuf,
+
+    /// Fetch all money records instead of the ones since the last sync
+    #[arg(long)]
+    pub full: bool,
+
+    /// Money records dated within these days before the last sync are fetched again,
+    /// to catch records changed or deleted after the last sync.
+    /// Changes of older records are not caught without --full, and a record moved to
+    /// an older date is flagged as deleted until --full (Zaim API cannot fetch a record by id)
+    #[arg(long, value_name = "DAYS", default_value_t = 30)]
+    pub lookback_days: u64,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct SyncSummary {
+    since: Option<NaiveDate>,
+    money: db::Changes,
+    categories: db::Changes,
+    genres: db::Changes,
+    accounts: db::Changes,
+}
+
+impl SyncSummary {
+    fn table(&self) -> Table {
+        let mut table = Table::new(&["table", "fetched", "inserted", "updated", "deleted"]);
+        for (name, c) in [
+            ("money", &self.money),
+            ("categories", &self.categories),
+            ("genres", &self.genres),
+            ("accounts", &self.accounts),
+        ] {
+            table.push(vec![json!(name), json!(c.fetched), json!(c.inserted), json!(c.updated), json!(c.deleted)]);
+        }
+        table
+    }
+}
+
+/// Fetch master data and money records into the database
+fn sync(api: &ZaimApi, conn: &mut Connection, args: &SyncArgs, now: DateTime<FixedOffset>) -> Result<SyncSummary> {
+    let since = match db::get_state(conn, STATE_LAST_SYNC_DATE)? {
+        Some(d) if !args.full => {
+            let last: NaiveDate = d.parse()
+                .map_err(|e| anyhow!("broken {} in {}: {}", STATE_LAST_SYNC_DATE, args.db.display(), e))?;
+            last.checked_sub_days(Days::new(args.lookback_days))
+        },
+        _ => None,
+    };
+
+    let categories = api.categories()?;
+    let genres = api.genres()?;
+    let accounts = api.accounts()?;
+    let money = api.money(&MoneyQuery { start_date: since, ..Default::default() })?;
+
+    let tx = conn.transaction()?;
+    let summary = SyncSummary {
+        since,
+        categories: db::save_categories(&tx, &categories)?,
+        genres: db::save_genres(&tx, &genres)?,
+        accounts: db::save_accounts(&tx, &accounts)?,
+        money: db::save_money(&tx, &money, since.map(|d| d.to_string()).as_deref(), &now.to_rfc3339())?,
+    };
+    db::set_state(&tx, STATE_LAST_SYNC_DATE, &now.date_naive().to_string())?;
+    db::set_state(&tx, STATE_LAST_SYNC_AT, &now.to_rfc3339())?;
+    tx.commit()?;
+
+    Ok(summary)
+}
+
+pub fn run(args: &SyncArgs, session: &Session, format: OutputFormat) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    let mut api = session.api()?;
+    // The mirror gets the latest master data, which also refresh the cache
+    if let Some(cache) = api.cache.as_mut() {
+        cache.refresh = true;
+    }
+    let mut conn = db::open(&args.db)?;
+
+    let summary = sync(&api, &mut conn, args, session.defaults.now()?)?;
+    match format {
+        OutputFormat::Json => print_json(&summary)?,
+        _ => summary.table().print(format)?,
+    }
+    if let Some(since) = summary.since {
+        eprintln!("money records dated before {} were not checked. Use --full to catch their changes", since);
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sync, SyncArgs};
+    use crate::db;
+    use zaim_cli::transport::{FakeTransport, HttpResponse};
+    use zaim_cli::zaim_api::{AccessTokens, ConsumerInfo, ZaimApi};
+
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use chrono::{FixedOffset, TimeZone};
+    use serde_json::json;
+
+    fn jst() -> FixedOffset {
+        FixedOffset::east_opt(9 * 3600).unwrap()
+    }
+
+    fn push_responses(transport: &FakeTransport, money: serde_json::Value) {
+        transport.push_response(HttpResponse::new(200, &json!({"categories": [
+            {"id": 101, "name": "食費", "mode": "payment"},
+        ]}).to_string()));
+        transport.push_response(HttpResponse::new(200, &json!({"genres": [
+            {"id": 10101, "category_id": 101, "name": "食料品"},
+        ]}).to_string()));
+        transport.push_response(HttpResponse::new(200, &json!({"accounts": [{"id": 1, "name": "お財布"}]}).to_string()));
+        transport.push_response(HttpResponse::new(200, &json!({"money": money}).to_string()));
+    }
+
+    #[test]
+    fn test_sync() {
+        let transport = Arc::new(FakeTransport::new());
+        let mut api = ZaimApi::with_consumer_info(
+            ConsumerInfo { consumer_key: String::from("key"), consumer_secret: String::from("secret") },
+            Some(AccessTokens { access_token: String::from("token"), access_token_secret: String::from("secret") })
+        );
+        api.transport = transport.clone();
+        let mut conn = db::open_in_memory().unwrap();
+        let args = SyncArgs { db: PathBuf::from("test.sqlite"), full: false, lookback_days: 30 };
+
+        push_responses(&transport, json!([
+            {"id": 1, "mode": "payment", "date": "2026-01-10", "amount": 480},
+            {"id": 2, "mode": "payment", "date": "2026-03-01", "amount": 1200},
+            {"id": 3, "mode": "payment", "date": "2026-03-05", "amount": 300},
+        ]));
+        let summary = sync(&api, &mut conn, &args, jst().with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap()).unwrap();
+        assert_eq!(summary.since, None);
+        assert_eq!((summary.money.inserted, summary.categories.inserted, summary.accounts.inserted), (3, 1, 1));
+
+        // Fetched since 30 days before the last sync: 2 is changed, 3 is deleted and 4 is new
+        push_responses(&transport, json!([
+            {"id": 2, "mode": "payment", "date": "2026-03-01", "amount": 1500},
+            {"id": 4, "mode": "payment", "date": "2026-03-15", "amount": 100},
+        ]));
+        let summary = sync(&api, &mut conn, &args, jst().with_ymd_and_hms(2026, 3, 20, 9, 0, 0).unwrap()).unwrap();
+        assert_eq!(summary.since.map(|d| d.to_string()).as_deref(), Some("2026-02-08"));
+        assert_eq!(
+            (summary.money.fetched, summary.money.inserted, summary.money.updated, summary.money.deleted),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(summary.categories.updated, 0);
+        assert!(transport.requests()[7].url.contains("start_date=2026-02-08"));
+    }
+}
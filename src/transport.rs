@@ -0,0 +1,265 @@
+//! Transports to send signed http requests
+
+use crate::zaim_api::{PreparedRequest, ZaimApiError};
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use reqwest::{header, Certificate, Client, Method, NoProxy, Proxy, Request, RequestBuilder, Url};
+
+pub const DEFAULT_USER_AGENT: &str = concat!("zaim-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Options of http client
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct NetworkOptions {
+    /// Proxy url for all requests (e.g. "http://proxy.example.com:8080").
+    /// If omitted, HTTPS_PROXY and HTTP_PROXY are used.
+    pub proxy: Option<String>,
+    /// Comma separated hosts, domains or IP networks not to use the proxy
+    pub no_proxy: Option<String>,
+    /// PEM files of root certificates trusted in addition to the system ones
+    pub ca_certificates: Vec<PathBuf>,
+    /// Timeout to connect. 0 disables it.
+    pub connect_timeout_secs: u64,
+    /// Timeout of a whole request including the response body. 0 disables it.
+    pub timeout_secs: u64,
+    pub user_agent: Option<String>,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: None,
+            ca_certificates: Vec::new(),
+            connect_timeout_secs: 10,
+            timeout_secs: 60,
+            user_agent: None,
+        }
+    }
+}
+
+/// Response of a http request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self { status, headers: Vec::new(), body: body.to_string() }
+    }
+}
+
+/// Send a signed request and return the response.
+/// Responses of any status are returned as Ok, and Err is only for failures to
+/// get a response. Use `ZaimApiError::transient` for failures worth retrying
+/// (e.g. connection refused or timeout).
+pub trait Transport: Send + Sync {
+    fn send(&self, request: &PreparedRequest) -> Result<HttpResponse, ZaimApiError>;
+}
+
+/// Transport by reqwest. The client and the runtime are shared by all requests.
+/// By default, the client is built with default options on the first request.
+#[derive(Default)]
+pub struct ReqwestTransport {
+    client: OnceLock<Client>,
+    runtime: OnceLock<tokio::runtime::Runtime>,
+}
+
+/// Proxy urls of HTTPS_PROXY, HTTP_PROXY and ALL_PROXY (or lowercase ones) by scheme,
+/// which reqwest uses if no proxy is given
+fn env_proxies() -> Vec<(&'static str, String)> {
+    let var = |keys: &[&str]| keys.iter().find_map(|k| std::env::var(k).ok().filter(|v| !v.is_empty()));
+    [
+        ("https", var(&["HTTPS_PROXY", "https_proxy"])),
+        ("http", var(&["HTTP_PROXY", "http_proxy"])),
+        ("all", var(&["ALL_PROXY", "all_proxy"])),
+    ]
+        .into_iter()
+        .filter_map(|(scheme, url)| url.map(|u| (scheme, u)))
+        .collect()
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        Self { client: OnceLock::from(client), runtime: OnceLock::new() }
+    }
+
+    pub fn with_options(options: &NetworkOptions) -> Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(options.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+
+        let no_proxy = || options.no_proxy.as_deref().and_then(NoProxy::from_string);
+        if let Some(url) = &options.proxy {
+            let proxy = Proxy::all(url).map_err(|e| anyhow!("invalid proxy {}\n{}", url, e))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy()));
+        } else if options.no_proxy.is_some() {
+            // Proxies of environment variables are set explicitly to apply no_proxy to them
+            for (scheme, url) in env_proxies() {
+                let proxy = match scheme {
+                    "https" => Proxy::https(&url),
+                    "http" => Proxy::http(&url),
+                    _ => Proxy::all(&url),
+                }.map_err(|e| anyhow!("invalid proxy {}\n{}", url, e))?;
+                builder = builder.proxy(proxy.no_proxy(no_proxy()));
+            }
+        }
+
+        for path in options.ca_certificates.iter() {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("failed to read {}\n{}", path.display(), e))?;
+            let certs = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| anyhow!("failed to parse {} as PEM\n{}", path.display(), e))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if options.connect_timeout_secs > 0 {
+            builder = builder.connect_timeout(Duration::from_secs(options.connect_timeout_secs));
+        }
+        if options.timeout_secs > 0 {
+            builder = builder.timeout(Duration::from_secs(options.timeout_secs));
+        }
+
+        Ok(Self::new(builder.build()?))
+    }
+
+    fn client(&self) -> Result<&Client, ZaimApiError> {
+        if let Some(c) = self.client.get() {
+            return Ok(c);
+        }
+        let client = Self::with_options(&NetworkOptions::default())
+            .map_err(|e| ZaimApiError::new(format!("failed to build http client: {}", e)))?
+            .client.into_inner().unwrap();
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    fn runtime(&self) -> Result<&tokio::runtime::Runtime, ZaimApiError> {
+        if let Some(r) = self.runtime.get() {
+            return Ok(r);
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ZaimApiError::new(format!("failed to build tokio runtime: {}", e)))?;
+        Ok(self.runtime.get_or_init(|| runtime))
+    }
+
+    fn build(&self, client: &Client, prepared: &PreparedRequest) -> Result<RequestBuilder> {
+        let mut headers = header::HeaderMap::new();
+        for (k, v) in prepared.headers.iter() {
+            headers.insert(
+                header::HeaderName::from_bytes(k.as_bytes())?,
+                header::HeaderValue::from_str(v)?
+            );
+        }
+
+        let mut request_builder = RequestBuilder::from_parts(
+            client.clone(),
+            Request::new(Method::from_bytes(prepared.method.as_bytes())?, Url::parse(&prepared.url)?)
+        );
+        request_builder = request_builder.headers(headers);
+
+        if let Some(body) = &prepared.body {
+            request_builder = request_builder.body(body.clone());
+        }
+
+        Ok(request_builder)
+    }
+
+    async fn send_async(&self, prepared: &PreparedRequest) -> Result<HttpResponse, ZaimApiError> {
+        let request_builder = match self.build(self.client()?, prepared) {
+            Ok(r) => r,
+            Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
+        };
+
+        let http_res = match request_builder.send().await {
+            Ok(r) => r,
+            Err(e) if e.is_connect() || e.is_timeout() => {
+                return Err(ZaimApiError::transient(format!("reqwest Error: {}", e)))
+            },
+            Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
+        };
+
+        let status = http_res.status().as_u16();
+        let headers = http_res.headers().iter()
+            .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v.as_bytes()).to_string()))
+            .collect();
+        match http_res.text().await {
+            Ok(body) => Ok(HttpResponse { status, headers, body }),
+            Err(e) if e.is_timeout() => Err(ZaimApiError::transient(format!("reqwest Error: {}", e))),
+            Err(e) => Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
+        }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: &PreparedRequest) -> Result<HttpResponse, ZaimApiError> {
+        self.runtime()?.block_on(self.send_async(request))
+    }
+}
+
+/// In-memory transport for tests.
+/// Queued responses are returned in order, and sent requests are kept.
+#[derive(Debug, Default)]
+pub struct FakeTransport {
+    responses: Mutex<VecDeque<HttpResponse>>,
+    requests: Mutex<Vec<PreparedRequest>>,
+}
+
+impl FakeTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a response for the next request
+    pub fn push_response(&self, response: HttpResponse) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    /// Requests sent so far
+    pub fn requests(&self) -> Vec<PreparedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&self, request: &PreparedRequest) -> Result<HttpResponse, ZaimApiError> {
+        self.requests.lock().unwrap().push(request.clone());
+        match self.responses.lock().unwrap().pop_front() {
+            Some(r) => Ok(r),
+            None => Err(ZaimApiError::new(format!(
+                "no fake response for {} {}", request.method, request.url
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkOptions, ReqwestTransport};
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_reqwest_transport_with_options() {
+        let mut options = NetworkOptions {
+            proxy: Some(String::from("http://proxy.example.com:8080")),
+            no_proxy: Some(String::from("localhost")),
+            user_agent: Some(String::from("test")),
+            ..Default::default()
+        };
+        assert!(ReqwestTransport::with_options(&options).is_ok());
+
+        options.ca_certificates.push(PathBuf::from("/nonexistent/ca.pem"));
+        assert!(ReqwestTransport::with_options(&options).is_err());
+    }
+}
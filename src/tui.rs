@@ -0,0 +1,668 @@
+//! tui subcommand: browse and edit money records in a full-screen terminal UI
+//!
+//! Changes are applied to the list first and reverted if the request fails.
+
+use crate::add::parse_amount;
+use crate::names::{FilterArgs, Masters};
+use crate::session::Session;
+
+use zaim_cli::models::{Mode, Money, MoneyQuery, NewIncome, NewMoney, NewPayment, NewTransfer};
+use zaim_cli::zaim_api::ZaimApi;
+
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Rows moved by PageUp and PageDown
+const PAGE: usize = 10;
+
+const HELP: &str = "j/k move  / filter  e edit  d delete  c duplicate  r reload  q quit";
+
+#[derive(clap::Args, Debug)]
+pub struct TuiArgs {
+    /// Show money records dated on or after this date (YYYY-MM-DD)
+    /// [default: first day of the month 2 months ago]
+    #[arg(long, value_name = "DATE")]
+    pub start_date: Option<NaiveDate>,
+
+    /// Show money records dated on or before this date (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub end_date: Option<NaiveDate>,
+
+    #[command(flatten)]
+    pub filter: FilterArgs,
+}
+
+/// Editable field of a money record and its text
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Date,
+    Amount,
+    Category,
+    Genre,
+    Place,
+    Name,
+    Comment,
+}
+
+impl Field {
+    fn label(&self) -> &'static str {
+        match self {
+            Field::Date => "date",
+            Field::Amount => "amount",
+            Field::Category => "category",
+            Field::Genre => "genre",
+            Field::Place => "place",
+            Field::Name => "name",
+            Field::Comment => "comment",
+        }
+    }
+
+    fn of(mode: Mode) -> &'static [Field] {
+        match mode {
+            Mode::Payment => &[Field::Date, Field::Amount, Field::Genre, Field::Place, Field::Name, Field::Comment],
+            Mode::Income => &[Field::Date, Field::Amount, Field::Category, Field::Place, Field::Comment],
+            Mode::Transfer => &[Field::Date, Field::Amount, Field::Comment],
+        }
+    }
+}
+
+/// Form to edit a money record
+#[derive(Debug)]
+struct Form {
+    index: usize,
+    fields: Vec<(Field, String)>,
+    /// Texts before editing, to keep fields not changed as they are
+    original: Vec<String>,
+    focus: usize,
+}
+
+impl Form {
+    fn new(index: usize, m: &Money, masters: &Masters) -> Self {
+        let text = |s: &Option<String>| s.clone().unwrap_or_default();
+        let fields: Vec<(Field, String)> = Field::of(m.mode).iter().map(|f| (*f, match f {
+            Field::Date => m.date.to_string(),
+            Field::Amount => m.amount.to_string(),
+            Field::Category => masters.category_name(m.category_id).map(String::from).unwrap_or_default(),
+            Field::Genre => masters.genre_name(m.genre_id).map(String::from).unwrap_or_default(),
+            Field::Place => text(&m.place),
+            Field::Name => text(&m.name),
+            Field::Comment => text(&m.comment),
+        })).collect();
+        let original = fields.iter().map(|(_, v)| v.clone()).collect();
+        Self { index, fields, original, focus: 0 }
+    }
+
+    /// Money record with values of the form. Only changed fields are parsed and resolved
+    fn apply(&self, m: &Money, masters: &Masters) -> Result<Money> {
+        let mut m = m.clone();
+        for ((field, value), original) in self.fields.iter().zip(self.original.iter()) {
+            let value = value.trim();
+            if value == original.trim() {
+                continue;
+            }
+            let text = (!value.is_empty()).then(|| value.to_string());
+            match field {
+                Field::Date => m.date = value.parse().map_err(|_| anyhow!("invalid date: {}", value))?,
+                Field::Amount => m.amount = parse_amount(value).ok_or_else(|| anyhow!("invalid amount: {}", value))?,
+                Field::Category => m.category_id = Some(masters.category_id(m.mode, value)?),
+                Field::Genre => {
+                    // Same names are common across categories (e.g. "その他")
+                    let genre_id = match m.category_id.map(|c| masters.genre_id(Some(c), value)) {
+                        Some(Ok(id)) => id,
+                        _ => masters.genre_id(None, value)?,
+                    };
+                    m.category_id = Some(masters.genres[&genre_id].category_id);
+                    m.genre_id = Some(genre_id);
+                },
+                Field::Place => m.place = text,
+                Field::Name => m.name = text,
+                Field::Comment => m.comment = text,
+            }
+        }
+        Ok(m)
+    }
+}
+
+#[derive(Debug)]
+enum Input {
+    Normal,
+    Filter,
+    Edit(Form),
+    /// Waiting for confirmation to delete the selected record
+    Delete,
+}
+
+/// Request for a change already applied to the list
+#[derive(Debug, PartialEq)]
+enum Action {
+    Update { index: usize, before: Money },
+    Delete { index: usize, money: Money },
+    /// Copy is inserted at the index with id 0 until created
+    Create { index: usize, money: NewMoney },
+    Reload,
+}
+
+/// Parameters to create a copy of the money record
+fn copy_of(m: &Money, date: NaiveDate) -> Result<NewMoney> {
+    let missing = |f: &str| anyhow!("{} {} has no {}", m.mode, m.id, f);
+    Ok(match m.mode {
+        Mode::Payment => NewMoney::Payment(NewPayment {
+            date,
+            amount: m.amount,
+            category_id: m.category_id.ok_or_else(|| missing("category"))?,
+            genre_id: m.genre_id.ok_or_else(|| missing("genre"))?,
+            from_account_id: m.from_account_id,
+            name: m.name.clone(),
+            place: m.place.clone(),
+            comment: m.comment.clone(),
+        }),
+        Mode::Income => NewMoney::Income(NewIncome {
+            date,
+            amount: m.amount,
+            category_id: m.category_id.ok_or_else(|| missing("category"))?,
+            to_account_id: m.to_account_id,
+            place: m.place.clone(),
+            comment: m.comment.clone(),
+        }),
+        Mode::Transfer => NewMoney::Transfer(NewTransfer {
+            date,
+            amount: m.amount,
+            from_account_id: m.from_account_id.ok_or_else(|| missing("from account"))?,
+            to_account_id: m.to_account_id.ok_or_else(|| missing("to account"))?,
+            comment: m.comment.clone(),
+        }),
+    })
+}
+
+/// Newer records first
+fn sort_money(money: &mut [Money]) {
+    money.sort_by(|a, b| b.date.cmp(&a.date).then(b.id.cmp(&a.id)));
+}
+
+struct App {
+    money: Vec<Money>,
+    masters: Masters,
+    today: NaiveDate,
+    filter: String,
+    /// Indices of records matching the filter
+    visible: Vec<usize>,
+    table: TableState,
+    input: Input,
+    message: Option<String>,
+    quit: bool,
+}
+
+impl App {
+    fn new(mut money: Vec<Money>, masters: Masters, today: NaiveDate) -> Self {
+        sort_money(&mut money);
+        let mut app = Self {
+            money,
+            masters,
+            today,
+            filter: String::new(),
+            visible: Vec::new(),
+            table: TableState::default(),
+            input: Input::Normal,
+            message: None,
+            quit: false,
+        };
+        app.refilter();
+        app
+    }
+
+    /// Every word of the filter is in date, mode, amount, texts, category or genre
+    fn matches(&self, m: &Money) -> bool {
+        let text = [
+            m.date.to_string(), m.mode.to_string(), m.amount.to_string(),
+            m.place.clone().unwrap_or_default(), m.name.clone().unwrap_or_default(), m.comment.clone().unwrap_or_default(),
+            self.masters.category_name(m.category_id).unwrap_or_default().to_string(),
+            self.masters.genre_name(m.genre_id).unwrap_or_default().to_string(),
+        ].join("\n").to_lowercase();
+        self.filter.to_lowercase().split_whitespace().all(|w| text.contains(w))
+    }
+
+    fn refilter(&mut self) {
+        let selected = self.selected();
+        self.visible = (0..self.money.len()).filter(|i| self.matches(&self.money[*i])).collect();
+        let row = selected.and_then(|s| self.visible.iter().position(|i| *i == s))
+            .unwrap_or(self.table.selected().unwrap_or(0));
+        self.table.select((!self.visible.is_empty()).then(|| row.min(self.visible.len() - 1)));
+    }
+
+    /// Index of the selected record in `money`
+    fn selected(&self) -> Option<usize> {
+        self.table.selected().and_then(|r| self.visible.get(r)).copied()
+    }
+
+    fn select(&mut self, index: usize) {
+        self.refilter();
+        if let Some(row) = self.visible.iter().position(|i| *i == index) {
+            self.table.select(Some(row));
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if let Some(row) = self.table.selected() {
+            let last = self.visible.len().saturating_sub(1) as isize;
+            self.table.select(Some((row as isize + delta).clamp(0, last) as usize));
+        }
+    }
+
+    /// Income and expense of each month of visible records, newer months first
+    fn monthly_totals(&self) -> Vec<(String, i64, i64)> {
+        let mut months: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+        for m in self.visible.iter().map(|i| &self.money[*i]) {
+            let t = months.entry(m.date.format("%Y-%m").to_string()).or_default();
+            match m.mode {
+                Mode::Income => t.0 += m.amount,
+                Mode::Payment => t.1 += m.amount,
+                Mode::Transfer => (),
+            }
+        }
+        months.into_iter().rev().map(|(month, (income, expense))| (month, income, expense)).collect()
+    }
+
+    /// Handle a key. The returned action is already applied to the list and should be requested
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        let action = match std::mem::replace(&mut self.input, Input::Normal) {
+            Input::Normal => self.handle_normal(key),
+            Input::Filter => {
+                match key.code {
+                    KeyCode::Enter => (),
+                    KeyCode::Esc => self.filter.clear(),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                        self.input = Input::Filter;
+                    },
+                    KeyCode::Char(c) => {
+                        self.filter.push(c);
+                        self.input = Input::Filter;
+                    },
+                    _ => self.input = Input::Filter,
+                }
+                None
+            },
+            Input::Edit(form) => self.handle_edit(key, form),
+            Input::Delete => match (key.code, self.selected()) {
+                (KeyCode::Char('y'), Some(index)) => {
+                    let money = self.money.remove(index);
+                    Some(Action::Delete { index, money })
+                },
+                _ => {
+                    self.message = Some(String::from("canceled"));
+                    None
+                },
+            },
+        };
+        self.refilter();
+        action
+    }
+
+    fn handle_edit(&mut self, key: KeyEvent, mut form: Form) -> Option<Action> {
+        let n = form.fields.len();
+        match key.code {
+            KeyCode::Esc => return None,
+            KeyCode::Enter => match form.apply(&self.money[form.index], &self.masters) {
+                Ok(after) => {
+                    let before = std::mem::replace(&mut self.money[form.index], after);
+                    return Some(Action::Update { index: form.index, before });
+                },
+                Err(e) => self.message = Some(e.to_string()),
+            },
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % n,
+            KeyCode::BackTab | KeyCode::Up => form.focus = (form.focus + n - 1) % n,
+            KeyCode::Backspace => {
+                form.fields[form.focus].1.pop();
+            },
+            KeyCode::Char(c) => form.fields[form.focus].1.push(c),
+            _ => (),
+        }
+        self.input = Input::Edit(form);
+        None
+    }
+
+    fn handle_normal(&mut self, key: KeyEvent) -> Option<Action> {
+        self.message = None;
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.refilter();
+            },
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('j') | KeyCode::Down => self.move_by(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(PAGE as isize),
+            KeyCode::PageUp => self.move_by(-(PAGE as isize)),
+            KeyCode::Char('g') | KeyCode::Home => self.move_by(isize::MIN / 2),
+            KeyCode::Char('G') | KeyCode::End => self.move_by(isize::MAX / 2),
+            KeyCode::Char('/') => self.input = Input::Filter,
+            KeyCode::Char('r') => return Some(Action::Reload),
+            KeyCode::Char('e') => if let Some(i) = self.selected() {
+                self.input = Input::Edit(Form::new(i, &self.money[i], &self.masters));
+            },
+            KeyCode::Char('d') if self.selected().is_some() => self.input = Input::Delete,
+            KeyCode::Char('c') => if let Some(i) = self.selected() {
+                match copy_of(&self.money[i], self.today) {
+                    Ok(money) => {
+                        let copy = Money { id: 0, date: self.today, receipt_id: None, created: None, modified: None, ..self.money[i].clone() };
+                        // Before other records of today
+                        let index = self.money.iter().position(|m| m.date <= self.today).unwrap_or(self.money.len());
+                        self.money.insert(index, copy);
+                        self.select(index);
+                        return Some(Action::Create { index, money });
+                    },
+                    Err(e) => self.message = Some(e.to_string()),
+                }
+            },
+            _ => (),
+        }
+        None
+    }
+
+    /// Send the request of an action, and revert the list on failure
+    fn perform(&mut self, action: Action, api: &ZaimApi, query: &MoneyQuery) {
+        let result = match action {
+            Action::Update { index, before } => {
+                let m = &self.money[index];
+                match api.update_money(m) {
+                    Ok(_) => Ok(format!("{} {} updated", m.mode, m.id)),
+                    Err(e) => {
+                        self.money[index] = before;
+                        Err(e.to_string())
+                    },
+                }
+            },
+            Action::Delete { index, money } => match api.delete_money(money.mode, money.id) {
+                Ok(_) => Ok(format!("{} {} deleted", money.mode, money.id)),
+                Err(e) => {
+                    self.money.insert(index, money);
+                    Err(e.to_string())
+                },
+            },
+            Action::Create { index, money } => match api.create_money(&money) {
+                Ok(created) => {
+                    self.money[index].id = created.id;
+                    Ok(format!("{} {} created", money.mode(), created.id))
+                },
+                Err(e) => {
+                    self.money.remove(index);
+                    Err(e.to_string())
+                },
+            },
+            Action::Reload => match api.money(query) {
+                Ok(mut money) => {
+                    sort_money(&mut money);
+                    self.money = money;
+                    Ok(format!("{} money records loaded", self.money.len()))
+                },
+                Err(e) => Err(e.to_string()),
+            },
+        };
+        self.message = Some(match result {
+            Ok(m) => m,
+            Err(e) => format!("failed: {}", e.replace('\n', " ")),
+        });
+        self.refilter();
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, detail, status] = Layout::vertical([
+            Constraint::Min(5), Constraint::Length(9), Constraint::Length(1),
+        ]).areas(frame.area());
+        let [list, sidebar] = Layout::horizontal([Constraint::Min(40), Constraint::Length(36)]).areas(main);
+
+        self.draw_list(frame, list);
+        self.draw_totals(frame, sidebar);
+        self.draw_detail(frame, detail);
+
+        let status_line = match &self.input {
+            Input::Filter => Line::from(format!("/{}", self.filter)),
+            Input::Delete => match self.selected().map(|i| &self.money[i]) {
+                Some(m) => Line::from(format!("Delete {} {} of {}? (y/n)", m.mode, m.id, m.date)).bold(),
+                None => Line::default(),
+            },
+            Input::Edit(_) => Line::from(self.message.as_deref().unwrap_or("Tab next  Enter save  Esc cancel")),
+            Input::Normal => Line::from(self.message.as_deref().unwrap_or(HELP)),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.visible.iter().map(|i| {
+            let m = &self.money[*i];
+            let amount = match m.mode {
+                Mode::Payment => format!("-{}", m.amount),
+                Mode::Income => format!("+{}", m.amount),
+                Mode::Transfer => m.amount.to_string(),
+            };
+            let category = match (self.masters.category_name(m.category_id), self.masters.genre_name(m.genre_id)) {
+                (Some(c), Some(g)) => format!("{}/{}", c, g),
+                (Some(c), None) => c.to_string(),
+                _ => m.mode.to_string(),
+            };
+            Row::new(vec![
+                m.date.to_string(),
+                format!("{:>10}", amount),
+                category,
+                m.place.clone().or(m.name.clone()).unwrap_or_default(),
+            ])
+        });
+        let title = match self.filter.is_empty() {
+            true => format!(" Money ({}) ", self.money.len()),
+            false => format!(" Money ({}/{}) /{} ", self.visible.len(), self.money.len(), self.filter),
+        };
+        let table = Table::new(rows, [
+            Constraint::Length(10), Constraint::Length(10), Constraint::Fill(1), Constraint::Fill(1),
+        ])
+            .header(Row::new(["date", "    amount", "category", "place"]).bold())
+            .block(Block::bordered().title(title))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_totals(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.monthly_totals().into_iter().map(|(month, income, expense)| {
+            Row::new(vec![month, format!("{:>9}", income), format!("{:>9}", expense)])
+        });
+        let table = Table::new(rows, [Constraint::Length(7), Constraint::Length(9), Constraint::Length(9)])
+            .header(Row::new(["month", "   income", "  expense"]).bold())
+            .block(Block::bordered().title(" Monthly "));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect) {
+        let line = |label: &str, value: String| Line::from(vec![Span::from(format!("{:<13}", label)).bold(), Span::from(value)]);
+        let lines = match (&self.input, self.selected().map(|i| &self.money[i])) {
+            (Input::Edit(form), _) => form.fields.iter().enumerate().map(|(i, (f, v))| {
+                let l = line(f.label(), v.clone());
+                if i == form.focus { l.reversed() } else { l }
+            }).collect(),
+            (_, Some(m)) => {
+                let name = |n: Option<&str>| n.unwrap_or("-").to_string();
+                let text = |t: &Option<String>| t.clone().unwrap_or_default();
+                vec![
+                    line("id", format!("{} {}", m.mode, if m.id == 0 { String::from("(creating)") } else { m.id.to_string() })),
+                    line("date", m.date.to_string()),
+                    line("amount", m.amount.to_string()),
+                    line("category", format!("{} / {}", name(self.masters.category_name(m.category_id)), name(self.masters.genre_name(m.genre_id)))),
+                    line("account", format!("{} -> {}", name(self.masters.account_name(m.from_account_id)), name(self.masters.account_name(m.to_account_id)))),
+                    line("place / name", format!("{} / {}", text(&m.place), text(&m.name))),
+                    line("comment", text(&m.comment)),
+                ]
+            },
+            (_, None) => Vec::new(),
+        };
+        let title = if matches!(self.input, Input::Edit(_)) { " Edit " } else { " Detail " };
+        frame.render_widget(Paragraph::new(lines).block(Block::bordered().title(title)), area);
+    }
+}
+
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App, api: &ZaimApi, query: &MoneyQuery) -> Result<()> {
+    while !app.quit {
+        terminal.draw(|f| app.draw(f))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if let Some(action) = app.handle_key(key) {
+            // Show the change before the request
+            terminal.draw(|f| app.draw(f))?;
+            app.perform(action, api, query);
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: &TuiArgs, session: &Session) -> Result<ExitCode> {
+    if !session.is_logged_in() {
+        return Err(anyhow!("not logged in. Please run `auth login`"));
+    }
+    if !std::io::stdout().is_terminal() {
+        return Err(anyhow!("tui needs a terminal"));
+    }
+
+    let api = session.api()?;
+    let today = session.defaults.today()?;
+    let masters = Masters::fetch(&api)?;
+    let mut query = MoneyQuery {
+        start_date: Some(args.start_date.unwrap_or(today.with_day(1).unwrap() - Months::new(2))),
+        end_date: args.end_date,
+        ..Default::default()
+    };
+    args.filter.apply(&mut query, &masters)?;
+    let mut app = App::new(api.money(&query)?, masters, today);
+
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, &mut app, &api, &query);
+    ratatui::restore();
+    result?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, App, Form};
+    use crate::names::test_masters;
+    use zaim_cli::models::{Money, NewMoney};
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use serde_json::json;
+
+    fn app() -> App {
+        let masters = test_masters();
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 1, "mode": "payment", "date": "2026-09-10", "amount": 1000, "category_id": 101, "genre_id": 10101, "place": "Market"},
+            {"id": 2, "mode": "payment", "date": "2026-10-05", "amount": 480, "category_id": 101, "genre_id": 10102, "place": "Starbucks"},
+            {"id": 3, "mode": "income", "date": "2026-09-25", "amount": 3000, "category_id": 11},
+        ])).unwrap();
+        App::new(money, masters, "2026-10-18".parse().unwrap())
+    }
+
+    fn keys(app: &mut App, keys: &str) -> Option<Action> {
+        keys.chars().fold(None, |_, c| app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)))
+    }
+
+    fn key(app: &mut App, code: KeyCode) -> Option<Action> {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_filter_and_totals() {
+        let mut app = app();
+        assert_eq!(app.money.iter().map(|m| m.id).collect::<Vec<_>>(), vec![2, 3, 1]);
+        assert_eq!(app.monthly_totals(), vec![(String::from("2026-10"), 0, 480), (String::from("2026-09"), 3000, 1000)]);
+
+        keys(&mut app, "/食費 09");
+        key(&mut app, KeyCode::Enter);
+        assert_eq!(app.visible, vec![2]);
+        assert_eq!(app.monthly_totals(), vec![(String::from("2026-09"), 0, 1000)]);
+        key(&mut app, KeyCode::Esc);
+        assert_eq!(app.visible.len(), 3);
+    }
+
+    #[test]
+    fn test_edit_delete_duplicate() {
+        let mut app = app();
+        keys(&mut app, "je");
+        key(&mut app, KeyCode::Down);
+        key(&mut app, KeyCode::Backspace);
+        keys(&mut app, "5");
+        let action = key(&mut app, KeyCode::Enter);
+        assert!(matches!(action, Some(Action::Update { index: 1, ref before }) if before.amount == 3000));
+        assert_eq!(app.money[1].amount, 3005);
+
+        keys(&mut app, "k");
+        assert!(keys(&mut app, "dn").is_none());
+        let action = keys(&mut app, "dy");
+        assert!(matches!(action, Some(Action::Delete { index: 0, ref money }) if money.id == 2));
+        assert_eq!(app.money.len(), 2);
+
+        // Copy of the income is dated today
+        let action = keys(&mut app, "c");
+        match action {
+            Some(Action::Create { index: 0, money: NewMoney::Income(i) }) => assert_eq!((i.date.to_string(), i.amount), (String::from("2026-10-18"), 3005)),
+            a => panic!("unexpected {:?}", a),
+        }
+        assert_eq!((app.money[0].id, app.selected()), (0, Some(0)));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+    }
+
+    #[test]
+    fn test_form_shared_genre_name() {
+        let app = app();
+        let money: Vec<Money> = serde_json::from_value(json!([
+            {"id": 4, "mode": "payment", "date": "2026-10-01", "amount": 100, "category_id": 102, "genre_id": 10299},
+            {"id": 5, "mode": "payment", "date": "2026-10-01", "amount": 100, "category_id": 101, "genre_id": 99999},
+        ])).unwrap();
+
+        // Genre not changed is kept without resolving its name
+        let mut form = Form::new(0, &money[0], &app.masters);
+        form.fields[1].1 = String::from("200");
+        let after = form.apply(&money[0], &app.masters).unwrap();
+        assert_eq!((after.amount, after.category_id, after.genre_id), (200, Some(102), Some(10299)));
+        let mut form = Form::new(0, &money[1], &app.masters);
+        form.fields[1].1 = String::from("200");
+        assert_eq!(form.apply(&money[1], &app.masters).unwrap().genre_id, Some(99999));
+        for amount in ["0", "-200"] {
+            form.fields[1].1 = String::from(amount);
+            assert_eq!(form.apply(&money[1], &app.masters).unwrap_err().to_string(), format!("invalid amount: {}", amount));
+        }
+
+        // Typed genre is resolved in the category first
+        let mut form = Form::new(0, &app.money[2], &app.masters);
+        form.fields[2].1 = String::from("その他");
+        assert_eq!(form.apply(&app.money[2], &app.masters).unwrap().genre_id, Some(10199));
+        form.fields[2].1 = String::from("カフェ");
+        assert_eq!(form.apply(&app.money[2], &app.masters).unwrap().genre_id, Some(10102));
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = app();
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("Starbucks"));
+        assert!(screen.contains("2026-09"));
+        assert!(screen.contains("j/k move"));
+    }
+}
@@ -1,33 +1,77 @@
 //! Library for Zaim API
 
-use crate::oauth1a::OAuth1;
+use crate::helper;
+use crate::master_cache::MasterCache;
+use crate::models::{
+    Account, AccountsResponse, CategoriesResponse, Category, CreateMoneyResponse, CreatedMoney, CurrenciesResponse,
+    Currency, Genre, GenresResponse, Mode, Money, MoneyQuery, MoneyResponse, NewIncome, NewMoney, NewPayment,
+    NewTransfer,
+};
+use crate::oauth1a::{OAuth1, SignedAuth, redact_auth_header, redact_signature_base_string};
+use crate::transport::{ReqwestTransport, Transport};
 
 use std::collections::HashMap;
 use std::io::stdin;
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
-use reqwest::{header, Request, RequestBuilder, Client, Method, Url};
-use serde;
-use serde_json;
+use reqwest::{header, Url};
 
-pub const REQUEST_TOKEN_URL: &str = "https://api.zaim.net/v2/auth/request";
+pub const API_BASE: &str = "https://api.zaim.net/v2";
 pub const AUTH_URL: &str = "https://auth.zaim.net/users/auth";
-pub const ACCESS_TOKEN_URL: &str = "https://api.zaim.net/v2/auth/access";
 
-#[derive(serde::Deserialize, Debug)]
+/// Max number of money records in a page
+const MONEY_PAGE_LIMIT: u32 = 100;
+
+pub const ENV_CONSUMER_KEY: &str = "ZAIM_CONSUMER_KEY";
+pub const ENV_CONSUMER_SECRET: &str = "ZAIM_CONSUMER_SECRET";
+pub const ENV_ACCESS_TOKEN: &str = "ZAIM_ACCESS_TOKEN";
+pub const ENV_ACCESS_TOKEN_SECRET: &str = "ZAIM_ACCESS_TOKEN_SECRET";
+
+/// Read a pair of environment variables.
+/// Return None if neither is set, and error if only one of them is set.
+/// Empty values are treated as unset.
+fn read_env_pair(key1: &str, key2: &str) -> Result<Option<(String, String)>, ZaimApiError> {
+    let v1 = std::env::var(key1).ok().filter(|v| !v.is_empty());
+    let v2 = std::env::var(key2).ok().filter(|v| !v.is_empty());
+
+    match (v1, v2) {
+        (Some(v1), Some(v2)) => Ok(Some((v1, v2))),
+        (None, None) => Ok(None),
+        (Some(_), None) => Err(ZaimApiError::new(format!("{} is set but {} is not", key1, key2))),
+        (None, Some(_)) => Err(ZaimApiError::new(format!("{} is set but {} is not", key2, key1))),
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
 pub struct ConsumerInfo {
     pub consumer_key: String,
     pub consumer_secret: String,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug)]
+impl ConsumerInfo {
+    /// Read ZAIM_CONSUMER_KEY and ZAIM_CONSUMER_SECRET.
+    pub fn from_env() -> Result<Option<Self>, ZaimApiError> {
+        Ok(read_env_pair(ENV_CONSUMER_KEY, ENV_CONSUMER_SECRET)?
+            .map(|(consumer_key, consumer_secret)| Self { consumer_key, consumer_secret }))
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub struct AccessTokens {
     pub access_token: String,
     pub access_token_secret: String,
 }
 
 impl AccessTokens {
+    /// Read ZAIM_ACCESS_TOKEN and ZAIM_ACCESS_TOKEN_SECRET.
+    pub fn from_env() -> Result<Option<Self>, ZaimApiError> {
+        Ok(read_env_pair(ENV_ACCESS_TOKEN, ENV_ACCESS_TOKEN_SECRET)?
+            .map(|(access_token, access_token_secret)| Self { access_token, access_token_secret }))
+    }
+
     fn new_uninit() -> Self {
         Self {
             access_token: String::new(),
@@ -40,6 +84,13 @@ pub struct ZaimApi {
     pub oauth1: OAuth1,
     pub consumer_info: ConsumerInfo,
     pub access_tokens: Option<AccessTokens>,
+    pub api_base: String,
+    pub retry: RetryPolicy,
+    pub dry_run: Option<DryRun>,
+    /// Cache of categories, genres, accounts and currencies. None to always fetch them
+    pub cache: Option<MasterCache>,
+    /// Transport to send requests (e.g. reqwest, cassette or fake for tests)
+    pub transport: Arc<dyn Transport>,
 }
 
 impl ZaimApi {
@@ -49,7 +100,70 @@ impl ZaimApi {
         access_tokens: Option<AccessTokens>
     ) -> Self {
 
-        Self { oauth1, consumer_info, access_tokens }
+        Self {
+            oauth1,
+            consumer_info,
+            access_tokens,
+            api_base: API_BASE.to_string(),
+            retry: RetryPolicy::default(),
+            dry_run: None,
+            cache: None,
+            transport: Arc::new(ReqwestTransport::default()),
+        }
+    }
+
+    /// Build with out-of-band OAuth1 for the given consumer.
+    pub fn with_consumer_info(
+        consumer_info: ConsumerInfo,
+        access_tokens: Option<AccessTokens>
+    ) -> Self {
+        Self::with_api_base(consumer_info, access_tokens, API_BASE)
+    }
+
+    /// Build with out-of-band OAuth1 for the given consumer.
+    /// Token endpoints are also located under `api_base`.
+    pub fn with_api_base(
+        consumer_info: ConsumerInfo,
+        access_tokens: Option<AccessTokens>,
+        api_base: &str
+    ) -> Self {
+        let api_base = api_base.trim_end_matches('/');
+        let oauth1 = OAuth1::new(
+            consumer_info.consumer_key.clone(),
+            consumer_info.consumer_secret.clone(),
+            String::from("oob"),
+            format!("{}/auth/request", api_base),
+            AUTH_URL.to_string(),
+            format!("{}/auth/access", api_base)
+        );
+
+        let mut api = Self::new(oauth1, consumer_info, access_tokens);
+        api.api_base = api_base.to_string();
+        api
+    }
+
+    /// Resolve path relative to `api_base` (e.g. "/home/money").
+    /// Absolute urls are returned as is.
+    pub fn url(&self, path: &str) -> String {
+        if path.contains("://") {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.api_base, path.trim_start_matches('/'))
+        }
+    }
+
+    /// Build from ZAIM_CONSUMER_KEY, ZAIM_CONSUMER_SECRET,
+    /// ZAIM_ACCESS_TOKEN and ZAIM_ACCESS_TOKEN_SECRET.
+    /// Access tokens are optional, consumer information is required.
+    pub fn from_env() -> Result<Self, ZaimApiError> {
+        let consumer_info = match ConsumerInfo::from_env()? {
+            Some(c) => c,
+            None => return Err(ZaimApiError::new(format!(
+                "{} and {} are not set", ENV_CONSUMER_KEY, ENV_CONSUMER_SECRET
+            ))),
+        };
+
+        Ok(Self::with_consumer_info(consumer_info, AccessTokens::from_env()?))
     }
 
     pub fn authenticate(&mut self) -> Result<(), ZaimApiError> {
@@ -57,7 +171,7 @@ impl ZaimApi {
             return Ok(());
         }
 
-        match authenticate(&self.oauth1) {
+        match authenticate(&self.oauth1, self.transport.as_ref()) {
             Ok(tokens) => self.access_tokens = Some(tokens),
             Err(e) => return Err(e)
         }
@@ -65,16 +179,60 @@ impl ZaimApi {
         Ok(())
     }
 
+    /// Authenticate with a custom way to get verifier code.
+    /// Existing access tokens are replaced.
+    pub fn authenticate_with<F>(&mut self, get_verifier: F) -> Result<(), ZaimApiError>
+    where
+        F: FnOnce(&str, &UnauthorizedRequestToken) -> Result<String, ZaimApiError>
+    {
+        self.access_tokens = Some(authenticate_with(&self.oauth1, self.transport.as_ref(), get_verifier)?);
+        Ok(())
+    }
+
     pub fn is_authenticated(&self) -> bool {
         self.access_tokens.is_some()
     }
 
-    fn request_rest_api(
+    /// Sign a request without sending it
+    pub fn preview_rest_api(
+        &self,
+        url: &str,
+        protocol: &str,
+        queries: Option<&HashMap<String, String>>,
+        show_secrets: bool
+    ) -> Result<RequestPreview, ZaimApiError> {
+        let _access_tokens = match self.access_tokens.as_ref() {
+            Some(t) => t,
+            None => return Err(ZaimApiError::new(String::from("User authentication not done"))),
+        };
+        let signed = match self.oauth1.sign_for_rest_api(
+            url,
+            protocol,
+            &_access_tokens.access_token,
+            &_access_tokens.access_token_secret,
+            queries
+        ) {
+            Ok(s) => s,
+            Err(e) => return Err(ZaimApiError::new(format!("Failed to generate auth: {}", e))),
+        };
+        let prepared = PreparedRequest::new(url, protocol, &signed.header, queries)?;
+
+        Ok(RequestPreview::new(&prepared, &signed, show_secrets))
+    }
+
+    /// In dry run, requests other than GET are printed instead of being sent, and
+    /// `DRY_RUN_RESPONSE` is returned. GET requests are sent to read data as usual
+    pub fn request_rest_api(
         &self,
         url: &str,
         protocol: &str,
         queries: Option<&HashMap<String, String>>
     ) -> Result<String, ZaimApiError> {
+        if let Some(dry_run) = self.dry_run.filter(|_| !protocol.eq_ignore_ascii_case("GET")) {
+            self.preview_rest_api(url, protocol, queries, dry_run.show_secrets)?.print(dry_run.json);
+            return Ok(String::from(DRY_RUN_RESPONSE));
+        }
+
         let _access_tokens = self.access_tokens.as_ref().unwrap();
         request_rest_api(
             &self.oauth1,
@@ -82,34 +240,251 @@ impl ZaimApi {
             protocol,
             &_access_tokens.access_token,
             &_access_tokens.access_token_secret,
-            queries
+            queries,
+            &self.retry,
+            self.transport.as_ref()
         )
     }
 
-    pub fn rest_api_verify_user(&self) -> Result<(), ZaimApiError> {
-        if self.access_tokens.is_some() {
-            let _access_tokens = &self.access_tokens.as_ref().unwrap();
-            return rest_api_verify_user(
-                &self.oauth1,
-                &_access_tokens.access_token,
-                &_access_tokens.access_token_secret,
-            )
-        } else {
-            return Err(ZaimApiError::new(
-                String::from("User authentication not done")
-            ));
+    /// Request api and parse the response as json
+    pub fn get_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        queries: Option<&HashMap<String, String>>
+    ) -> Result<T, ZaimApiError> {
+        self.request_json(path, "GET", queries)
+    }
+
+    /// Post to api and parse the response as json
+    pub fn post_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        queries: &HashMap<String, String>
+    ) -> Result<T, ZaimApiError> {
+        self.request_json(path, "POST", Some(queries))
+    }
+
+    /// Put to api and parse the response as json
+    pub fn put_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        queries: &HashMap<String, String>
+    ) -> Result<T, ZaimApiError> {
+        self.request_json(path, "PUT", Some(queries))
+    }
+
+    fn request_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        protocol: &str,
+        queries: Option<&HashMap<String, String>>
+    ) -> Result<T, ZaimApiError> {
+        if !self.is_authenticated() {
+            return Err(ZaimApiError::new(String::from("User authentication not done")));
+        }
+
+        let http_res = self.request_rest_api(&self.url(path), protocol, queries)?;
+        match serde_json::from_str(&http_res) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(ZaimApiError::new(format!("Failed to parse response of {}: {}", path, e))),
         }
     }
+
+    /// Verify access tokens and get the user profile
+    pub fn verify_user(&self) -> Result<User, ZaimApiError> {
+        let res: VerifyUserResponse = self.get_json("/home/user/verify", None)?;
+        Ok(res.me)
+    }
+
+    /// Get all money records matching the query, following pages
+    pub fn money(&self, query: &MoneyQuery) -> Result<Vec<Money>, ZaimApiError> {
+        let mut money = Vec::new();
+        for page in 1.. {
+            let res: MoneyResponse = self.get_json("/home/money", Some(&query.to_queries(page, MONEY_PAGE_LIMIT)))?;
+            let n = res.money.len();
+            money.extend(res.money);
+            if n < MONEY_PAGE_LIMIT as usize {
+                break;
+            }
+        }
+        Ok(money)
+    }
+
+    /// Cached list of the kind, or fetched one which is saved into the cache
+    fn cached<T, F>(&self, kind: &str, fetch: F) -> Result<Vec<T>, ZaimApiError>
+    where
+        T: serde::de::DeserializeOwned + serde::Serialize,
+        F: FnOnce() -> Result<Vec<T>, ZaimApiError>,
+    {
+        let cache = match &self.cache {
+            Some(c) => c,
+            None => return fetch(),
+        };
+        if let Some(items) = cache.get(kind) {
+            tracing::debug!(kind, "using cached master data");
+            return Ok(items);
+        }
+        let items = fetch()?;
+        if let Err(e) = cache.put(kind, &items) {
+            tracing::warn!(kind, error = %e, "failed to save master data into cache");
+        }
+        Ok(items)
+    }
+
+    pub fn categories(&self) -> Result<Vec<Category>, ZaimApiError> {
+        self.cached("categories", || {
+            let res: CategoriesResponse = self.get_json("/home/category", Some(&mapping_query()))?;
+            Ok(res.categories)
+        })
+    }
+
+    pub fn genres(&self) -> Result<Vec<Genre>, ZaimApiError> {
+        self.cached("genres", || {
+            let res: GenresResponse = self.get_json("/home/genre", Some(&mapping_query()))?;
+            Ok(res.genres)
+        })
+    }
+
+    pub fn accounts(&self) -> Result<Vec<Account>, ZaimApiError> {
+        self.cached("accounts", || {
+            let res: AccountsResponse = self.get_json("/home/account", Some(&mapping_query()))?;
+            Ok(res.accounts)
+        })
+    }
+
+    pub fn currencies(&self) -> Result<Vec<Currency>, ZaimApiError> {
+        self.cached("currencies", || {
+            let res: CurrenciesResponse = self.get_json("/currency", None)?;
+            Ok(res.currencies)
+        })
+    }
+
+    pub fn create_payment(&self, payment: &NewPayment) -> Result<CreatedMoney, ZaimApiError> {
+        let res: CreateMoneyResponse = self.post_json("/home/money/payment", &payment.to_queries())?;
+        Ok(res.money)
+    }
+
+    pub fn create_income(&self, income: &NewIncome) -> Result<CreatedMoney, ZaimApiError> {
+        let res: CreateMoneyResponse = self.post_json("/home/money/income", &income.to_queries())?;
+        Ok(res.money)
+    }
+
+    pub fn create_transfer(&self, transfer: &NewTransfer) -> Result<CreatedMoney, ZaimApiError> {
+        let res: CreateMoneyResponse = self.post_json("/home/money/transfer", &transfer.to_queries())?;
+        Ok(res.money)
+    }
+
+    pub fn create_money(&self, money: &NewMoney) -> Result<CreatedMoney, ZaimApiError> {
+        let path = format!("/home/money/{}", money.mode());
+        let res: CreateMoneyResponse = self.post_json(&path, &money.to_queries())?;
+        Ok(res.money)
+    }
+
+    pub fn delete_money(&self, mode: Mode, id: u64) -> Result<(), ZaimApiError> {
+        let path = format!("/home/money/{}/{}", mode, id);
+        let _: serde_json::Value = self.request_json(&path, "DELETE", None)?;
+        Ok(())
+    }
+
+    /// Save category, genre, accounts, amount, date and texts of the money record
+    pub fn update_money(&self, money: &Money) -> Result<CreatedMoney, ZaimApiError> {
+        let path = format!("/home/money/{}/{}", money.mode, money.id);
+        let res: CreateMoneyResponse = self.put_json(&path, &money.to_update_queries())?;
+        Ok(res.money)
+    }
+}
+
+fn mapping_query() -> HashMap<String, String> {
+    HashMap::from([(String::from("mapping"), String::from("1"))])
+}
+
+/// User profile returned by /v2/home/user/verify
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct User {
+    pub id: u64,
+    #[serde(default)]
+    pub login: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub currency_code: Option<String>,
+    #[serde(default)]
+    pub input_count: Option<u64>,
+    #[serde(default)]
+    pub day_count: Option<u64>,
+    #[serde(default)]
+    pub repeat_count: Option<u64>,
+    #[serde(default)]
+    pub day: Option<u64>,
+    #[serde(default)]
+    pub week: Option<u64>,
+    #[serde(default)]
+    pub month: Option<u64>,
+    #[serde(default)]
+    pub profile_image_url: Option<String>,
+    #[serde(default)]
+    pub cover_image_url: Option<String>,
+    #[serde(default)]
+    pub profile_modified: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct VerifyUserResponse {
+    me: User,
 }
 
 #[derive(Debug)]
 pub struct ZaimApiError {
     description: String,
+    status: Option<u16>,
+    transient: bool,
 }
 
 impl ZaimApiError {
-    fn new(description: String) -> Self {
-        Self { description }
+    pub fn new(description: String) -> Self {
+        Self { description, status: None, transient: false }
+    }
+
+    fn with_status(description: String, status: u16) -> Self {
+        Self { description, status: Some(status), transient: false }
+    }
+
+    /// Failure worth retrying for GET (e.g. connection refused or timeout)
+    pub fn transient(description: String) -> Self {
+        Self { description, status: None, transient: true }
+    }
+
+    /// 429 is always retryable because the request is not processed.
+    /// Server errors and connection failures are retryable only for GET.
+    fn is_retryable(&self, protocol: &str) -> bool {
+        match self.status {
+            Some(429) => true,
+            Some(s) => protocol == "GET" && s >= 500,
+            None => protocol == "GET" && self.transient,
+        }
+    }
+}
+
+/// Retry policy of rest api requests
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct RetryPolicy {
+    /// Number of attempts including the first one
+    pub max_attempts: u32,
+    /// Wait before the first retry. It is doubled for each retry.
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, backoff_ms: 500 }
+    }
+}
+
+impl RetryPolicy {
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        std::time::Duration::from_millis(self.backoff_ms.saturating_mul(factor))
     }
 }
 
@@ -142,98 +517,192 @@ impl UnauthorizedRequestToken {
     }
 }
 
-fn _gen_request_for_get(
-    url: &str,
-    headers: header::HeaderMap,
-    queries: Option<&HashMap<String, String>>
-) -> Result<RequestBuilder> {
-    let mut request_builder = RequestBuilder::from_parts(
-        Client::new(), Request::new(Method::GET, Url::parse(url)?)
-    );
-    request_builder = request_builder.headers(headers);
+/// HTTP request ready to be sent
+#[derive(Debug, Clone)]
+pub struct PreparedRequest {
+    pub method: String,
+    /// Final url including query string
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
 
-    if queries.is_some() {
-        request_builder = request_builder.query(queries.unwrap());
+impl PreparedRequest {
+    /// Query parameters are put into url for GET and DELETE, and into form body for POST and PUT,
+    /// so that they are part of the signature (RFC 5849 3.4.1.3.1).
+    pub fn new(
+        url: &str,
+        protocol: &str,
+        auth: &str,
+        queries: Option<&HashMap<String, String>>
+    ) -> Result<Self, ZaimApiError> {
+        let mut url = match Url::parse(url) {
+            Ok(u) => u,
+            Err(e) => return Err(ZaimApiError::new(format!("Invalid url: {}", e))),
+        };
+        let mut body = None;
+
+        match (protocol, queries) {
+            ("GET" | "DELETE", Some(q)) => {
+                let mut keys: Vec<&String> = q.keys().collect();
+                keys.sort();
+                url.query_pairs_mut().extend_pairs(keys.into_iter().map(|k| (k, &q[k])));
+            },
+            ("POST" | "PUT", Some(q)) => {
+                // Sorted for reproducible body (e.g. cassettes)
+                body = Some(helper::form_urlencode(q));
+            },
+            ("GET" | "POST" | "PUT" | "DELETE", None) => (),
+            _ => return Err(ZaimApiError::new(format!("Unexpected protocol: {}", protocol))),
+        }
+
+        let mut headers = vec![(header::AUTHORIZATION.to_string(), auth.to_string())];
+        if body.is_some() {
+            headers.push((header::CONTENT_TYPE.to_string(), String::from("application/x-www-form-urlencoded")));
+        }
+
+        Ok(Self {
+            method: protocol.to_string(),
+            url: url.to_string(),
+            headers,
+            body,
+        })
     }
 
-    Ok(request_builder)
+    /// Equivalent curl command line
+    pub fn to_curl(&self) -> String {
+        let mut cmd = format!("curl -X {} {}", self.method, shell_quote(&self.url));
+        for (k, v) in self.headers.iter() {
+            cmd.push_str(format!(" -H {}", shell_quote(&format!("{}: {}", k, v))).as_str());
+        }
+        if let Some(body) = &self.body {
+            cmd.push_str(format!(" --data-raw {}", shell_quote(body)).as_str());
+        }
+        cmd
+    }
 }
 
-fn _gen_request_for_post(
-    url: &str,
-    headers: header::HeaderMap,
-    queries: Option<&HashMap<String, String>>
-) -> Result<RequestBuilder> {
-    let mut request_builder = RequestBuilder::from_parts(
-        Client::new(), Request::new(Method::POST, Url::parse(url)?)
-    );
-    request_builder = request_builder.headers(headers);
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
 
-    if queries.is_some() {
-        // NOTE: unwrap is safety?
-        request_builder = request_builder.body(
-            serde_json::to_string(queries.unwrap()).unwrap()
-        );
+/// Signed request which is not sent, for diagnosing signature problems
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct RequestPreview {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub signature_base_string: String,
+    pub curl: String,
+}
+
+impl RequestPreview {
+    pub fn new(request: &PreparedRequest, signed: &SignedAuth, show_secrets: bool) -> Self {
+        let mut request = request.clone();
+        let mut signature_base_string = signed.signature_base_string.clone();
+        if !show_secrets {
+            for (k, v) in request.headers.iter_mut() {
+                if k == header::AUTHORIZATION.as_str() {
+                    *v = redact_auth_header(v);
+                }
+            }
+            signature_base_string = redact_signature_base_string(&signature_base_string);
+        }
+
+        Self {
+            curl: request.to_curl(),
+            method: request.method,
+            url: request.url,
+            headers: request.headers,
+            body: request.body,
+            signature_base_string,
+        }
+    }
+
+    /// Print into stdout as json or text
+    pub fn print(&self, json: bool) {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self).unwrap());
+        } else {
+            println!("{}", self);
+        }
+    }
+}
+
+impl std::fmt::Display for RequestPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Method: {}", self.method)?;
+        writeln!(f, "URL: {}", self.url)?;
+        writeln!(f, "Headers:")?;
+        for (k, v) in self.headers.iter() {
+            writeln!(f, "  {}: {}", k, v)?;
+        }
+        writeln!(f, "Body:\n  {}", self.body.as_deref().unwrap_or("(none)"))?;
+        writeln!(f, "Signature base string:\n  {}", self.signature_base_string)?;
+        writeln!(f, "curl:\n  {}", self.curl)
     }
+}
+
+/// Response of requests not sent in dry run, parsed as a money record of id 0
+pub const DRY_RUN_RESPONSE: &str = r#"{"money":{"id":0}}"#;
 
-    Ok(request_builder)
+/// Options of dry run. Requests other than GET are printed into stdout instead of being sent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DryRun {
+    pub show_secrets: bool,
+    pub json: bool,
 }
 
-#[tokio::main]
-async fn request(
+fn request(
     url: &str,
     protocol: &str,
     auth: &str,
-    queries: Option<&HashMap<String, String>>
+    queries: Option<&HashMap<String, String>>,
+    transport: &dyn Transport
 ) -> Result<String, ZaimApiError> {
-    let mut headers = header::HeaderMap::new();
-    let auth_header_value;
-    match header::HeaderValue::from_str(auth) {
-        Ok(v) => auth_header_value = v,
-        Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
-    }
-    headers.insert(header::AUTHORIZATION, auth_header_value);
-
-    let request_builder;
-    if protocol == "GET" {
-        request_builder = match _gen_request_for_get(url, headers, queries){
-            Ok(r) => r,
-            Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
-        }
-    } else if protocol == "POST" {
-        request_builder = match _gen_request_for_post(url, headers, queries){
-            Ok(r) => r,
-            Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
-        }
-    } else {
-        return Err(ZaimApiError::new(format!("Unexpected protocol: {}", protocol)));
-    }
+    let prepared = PreparedRequest::new(url, protocol, auth, queries)?;
+    let span = tracing::debug_span!("http", method = %prepared.method, url = %prepared.url);
+    let _enter = span.enter();
+    tracing::trace!(authorization = %redact_auth_header(auth), "sending request");
+
+    let started = Instant::now();
+    let http_res = match transport.send(&prepared) {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::debug!(latency_ms = started.elapsed().as_millis() as u64, error = %e, "request failed");
+            return Err(e);
+        },
+    };
+    tracing::debug!(
+        status = http_res.status,
+        latency_ms = started.elapsed().as_millis() as u64,
+        size = http_res.body.len(),
+        "response"
+    );
 
-    let ret = request_builder.send().await;
-    
-    let http_res;
-    match ret {
-        Ok(r) => http_res = r,
-        Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
-    }
-    let status = http_res.status();
-    if status == reqwest::StatusCode::OK {
-        match http_res.text().await {
-            Ok(data) => return Ok(data),
-            Err(e) => return Err(ZaimApiError::new(format!("reqwest Error: {}", e))),
-        }
+    response_result(http_res.status, http_res.body)
+}
+
+fn response_result(status: u16, body: String) -> Result<String, ZaimApiError> {
+    if status == 200 {
+        Ok(body)
     } else {
-        return Err(ZaimApiError::new(format!("reqwest Error: {}", status)));
+        let reason = reqwest::StatusCode::from_u16(status)
+            .map(|s| s.to_string())
+            .unwrap_or_else(|_| status.to_string());
+        Err(ZaimApiError::with_status(format!("HTTP Error: {}", reason), status))
     }
 }
 
 pub fn request_request_token(
     url: &str,
-    auth: &str
+    auth: &str,
+    transport: &dyn Transport
 ) -> Result<UnauthorizedRequestToken, ZaimApiError> {
     let mut response = UnauthorizedRequestToken::new_uninit();
     let mut flags: u32 = 0;
-    let http_res = request(url, "POST", auth, None);
+    let http_res = request(url, "POST", auth, None, transport);
 
     if let Err(e) = http_res {
         return Err(ZaimApiError::new(format!("Failed http request: {}", e)));
@@ -244,7 +713,7 @@ pub fn request_request_token(
     for token in tokens {
         let mut key_value: Vec<String> = token.split("=").map(String::from).collect();
         if key_value.len() != 2 {
-            return Err(ZaimApiError::new(format!("Unexpected response format")));
+            return Err(ZaimApiError::new(String::from("Unexpected response format")));
         }
 
         let v = key_value.pop().unwrap();
@@ -266,7 +735,7 @@ pub fn request_request_token(
             }
             flags |= 4;
         } else {
-            eprintln!("Warn: Unknown key: {}", k);
+            tracing::warn!("Unknown key in token response: {}", k);
         }
     }       
 
@@ -279,11 +748,12 @@ pub fn request_request_token(
 
 pub fn request_access_token(
     url: &str,
-    auth: &str
+    auth: &str,
+    transport: &dyn Transport
 ) -> Result<AccessTokens, ZaimApiError> {
     let mut response = AccessTokens::new_uninit();
     let mut flags: u32 = 0;
-    let http_res = request(url, "POST", auth, None);
+    let http_res = request(url, "POST", auth, None, transport);
 
     if let Err(e) = http_res {
         return Err(ZaimApiError::new(format!("Failed http request: {}", e)));
@@ -294,7 +764,7 @@ pub fn request_access_token(
     for token in tokens {
         let mut key_value: Vec<String> = token.split("=").map(String::from).collect();
         if key_value.len() != 2 {
-            return Err(ZaimApiError::new(format!("Error: Unexpected response format")));
+            return Err(ZaimApiError::new(String::from("Error: Unexpected response format")));
         }
 
         let v = key_value.pop().unwrap();
@@ -307,7 +777,7 @@ pub fn request_access_token(
             response.access_token_secret = v;
             flags |= 2;
         } else {
-            eprintln!("Warn: Unknown key: {}", k);
+            tracing::warn!("Unknown key in token response: {}", k);
         }
     }
 
@@ -318,7 +788,31 @@ pub fn request_access_token(
     Ok(response)
 }
 
-pub fn authenticate(oauth1: &OAuth1) -> Result<AccessTokens, ZaimApiError> {
+pub fn authenticate(oauth1: &OAuth1, transport: &dyn Transport) -> Result<AccessTokens, ZaimApiError> {
+    authenticate_with(oauth1, transport, |auth_link, _| {
+        eprintln!("Please access following url by your web browser.\n  {}", auth_link);
+        eprintln!("When you can get verifier code, input it.");
+
+        let mut user_input = String::new();
+        if let Err(e) = stdin().read_line(&mut user_input) {
+            return Err(ZaimApiError::new(
+                format!("Failed to read user input\n{}", e)
+            ));
+        }
+        Ok(user_input.trim().to_string())
+    })
+}
+
+/// Authenticate user. `get_verifier` receives the user authentication link and
+/// the request token, and returns verifier code.
+pub fn authenticate_with<F>(
+    oauth1: &OAuth1,
+    transport: &dyn Transport,
+    get_verifier: F
+) -> Result<AccessTokens, ZaimApiError>
+where
+    F: FnOnce(&str, &UnauthorizedRequestToken) -> Result<String, ZaimApiError>
+{
     let auth_for_request_token = oauth1.gen_auth_for_request_token();
     if let Err(e) = auth_for_request_token {
         return Err(ZaimApiError::new(
@@ -328,8 +822,9 @@ pub fn authenticate(oauth1: &OAuth1) -> Result<AccessTokens, ZaimApiError> {
     let auth_for_request_token = auth_for_request_token.unwrap();
 
     let request_tokens = request_request_token(
-        REQUEST_TOKEN_URL,
-        auth_for_request_token.as_str()
+        oauth1.request_token_url.as_str(),
+        auth_for_request_token.as_str(),
+        transport
     );
 
     if let Err(e) = request_tokens {
@@ -339,17 +834,10 @@ pub fn authenticate(oauth1: &OAuth1) -> Result<AccessTokens, ZaimApiError> {
     }
     let request_tokens = request_tokens.unwrap();
 
-    println!("Please access following url by your web browser.\n  {}",
-            oauth1.gen_user_auth_link(request_tokens.request_token.as_str()));
-    println!("When you can get verifier code, input it.");
-
-    let mut user_input = String::new();
-    if let Err(e) = stdin().read_line(&mut user_input) {
-        return Err(ZaimApiError::new(
-            format!("Failed to read user input\n{}", e)
-        ));
-    }
-    let verifier_code = user_input.trim().to_string();
+    let verifier_code = get_verifier(
+        oauth1.gen_user_auth_link(request_tokens.request_token.as_str()).as_str(),
+        &request_tokens
+    )?;
 
     let auth_for_access_token = oauth1.gen_auth_for_access_token(
         &request_tokens.request_token,
@@ -364,8 +852,9 @@ pub fn authenticate(oauth1: &OAuth1) -> Result<AccessTokens, ZaimApiError> {
     let auth_for_access_token = auth_for_access_token.unwrap();
 
     let access_tokens = request_access_token(
-        ACCESS_TOKEN_URL,
-        auth_for_access_token.as_str()
+        oauth1.access_token_url.as_str(),
+        auth_for_access_token.as_str(),
+        transport
     );
 
     if let Err(e) = access_tokens {
@@ -378,57 +867,48 @@ pub fn authenticate(oauth1: &OAuth1) -> Result<AccessTokens, ZaimApiError> {
     Ok(access_tokens)
 }
 
+/// Request rest api. The request is signed again for each retry.
+#[allow(clippy::too_many_arguments)]
 pub fn request_rest_api(
     oauth1: &OAuth1,
     url: &str,
     protocol: &str,
     access_token: &str,
     access_token_secret: &str,
-    queries: Option<&HashMap<String, String>>
+    queries: Option<&HashMap<String, String>>,
+    retry: &RetryPolicy,
+    transport: &dyn Transport
 ) -> Result<String, ZaimApiError> {
-    let auth = oauth1.gen_auth_for_rest_api(
-        url,
-        protocol,
-        access_token,
-        access_token_secret,
-        queries
-    );
-
-    if let Err(e) = auth {
-        return Err(ZaimApiError::new(format!("Failed to generate auth: {}", e)));
-    }
-    let auth = auth.unwrap();
-
-    request(url, protocol, &auth, queries)
-}
-
-// NOTE: This is debug code
-pub fn rest_api_verify_user(
-    oauth1: &OAuth1,
-    access_token: &str,
-    access_token_secret: &str
-) -> Result<(), ZaimApiError> {
-    let url = "https://api.zaim.net/v2/home/user/verify";
-    let protocol = "GET";
+    let mut attempt = 1;
+    loop {
+        let _span = tracing::debug_span!("rest_api", retry = attempt - 1).entered();
+        let auth = oauth1.gen_auth_for_rest_api(
+            url,
+            protocol,
+            access_token,
+            access_token_secret,
+            queries
+        );
 
-    let http_res = request_rest_api(
-        oauth1,
-        url,
-        protocol,
-        access_token,
-        access_token_secret,
-        None
-    );
-    if let Err(e) = http_res {
-        return Err(ZaimApiError::new(format!("Failed http request: {}", e)));
+        if let Err(e) = auth {
+            return Err(ZaimApiError::new(format!("Failed to generate auth: {}", e)));
+        }
+        let auth = auth.unwrap();
+
+        match request(url, protocol, &auth, queries, transport) {
+            Err(e) if attempt < retry.max_attempts && e.is_retryable(protocol) => {
+                let backoff = retry.backoff(attempt);
+                tracing::warn!(attempt, backoff_ms = backoff.as_millis() as u64, error = %e, "retrying request");
+                std::thread::sleep(backoff);
+                attempt += 1;
+            },
+            ret => return ret,
+        }
     }
-    let http_res = http_res.unwrap();
-    println!("Response:\n{}", http_res);
-
-    Ok(())
 }
 
 // NOTE: This is debug code
+#[allow(dead_code)]
 pub fn rest_api_fetch_transactions(
     oauth1: &OAuth1,
     access_token: &str,
@@ -470,7 +950,9 @@ pub fn rest_api_fetch_transactions(
         protocol,
         access_token,
         access_token_secret,
-        Some(&queries)
+        Some(&queries),
+        &RetryPolicy::default(),
+        &ReqwestTransport::default()
     );
     if let Err(e) = http_res {
         return Err(ZaimApiError::new(format!("Failed http request: {}", e)));
@@ -481,3 +963,184 @@ pub fn rest_api_fetch_transactions(
     Ok(())
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessTokens, ConsumerInfo, PreparedRequest, RetryPolicy, ZaimApi};
+    use crate::master_cache::MasterCache;
+    use crate::models::{Mode, Money, NewPayment};
+    use crate::transport::{FakeTransport, HttpResponse};
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn prepare_api(transport: Arc<FakeTransport>) -> ZaimApi {
+        let mut api = ZaimApi::with_consumer_info(
+            ConsumerInfo { consumer_key: String::from("key"), consumer_secret: String::from("secret") },
+            Some(AccessTokens { access_token: String::from("token"), access_token_secret: String::from("secret") })
+        );
+        api.retry = RetryPolicy { max_attempts: 3, backoff_ms: 0 };
+        api.transport = transport;
+        api
+    }
+
+    #[test]
+    fn test_prepared_request_get() {
+        let mut queries = HashMap::new();
+        queries.insert(String::from("page"), String::from("1"));
+        queries.insert(String::from("mode"), String::from("payment"));
+        let prepared = PreparedRequest::new(
+            "https://api.zaim.net/v2/home/money", "GET", "OAuth x=\"y\"", Some(&queries)
+        ).unwrap();
+
+        assert_eq!(prepared.url, "https://api.zaim.net/v2/home/money?mode=payment&page=1");
+        assert!(prepared.body.is_none());
+        assert_eq!(
+            prepared.to_curl(),
+            "curl -X GET 'https://api.zaim.net/v2/home/money?mode=payment&page=1' -H 'authorization: OAuth x=\"y\"'"
+        );
+    }
+
+    #[test]
+    fn test_prepared_request_post() {
+        let mut queries = HashMap::new();
+        queries.insert(String::from("place"), String::from("Joe's"));
+        let prepared = PreparedRequest::new(
+            "https://api.zaim.net/v2/home/money/payment", "POST", "OAuth", Some(&queries)
+        ).unwrap();
+
+        assert_eq!(prepared.body.as_deref(), Some("place=Joe%27s"));
+        assert_eq!(
+            prepared.headers[1],
+            (String::from("content-type"), String::from("application/x-www-form-urlencoded"))
+        );
+        assert!(prepared.to_curl().ends_with("--data-raw 'place=Joe%27s'"));
+        assert!(PreparedRequest::new("https://api.zaim.net/v2", "PATCH", "OAuth", None).is_err());
+    }
+
+    #[test]
+    fn test_form_body_signed() {
+        let api = prepare_api(Arc::new(FakeTransport::new()));
+        let mut queries = HashMap::new();
+        queries.insert(String::from("place"), String::from("Joe's Cafe 渋谷"));
+        queries.insert(String::from("amount"), String::from("480"));
+        let preview = api.preview_rest_api(
+            "https://api.zaim.net/v2/home/money/payment", "POST", Some(&queries), true
+        ).unwrap();
+
+        let body = preview.body.unwrap();
+        assert_eq!(body, "amount=480&place=Joe%27s%20Cafe%20%E6%B8%8B%E8%B0%B7");
+        // Each pair of the body appears in the base string, encoded once more
+        for pair in body.split('&') {
+            assert!(preview.signature_base_string.contains(&crate::helper::percent_encode(pair)), "{}", pair);
+        }
+    }
+
+    #[test]
+    fn test_request_rest_api_retry() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(503, ""));
+        transport.push_response(HttpResponse::new(200, "{}"));
+        let api = prepare_api(transport.clone());
+
+        assert_eq!(api.request_rest_api(&api.url("/home/money"), "GET", None).unwrap(), "{}");
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        // Signed again for retry
+        assert_ne!(requests[0].headers, requests[1].headers);
+    }
+
+    #[test]
+    fn test_create_payment() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(200, r#"{"money": {"id": 12, "modified": "2026-01-05 10:00:00"}}"#));
+        let api = prepare_api(transport.clone());
+
+        let created = api.create_payment(&NewPayment {
+            date: "2026-01-05".parse().unwrap(),
+            amount: 480,
+            category_id: 101,
+            genre_id: 10102,
+            from_account_id: Some(1),
+            name: None,
+            place: Some(String::from("Cafe")),
+            comment: None,
+        }).unwrap();
+        assert_eq!(created.id, 12);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "https://api.zaim.net/v2/home/money/payment");
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some("amount=480&category_id=101&date=2026-01-05&from_account_id=1&genre_id=10102&mapping=1&place=Cafe")
+        );
+    }
+
+    #[test]
+    fn test_update_money() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(200, r#"{"money": {"id": 12}}"#));
+        let api = prepare_api(transport.clone());
+
+        let money: Money = serde_json::from_value(serde_json::json!({
+            "id": 12, "mode": "payment", "date": "2026-01-05", "amount": 480, "category_id": 101, "genre_id": 10102
+        })).unwrap();
+        assert_eq!(api.update_money(&money).unwrap().id, 12);
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].url, "https://api.zaim.net/v2/home/money/payment/12");
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some("amount=480&category_id=101&date=2026-01-05&genre_id=10102&mapping=1")
+        );
+    }
+
+    #[test]
+    fn test_delete_money() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(200, r#"{"money": {"id": 12}}"#));
+        let api = prepare_api(transport.clone());
+
+        api.delete_money(Mode::Payment, 12).unwrap();
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(requests[0].url, "https://api.zaim.net/v2/home/money/payment/12");
+        assert!(requests[0].body.is_none());
+    }
+
+    #[test]
+    fn test_cached_accounts() {
+        let dir = std::env::temp_dir().join(format!("zaim-cli-test-cache-{}", std::process::id()));
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(200, r#"{"accounts": [{"id": 1, "name": "Wallet"}]}"#));
+        transport.push_response(HttpResponse::new(200, r#"{"accounts": [{"id": 2, "name": "Bank"}]}"#));
+        let mut api = prepare_api(transport.clone());
+        api.cache = Some(MasterCache::new(&dir, std::time::Duration::from_secs(60)));
+
+        // The second one is read from the cache
+        assert_eq!(api.accounts().unwrap()[0].name, "Wallet");
+        assert_eq!(api.accounts().unwrap()[0].name, "Wallet");
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(api.cache.as_ref().unwrap().entries()[0].items, 1);
+
+        api.cache.as_mut().unwrap().refresh = true;
+        assert_eq!(api.accounts().unwrap()[0].name, "Bank");
+        api.cache.as_mut().unwrap().refresh = false;
+        assert_eq!(api.accounts().unwrap()[0].name, "Bank");
+        assert_eq!(transport.requests().len(), 2);
+
+        assert_eq!(api.cache.as_ref().unwrap().clear().unwrap(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_request_rest_api_no_retry_for_post() {
+        let transport = Arc::new(FakeTransport::new());
+        transport.push_response(HttpResponse::new(500, ""));
+        let api = prepare_api(transport.clone());
+
+        assert!(api.request_rest_api(&api.url("/home/money/payment"), "POST", None).is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+}